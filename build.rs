@@ -0,0 +1,23 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use std::process::Command;
+
+/// Embed the git revision the crate is built from as `KIFAPWA_GIT_HASH`
+fn main() {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output();
+
+    if let Ok(output) = output {
+        if output.status.success() {
+            let hash = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=KIFAPWA_GIT_HASH={}", hash.trim());
+        }
+    }
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
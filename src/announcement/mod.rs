@@ -0,0 +1,68 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use pulldown_cmark::{
+    html,
+    Options,
+    Parser
+};
+use crate::sanitize::{
+    sanitize_html,
+    SanitizePolicy
+};
+
+/// Render the markdown of an announcement into HTML which is safe to display.
+/// Any raw HTML or link not covered by the [`SanitizePolicy::Rich`] allowlist is removed,
+/// so no script can be injected.
+///
+/// # Arguments
+///
+/// * `markdown` - The markdown source of the announcement
+///
+/// # Returns
+///
+/// * `String` - The sanitized HTML
+///
+/// # Example
+/// ```rust
+/// let html = render_announcement("**Maintenance** <script>alert(1)</script>");
+/// assert_eq!(html, "<p><strong>Maintenance</strong> </p>\n");
+/// ```
+#[wasm_bindgen]
+pub fn render_announcement(markdown: &str) -> String {
+
+    let parser = Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH);
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser);
+
+    sanitize_html(&unsafe_html, SanitizePolicy::Rich)
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn renders_markdown() {
+        assert_eq!(render_announcement("**Maintenance**"), "<p><strong>Maintenance</strong></p>\n");
+    }
+
+    #[test]
+    fn removes_scripts() {
+        let html = render_announcement("Hello <script>alert(1)</script><img src=x onerror=alert(1)>");
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn removes_javascript_links() {
+        let html = render_announcement("[click](javascript:alert(1))");
+        assert!(!html.contains("javascript"));
+    }
+}
@@ -0,0 +1,233 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use chrono::{
+    DateTime,
+    Utc
+};
+
+/// The Calendar collects planned events, e.g. maintenance windows or moderation duties,
+/// and serializes them as iCalendar ([RFC 5545](https://www.rfc-editor.org/rfc/rfc5545)),
+/// so they can be downloaded as `.ics` file or served as subscription by the backend.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Calendar {
+
+    /// The name of the calendar shown by calendar applications
+    name: String,
+
+    /// The events of the calendar
+    events: Vec<CalendarEvent>
+}
+
+/// A single event of a [`Calendar`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CalendarEvent {
+
+    /// The globally unique identifier of the event, stable across exports
+    uid: String,
+
+    /// The title of the event
+    summary: String,
+
+    /// The start in seconds since the unix epoch
+    start: u64,
+
+    /// The end in seconds since the unix epoch
+    end: u64,
+
+    /// The description of the event, if any
+    description: Option<String>
+}
+
+impl Calendar {
+
+    /// The maximum length of a content line in octets, without the line break
+    const LINE_LENGTH: usize = 75;
+
+    /// Serialize the calendar, stamping the events with the given point in time
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The time of the export in seconds since the unix epoch
+    pub fn to_ics_at(&self, now: u64) -> String {
+        let mut lines = vec![
+            String::from("BEGIN:VCALENDAR"),
+            String::from("VERSION:2.0"),
+            String::from("PRODID:-//kifapwa//admin panel//EN"),
+            String::from("CALSCALE:GREGORIAN"),
+            format!("X-WR-CALNAME:{}", escape(&self.name))
+        ];
+        for event in &self.events {
+            lines.push(String::from("BEGIN:VEVENT"));
+            lines.push(format!("UID:{}", escape(&event.uid)));
+            lines.push(format!("DTSTAMP:{}", timestamp(now)));
+            lines.push(format!("DTSTART:{}", timestamp(event.start)));
+            lines.push(format!("DTEND:{}", timestamp(event.end)));
+            lines.push(format!("SUMMARY:{}", escape(&event.summary)));
+            if let Some(description) = &event.description {
+                lines.push(format!("DESCRIPTION:{}", escape(description)));
+            }
+            lines.push(String::from("END:VEVENT"));
+        }
+        lines.push(String::from("END:VCALENDAR"));
+
+        lines.iter()
+            .map(|line| fold(line, Self::LINE_LENGTH))
+            .collect::<Vec<String>>()
+            .join("\r\n") + "\r\n"
+    }
+}
+
+#[wasm_bindgen]
+impl Calendar {
+
+    /// Create an empty calendar
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the calendar shown by calendar applications
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut calendar = Calendar::new(String::from("Maintenance"));
+    /// calendar.add_event(String::from("maintenance-42@kit.edu"), String::from("Database upgrade"), 1700000000, 1700003600, None);
+    /// let ics = calendar.to_ics();
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: String) -> Calendar {
+        Calendar {
+            name,
+            events: Vec::new()
+        }
+    }
+
+    /// Add an event to the calendar
+    ///
+    /// # Arguments
+    ///
+    /// * `uid` - The globally unique identifier of the event, e.g. `<id>@<domain>`
+    /// * `summary` - The title of the event
+    /// * `start` - The start in seconds since the unix epoch
+    /// * `end` - The end in seconds since the unix epoch
+    /// * `description` - The description of the event, if any
+    ///
+    /// # Returns
+    ///
+    /// * `true` - If the event was added
+    /// * `false` - If the event ends before it starts
+    pub fn add_event(&mut self, uid: String, summary: String, start: u64, end: u64, description: Option<String>) -> bool {
+        if end < start {
+            return false;
+        }
+        self.events.push(CalendarEvent {
+            uid,
+            summary,
+            start,
+            end,
+            description
+        });
+        true
+    }
+
+    /// The number of events in the calendar
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether the calendar contains no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Serialize the calendar as iCalendar, to be offered as `text/calendar` download
+    pub fn to_ics(&self) -> String {
+        self.to_ics_at((js_sys::Date::now() / 1000.0) as u64)
+    }
+}
+
+/// Format the given point in time as UTC date-time, e.g. `20231114T221320Z`
+fn timestamp(seconds: u64) -> String {
+    match DateTime::<Utc>::from_timestamp(seconds as i64, 0) {
+        Some(time) => time.format("%Y%m%dT%H%M%SZ").to_string(),
+        None => String::from("99991231T235959Z")
+    }
+}
+
+/// Escape the given text for use as property value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {},
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// Fold the given content line into lines of at most `length` octets,
+/// continuing lines with a leading space and never splitting a character
+fn fold(line: &str, length: usize) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut current = 0;
+    for c in line.chars() {
+        if current + c.len_utf8() > length {
+            folded.push_str("\r\n ");
+            // the leading space counts towards the length of the continued line
+            current = 1;
+        }
+        folded.push(c);
+        current += c.len_utf8();
+    }
+    folded
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn serializes_events() {
+        let mut calendar = Calendar::new(String::from("Maintenance"));
+        assert!(calendar.add_event(
+            String::from("maintenance-1@kit.edu"),
+            String::from("Upgrade; database, cache"),
+            1700000000,
+            1700003600,
+            Some(String::from("Line one\nLine two"))
+        ));
+        let ics = calendar.to_ics_at(1699990000);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20231114T221320Z\r\nDTEND:20231114T231320Z\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Upgrade\\; database\\, cache\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:Line one\\nLine two\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn rejects_inverted_events() {
+        let mut calendar = Calendar::new(String::from("Duties"));
+        assert!(!calendar.add_event(String::from("duty-1@kit.edu"), String::from("Duty"), 10, 5, None));
+        assert!(calendar.is_empty());
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let folded = fold(&"ä".repeat(50), 75);
+        for line in folded.split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), "ä".repeat(50));
+    }
+}
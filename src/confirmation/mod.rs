@@ -0,0 +1,253 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use hmac::{
+    Hmac,
+    Mac
+};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+/// The ConfirmationSigner issues short-lived confirmation tokens for sensitive operations.
+/// A token is an HMAC over the action and the time of issue, keyed with a session key shared with the backend,
+/// so the backend can verify that the user explicitly confirmed exactly this action just now.
+/// Tokens for the same action are issued at most once per minimum interval, so a confirmation dialog
+/// cannot be used to sign a burst of destructive requests.
+#[wasm_bindgen]
+pub struct ConfirmationSigner {
+
+    /// The key shared with the backend for this session
+    session_key: Vec<u8>,
+
+    /// The time in seconds a token stays valid
+    validity: u64,
+
+    /// The time in seconds until another token is issued for the same action
+    min_interval: u64,
+
+    /// The time of the last issue per action in seconds since the unix epoch
+    last_issued: HashMap<String, u64>
+}
+
+/// The ConfirmationToken is a signed confirmation of a single action.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfirmationToken {
+
+    /// The action which was confirmed
+    action: String,
+
+    /// The time of issue in seconds since the unix epoch
+    issued_at: u64,
+
+    /// The time in seconds the token is valid after issue
+    validity: u64,
+
+    /// The base64url encoded HMAC-SHA256 signature
+    signature: String
+}
+
+impl ConfirmationSigner {
+
+    /// The default time in seconds a token stays valid
+    const DEFAULT_VALIDITY: u64 = 60;
+
+    /// The default time in seconds until another token is issued for the same action
+    const DEFAULT_MIN_INTERVAL: u64 = 10;
+
+    /// Issue a token for the given action at the given point in time
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ConfirmationToken)` - The token
+    /// * `Err(String)` - If a token was issued for the action within the minimum interval
+    pub fn issue_at(&mut self, action: &str, issued_at: u64) -> Result<ConfirmationToken, String> {
+        if let Some(last) = self.last_issued.get(action) {
+            let next = last.saturating_add(self.min_interval);
+            if issued_at >= *last && issued_at < next {
+                return Err(format!("The action {} can be confirmed again in {} seconds", action, next - issued_at));
+            }
+        }
+        self.last_issued.insert(String::from(action), issued_at);
+        Ok(ConfirmationToken {
+            action: String::from(action),
+            issued_at,
+            validity: self.validity,
+            signature: self.sign(action, issued_at)
+        })
+    }
+
+    /// Verify the given token at the given point in time, as the backend does
+    pub fn verify_at(&self, token: &ConfirmationToken, now: u64) -> bool {
+        let mut mac = self.mac();
+        mac.update(ConfirmationToken::payload(&token.action, token.issued_at).as_bytes());
+        let signature = match base64::decode_config(&token.signature, base64::URL_SAFE_NO_PAD) {
+            Ok(signature) => signature,
+            Err(_) => return false
+        };
+        mac.verify_slice(&signature).is_ok() && !token.is_expired_at(now)
+    }
+
+    fn sign(&self, action: &str, issued_at: u64) -> String {
+        let mut mac = self.mac();
+        mac.update(ConfirmationToken::payload(action, issued_at).as_bytes());
+        base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.session_key)
+            .expect("HMAC accepts keys of any length")
+    }
+}
+
+#[wasm_bindgen]
+impl ConfirmationSigner {
+
+    /// Create a signer for the given session key
+    ///
+    /// # Arguments
+    ///
+    /// * `session_key` - The key shared with the backend for this session
+    /// * `validity` - The time in seconds a token stays valid, defaults to 60 seconds
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut signer = ConfirmationSigner::new(session_key, None);
+    /// let token = signer.issue("delete_all_aliases")?;
+    /// // send token.to_string() along with the request
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(session_key: Vec<u8>, validity: Option<u32>) -> ConfirmationSigner {
+        ConfirmationSigner {
+            session_key,
+            validity: validity.map(u64::from).unwrap_or(Self::DEFAULT_VALIDITY),
+            min_interval: Self::DEFAULT_MIN_INTERVAL,
+            last_issued: HashMap::new()
+        }
+    }
+
+    /// Set the time until another token is issued for the same action, defaults to 10 seconds.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `min_interval` - The time in seconds, `0` to issue tokens without limit
+    pub fn with_min_interval(mut self, min_interval: u32) -> ConfirmationSigner {
+        self.min_interval = u64::from(min_interval);
+        self
+    }
+
+    /// Issue a token confirming the given action now
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The identifier of the action to confirm
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ConfirmationToken)` - The token
+    /// * `Err(JsValue)` - If a token was issued for the action within the minimum interval
+    pub fn issue(&mut self, action: &str) -> Result<ConfirmationToken, JsValue> {
+        self.issue_at(action, now()).map_err(|cause| JsValue::from_str(&cause))
+    }
+}
+
+impl ConfirmationToken {
+
+    /// The signed payload of a token
+    fn payload(action: &str, issued_at: u64) -> String {
+        format!("{}.{}", action, issued_at)
+    }
+
+    /// Whether the token is expired at the given point in time
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        now < self.issued_at || now - self.issued_at > self.validity
+    }
+}
+
+#[wasm_bindgen]
+impl ConfirmationToken {
+
+    /// The confirmed action
+    pub fn action(&self) -> String {
+        self.action.clone()
+    }
+
+    /// Whether the token is expired and a new confirmation has to be requested
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(now())
+    }
+
+    /// The remaining time in seconds until the token expires
+    pub fn remaining_seconds(&self) -> u32 {
+        (self.issued_at + self.validity).saturating_sub(now()) as u32
+    }
+
+    /// The token in the format `<action>.<issued_at>.<signature>` expected by the backend
+    #[allow(clippy::inherent_to_string)]
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        format!("{}.{}", Self::payload(&self.action, self.issued_at), self.signature)
+    }
+}
+
+/// The current time in seconds since the unix epoch
+fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn signer() -> ConfirmationSigner {
+        ConfirmationSigner::new(b"session-key".to_vec(), Some(60))
+    }
+
+    #[test]
+    fn issued_tokens_verify() {
+        let token = signer().issue_at("delete_user", 1000).unwrap();
+        assert!(signer().verify_at(&token, 1030));
+        assert!(token.to_string().starts_with("delete_user.1000."));
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let token = signer().issue_at("delete_user", 1000).unwrap();
+        assert!(!signer().verify_at(&token, 1061));
+        assert!(!signer().verify_at(&token, 999));
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let mut token = signer().issue_at("delete_user", 1000).unwrap();
+        token.action = String::from("delete_all_users");
+        assert!(!signer().verify_at(&token, 1000));
+
+        let mut other = ConfirmationSigner::new(b"other-key".to_vec(), None);
+        let token = other.issue_at("delete_user", 1000).unwrap();
+        assert!(!signer().verify_at(&token, 1000));
+    }
+
+    #[test]
+    fn limits_issues_per_action() {
+        let mut signer = signer();
+        assert!(signer.issue_at("delete_user", 1000).is_ok());
+        assert_eq!(
+            signer.issue_at("delete_user", 1004),
+            Err(String::from("The action delete_user can be confirmed again in 6 seconds"))
+        );
+        assert!(signer.issue_at("restore_backup:42", 1004).is_ok());
+        assert!(signer.issue_at("delete_user", 1010).is_ok());
+
+        let mut unlimited = ConfirmationSigner::new(b"session-key".to_vec(), None).with_min_interval(0);
+        assert!(unlimited.issue_at("delete_user", 1000).is_ok());
+        assert!(unlimited.issue_at("delete_user", 1000).is_ok());
+    }
+}
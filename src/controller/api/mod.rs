@@ -0,0 +1,283 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{
+    future_to_promise,
+    JsFuture
+};
+use web_sys::{
+    AbortController,
+    Request,
+    RequestInit,
+    Response
+};
+use js_sys::{
+    Error,
+    Promise,
+    JSON
+};
+use openidconnect::url::Url;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::AuthManager;
+use super::NetworkConditions;
+use super::PersistentStore;
+use super::auth_manager::AuthError;
+use super::refresh::refresh_session;
+use crate::stats::Latencies;
+
+/// The ApiClient sends requests to the backend on behalf of the authenticated user.
+/// The access token is attached as `Authorization: Bearer` header, so it never has to leave the framework.
+/// If the backend answers with `401 Unauthorized`, the tokens are renewed once and the request is repeated.
+/// Concurrent requests share a single renewal.
+/// The duration of each request is recorded, see [`Framework::latencies`](super::Framework::latencies).
+#[wasm_bindgen]
+pub struct ApiClient {
+
+    /// The URL the paths of the requests are resolved against, ending with a slash
+    base_url: Url,
+
+    /// The [`AuthManager`] holding the tokens of the user
+    auth: Rc<RefCell<AuthManager>>,
+
+    /// The store to persist renewed tokens in
+    session: Rc<dyn PersistentStore>,
+
+    /// The durations of the requests sent
+    latencies: Rc<RefCell<Latencies>>
+}
+
+impl ApiClient {
+
+    const STATUS_UNAUTHORIZED: u16 = 401;
+
+    /// Create a client for the backend at the given URL
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The URL of the backend, the paths of the requests are resolved against
+    /// * `auth` - The [`AuthManager`] holding the tokens of the user
+    /// * `session` - The [`PersistentStore`] to persist renewed tokens in
+    /// * `latencies` - The [`Latencies`] to record the durations of the requests in
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ApiClient)` - The client
+    /// * `Err(AuthError)` - If the base URL is not valid
+    pub fn new(
+        base_url: &str,
+        auth: Rc<RefCell<AuthManager>>,
+        session: Rc<dyn PersistentStore>,
+        latencies: Rc<RefCell<Latencies>>
+    ) -> Result<ApiClient, AuthError> {
+        let mut base_url = match Url::parse(base_url) {
+            Ok(url) if !url.cannot_be_a_base() => url,
+            _ => return Err(AuthError::InvalidConfiguration(String::from("The provided api url is not correct!")))
+        };
+        // Without a trailing slash, the last segment of the base would be replaced by the path
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Ok(ApiClient {
+            base_url,
+            auth,
+            session,
+            latencies
+        })
+    }
+
+    /// Resolve the given path against the base URL, e.g. `users/42` or `/users/42`.
+    /// The access token is attached to the request, so the result has to stay below the base URL.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Url)` - The URL of the endpoint
+    /// * `None` - If the path is not valid or leaves the base URL, e.g. `https://evil.org/x` or `../admin`
+    fn endpoint(&self, path: &str) -> Option<Url> {
+        let endpoint = self.base_url.join(path.trim_start_matches('/')).ok()?;
+        if endpoint.origin() != self.base_url.origin() || !endpoint.path().starts_with(self.base_url.path()) {
+            return None;
+        }
+        Some(endpoint)
+    }
+
+    /// Send a request with the access token, see [`send`]
+    fn send(&self, method: &'static str, path: &str, body: Option<String>) -> Promise {
+        let endpoint = self.endpoint(path)
+            .ok_or_else(|| JsValue::from(Error::new(&format!("The path {} is not valid!", path))));
+        let auth = self.auth.clone();
+        let session = self.session.clone();
+        let latencies = self.latencies.clone();
+
+        future_to_promise(async move {
+            Ok(send(auth, session, latencies, method, endpoint?, body).await?.into())
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl ApiClient {
+
+    /// Send a `GET` request to the given path
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<Response>` - Resolves to the `Response` of the backend, whatever its status.
+    ///   Rejects if the user is not authenticated, the tokens cannot be renewed or the request fails
+    ///
+    /// # Example
+    /// ```rust
+    /// let api: ApiClient = framework.api_client(String::from("https://api.my.site/v1"))?;
+    /// const users = await (await api.get("users")).json();
+    /// ```
+    pub fn get(&self, path: String) -> Promise {
+        self.send("GET", &path, None)
+    }
+
+    /// Send a `POST` request to the given path, with the given value serialized as JSON body.
+    /// See [`ApiClient::get`] for the result.
+    pub fn post(&self, path: String, body: &JsValue) -> Result<Promise, JsValue> {
+        Ok(self.send("POST", &path, Some(String::from(JSON::stringify(body)?))))
+    }
+
+    /// Send a `PUT` request to the given path, with the given value serialized as JSON body.
+    /// See [`ApiClient::get`] for the result.
+    pub fn put(&self, path: String, body: &JsValue) -> Result<Promise, JsValue> {
+        Ok(self.send("PUT", &path, Some(String::from(JSON::stringify(body)?))))
+    }
+
+    /// Send a `DELETE` request to the given path.
+    /// See [`ApiClient::get`] for the result.
+    pub fn delete(&self, path: String) -> Promise {
+        self.send("DELETE", &path, None)
+    }
+}
+
+/// Send a request to the given URL on behalf of the user authenticated at the given [`AuthManager`].
+/// If the backend answers with `401 Unauthorized`, the tokens are renewed and persisted once and the request is repeated.
+/// If the tokens have been renewed meanwhile, e.g. by a concurrent request, the renewed ones are used right away.
+/// The request is only repeated if the access token actually changed.
+/// The duration of each request answered by the backend is recorded in the given [`Latencies`].
+///
+/// # Returns
+///
+/// * `Ok(Response)` - The response of the backend, whatever its status
+/// * `Err(JsValue)` - A structured [`AuthError`] if the user is not authenticated or the tokens cannot be renewed,
+///   or the error of `fetch` if the request fails
+pub(crate) async fn send(
+    auth: Rc<RefCell<AuthManager>>,
+    session: Rc<dyn PersistentStore>,
+    latencies: Rc<RefCell<Latencies>>,
+    method: &'static str,
+    endpoint: Url,
+    body: Option<String>
+) -> Result<Response, JsValue> {
+    let token = auth.borrow().access_token()?.secret().clone();
+    let response = fetch(method, &endpoint, body.as_deref(), &token, &latencies).await?;
+    if response.status() != ApiClient::STATUS_UNAUTHORIZED {
+        return Ok(response);
+    }
+
+    if *auth.borrow().access_token()?.secret() == token {
+        refresh_session(&auth, &session).await?;
+    }
+    let renewed = auth.borrow().access_token()?.secret().clone();
+    if renewed == token {
+        return Ok(response);
+    }
+    fetch(method, &endpoint, body.as_deref(), &renewed, &latencies).await
+}
+
+/// Send a single request with the given access token and record its duration.
+/// The request is aborted if no response arrives in time, see [`NetworkConditions::timeout`].
+async fn fetch(
+    method: &str,
+    url: &Url,
+    body: Option<&str>,
+    token: &str,
+    latencies: &RefCell<Latencies>
+) -> Result<Response, JsValue> {
+    let controller = AbortController::new()?;
+    let init = RequestInit::new();
+    init.set_method(method);
+    init.set_signal(Some(&controller.signal()));
+    if let Some(body) = body {
+        init.set_body(&JsValue::from_str(body));
+    }
+
+    let request = Request::new_with_str_and_init(url.as_str(), &init)?;
+    request.headers().set("Authorization", &format!("Bearer {}", token))?;
+    if body.is_some() {
+        request.headers().set("Content-Type", "application/json")?;
+    }
+
+    let window = web_sys::window().ok_or_else(|| JsValue::from(Error::new("No window available!")))?;
+    let abort = Closure::once(move || controller.abort());
+    let timeout = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+        abort.as_ref().unchecked_ref(),
+        NetworkConditions::current().timeout().min(i32::MAX as u32) as i32
+    )?;
+    let sent = js_sys::Date::now();
+    let response = JsFuture::from(window.fetch_with_request(&request)).await;
+    window.clear_timeout_with_handle(timeout);
+    let response = response?.dyn_into::<Response>()?;
+    latencies.borrow_mut().record(sent, js_sys::Date::now() - sent);
+    Ok(response)
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::{
+        ClientData,
+        MemoryStore
+    };
+
+    fn client(base_url: &str) -> Result<ApiClient, AuthError> {
+        let client_data = ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from("client"),
+            String::from("https://my.site")
+        ).ok().unwrap();
+        ApiClient::new(
+            base_url,
+            Rc::new(RefCell::new(AuthManager::new(client_data))),
+            Rc::new(MemoryStore::new()),
+            Rc::new(RefCell::new(Latencies::new()))
+        )
+    }
+
+    #[test]
+    fn resolves_paths_against_base() {
+        let api = client("https://api.my.site/v1").ok().unwrap();
+        assert_eq!(api.endpoint("users/42").unwrap().as_str(), "https://api.my.site/v1/users/42");
+        assert_eq!(api.endpoint("/users?page=2").unwrap().as_str(), "https://api.my.site/v1/users?page=2");
+    }
+
+    #[test]
+    fn stays_below_base() {
+        let api = client("https://api.my.site/v1").ok().unwrap();
+        assert_eq!(api.endpoint("https://evil.org/x"), None);
+        assert_eq!(api.endpoint("https://api.my.site/admin"), None);
+        assert_eq!(api.endpoint("../admin"), None);
+        assert_eq!(api.endpoint("users/../../admin"), None);
+        // resolved as path below the base, not as another host
+        assert_eq!(api.endpoint("//evil.org/x").unwrap().as_str(), "https://api.my.site/v1/evil.org/x");
+    }
+
+    #[test]
+    fn rejects_invalid_base() {
+        assert!(matches!(client("not a url"), Err(AuthError::InvalidConfiguration(_))));
+        assert!(matches!(client("mailto:admin@my.site"), Err(AuthError::InvalidConfiguration(_))));
+    }
+}
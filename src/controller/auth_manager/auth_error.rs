@@ -1,44 +1,204 @@
-/// SPDX-License-Identifier: MIT
-/// SPDX-License-Identifier: APACHE
-/// 
-/// 2022, Patrick Schneider <patrick@itermori.de>
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
 
 use wasm_bindgen::prelude::*;
+use js_sys::{
+    Object,
+    Reflect
+};
+use openidconnect::{
+    ErrorResponse,
+    RequestTokenError
+};
+use std::error::Error;
 use std::fmt;
 use std::convert::From;
+use crate::controller::StoreError;
 
-/// The AuthError represents an error which occurs during the authorization process 
-#[wasm_bindgen]
-pub struct AuthError {
+/// The AuthError represents an error which occurs during the authorization process
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthError {
 
-    /// The URL to redirect to.
-    /// Must be known to the authentication provider
-    cause: String
+    /// The provided client data or URLs are not valid
+    InvalidConfiguration(String),
+
+    /// No authentication process was initiated, e.g. the stored data is missing
+    NotInitiated,
+
+    /// The state returned by the authentication provider did not match the stored one
+    CsrfMismatch,
+
+    /// The authorization response does not contain the expected parameters
+    InvalidResponse(String),
+
+    /// The authentication provider denied the authorization, e.g. with `access_denied`
+    ProviderError {
+
+        /// The error code returned by the provider
+        error: String,
+
+        /// The human readable description returned by the provider, if any
+        description: Option<String>
+    },
+
+    /// The authorization code or refresh token could not be exchanged for tokens
+    TokenExchangeFailed {
+
+        /// The HTTP status of the token response, if one was received
+        status: Option<u16>,
+
+        /// The body of the token response or a description of the failure
+        body: String
+    },
+
+    /// The authentication provider did not return an id token
+    MissingIdToken,
+
+    /// The id token failed the verification
+    InvalidIdToken(String),
+
+    /// A renewed id token was issued for another user than the authenticated one
+    SubjectChanged,
+
+    /// No refresh token is held to renew the session with
+    MissingRefreshToken,
+
+    /// The user is not authenticated, i.e. no access token is held
+    NotAuthenticated,
+
+    /// The claims could not be fetched from the userinfo endpoint
+    UserInfoFailed(String),
+
+    /// The storage could not be accessed
+    StorageUnavailable(String),
+
+    /// The stored authentication state could not be read or written
+    CorruptedState(String)
+}
+
+impl AuthError {
+
+    /// A stable identifier of the kind of the error, for the frontend to branch on.
+    ///
+    /// # Example
+    /// ```rust
+    /// assert_eq!(AuthError::CsrfMismatch.code(), "csrf_mismatch");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::InvalidConfiguration(_) => "invalid_configuration",
+            AuthError::NotInitiated => "not_initiated",
+            AuthError::CsrfMismatch => "csrf_mismatch",
+            AuthError::InvalidResponse(_) => "invalid_response",
+            AuthError::ProviderError { .. } => "provider_error",
+            AuthError::TokenExchangeFailed { .. } => "token_exchange_failed",
+            AuthError::MissingIdToken => "missing_id_token",
+            AuthError::InvalidIdToken(_) => "invalid_id_token",
+            AuthError::SubjectChanged => "subject_changed",
+            AuthError::MissingRefreshToken => "missing_refresh_token",
+            AuthError::NotAuthenticated => "not_authenticated",
+            AuthError::UserInfoFailed(_) => "user_info_failed",
+            AuthError::StorageUnavailable(_) => "storage_unavailable",
+            AuthError::CorruptedState(_) => "corrupted_state"
+        }
+    }
+
+    /// A description of the error, without the common prefix of [`Display`](fmt::Display).
+    pub fn message(&self) -> String {
+        match self {
+            AuthError::InvalidConfiguration(cause) => format!("The configuration is not valid: {}", cause),
+            AuthError::NotInitiated => String::from("No authentication process was initiated!"),
+            AuthError::CsrfMismatch => String::from("Cross-Site Request Forgery detected! The returned state did not match!"),
+            AuthError::InvalidResponse(cause) => format!("The authorization response is not valid: {}", cause),
+            AuthError::ProviderError { error, description: Some(description) } => format!("The authentication provider returned {}: {}", error, description),
+            AuthError::ProviderError { error, description: None } => format!("The authentication provider returned {}", error),
+            AuthError::TokenExchangeFailed { status: Some(status), body } => format!("The token request failed with status {}: {}", status, body),
+            AuthError::TokenExchangeFailed { status: None, body } => format!("The token request failed: {}", body),
+            AuthError::MissingIdToken => String::from("The authentication provider did not return an id token!"),
+            AuthError::InvalidIdToken(cause) => format!("The id token is invalid: {}", cause),
+            AuthError::SubjectChanged => String::from("The renewed id token was issued for another user!"),
+            AuthError::MissingRefreshToken => String::from("No refresh token available!"),
+            AuthError::NotAuthenticated => String::from("The user is not authenticated!"),
+            AuthError::UserInfoFailed(cause) => format!("The userinfo request failed: {}", cause),
+            AuthError::StorageUnavailable(cause) => cause.clone(),
+            AuthError::CorruptedState(cause) => format!("The stored authentication state is corrupted: {}", cause)
+        }
+    }
+
+    /// Convert into a structured JS object carrying `code` and `message`,
+    /// plus `status` and `body` for a failed token exchange
+    /// and `error` and `description` for an error of the provider.
+    ///
+    /// # Example
+    /// ```rust
+    /// let err = AuthError::CsrfMismatch;
+    /// let value: JsValue = err.to_js_value();
+    /// // { code: "csrf_mismatch", message: "Cross-Site Request Forgery detected! ..." }
+    /// ```
+    pub fn to_js_value(&self) -> JsValue {
+        let object = Object::new();
+        let set = |key: &str, value: JsValue| {
+            let _ = Reflect::set(&object, &JsValue::from_str(key), &value);
+        };
+        set("code", JsValue::from_str(self.code()));
+        set("message", JsValue::from_str(&self.message()));
+        if let AuthError::TokenExchangeFailed { status, body } = self {
+            set("status", status.map(JsValue::from).unwrap_or(JsValue::UNDEFINED));
+            set("body", JsValue::from_str(body));
+        }
+        if let AuthError::ProviderError { error, description } = self {
+            set("error", JsValue::from_str(error));
+            set("description", description.as_deref().map(JsValue::from_str).unwrap_or(JsValue::UNDEFINED));
+        }
+        object.into()
+    }
 }
 
 impl fmt::Display for AuthError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Error, cannot authenticate: {}", self.cause)
+        write!(f, "Error, cannot authenticate: {}", self.message())
     }
 }
 
-impl fmt::Debug for AuthError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{{ file: {}, line: {}, message: {} }}", file!(), line!(), self.cause)
+impl Error for AuthError {}
+
+impl From<AuthError> for JsValue {
+    fn from(err: AuthError) -> JsValue {
+        err.to_js_value()
     }
 }
 
-impl From<String> for AuthError {
-    fn from(cause: String) -> AuthError{
-        AuthError {
-            cause
-        }
+impl From<StoreError> for AuthError {
+    fn from(err: StoreError) -> AuthError {
+        AuthError::StorageUnavailable(err.to_string())
     }
 }
 
-impl From<&str> for AuthError {
-    fn from(cause: &str) -> AuthError{
-        AuthError::from(String::from(cause))
+impl AuthError {
+
+    /// Convert a failed request to the token endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `err` - The error of the token request
+    /// * `status` - The HTTP status of the token response, if one was received
+    pub fn token_exchange_failed<RE, T>(err: RequestTokenError<RE, T>, status: Option<u16>) -> AuthError
+    where
+        RE: Error + 'static,
+        T: ErrorResponse + 'static
+    {
+        let body = match &err {
+            RequestTokenError::ServerResponse(response) => serde_json::to_string(response)
+                .unwrap_or_else(|_| err.to_string()),
+            RequestTokenError::Parse(_, body) => String::from_utf8_lossy(body).into_owned(),
+            _ => err.to_string()
+        };
+        AuthError::TokenExchangeFailed {
+            status,
+            body
+        }
     }
 }
 
@@ -48,4 +208,32 @@ impl From<&str> for AuthError {
 mod tests {
 
     use super::*;
-}
\ No newline at end of file
+    use openidconnect::StandardErrorResponse;
+    use openidconnect::core::CoreErrorResponseType;
+
+    #[test]
+    fn display_contains_message() {
+        let err = AuthError::InvalidResponse(String::from("no code"));
+        assert_eq!(err.to_string(), "Error, cannot authenticate: The authorization response is not valid: no code");
+    }
+
+    #[test]
+    fn codes_are_distinct() {
+        assert_eq!(AuthError::CsrfMismatch.code(), "csrf_mismatch");
+        assert_ne!(AuthError::MissingIdToken.code(), AuthError::InvalidIdToken(String::new()).code());
+    }
+
+    #[test]
+    fn from_server_response() {
+        let response = StandardErrorResponse::new(
+            CoreErrorResponseType::InvalidGrant,
+            Some(String::from("code expired")),
+            None
+        );
+        let err = AuthError::token_exchange_failed(RequestTokenError::<std::io::Error, _>::ServerResponse(response), None);
+        match err {
+            AuthError::TokenExchangeFailed { status: None, body } => assert!(body.contains("invalid_grant")),
+            other => panic!("unexpected error {:?}", other)
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use openidconnect::{
+    AdditionalClaims,
+    Client,
+    EmptyExtraTokenFields,
+    IdToken,
+    IdTokenClaims,
+    IdTokenFields,
+    StandardErrorResponse,
+    StandardTokenResponse,
+    UserInfoClaims
+};
+use openidconnect::core::{
+    CoreAuthDisplay,
+    CoreAuthPrompt,
+    CoreErrorResponseType,
+    CoreGenderClaim,
+    CoreJsonWebKey,
+    CoreJsonWebKeyType,
+    CoreJsonWebKeyUse,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+    CoreRevocableToken,
+    CoreRevocationErrorResponse,
+    CoreTokenIntrospectionResponse,
+    CoreTokenType
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    Map,
+    Value
+};
+
+/// The claims of an id token or userinfo response which are not defined by the OpenID Connect standard,
+/// e.g. the `realm_access` or `groups` claims issued by Keycloak.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct CustomClaims {
+
+    /// The claims by their name
+    #[serde(flatten)]
+    claims: Map<String, Value>
+}
+
+impl AdditionalClaims for CustomClaims {}
+
+impl CustomClaims {
+
+    /// Get the claim with the given name.
+    /// If no claim has exactly this name, the name is read as a path of nested claims separated by dots.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `name` - The name of the claim, e.g. `groups` or `realm_access.roles`
+    /// 
+    /// # Returns
+    /// 
+    /// * `Some(&Value)` - The value of the claim
+    /// * `None` - If there is no such claim
+    /// 
+    /// # Example
+    /// ```rust
+    /// let claims: CustomClaims; // issued by Keycloak
+    /// let roles = claims.get("realm_access.roles");
+    /// ```
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        if let Some(value) = self.claims.get(name) {
+            return Some(value);
+        }
+        let mut path = name.split('.');
+        let first = self.claims.get(path.next()?)?;
+        path.try_fold(first, |value, segment| value.get(segment))
+    }
+}
+
+/// The client used to authenticate, with [`CustomClaims`] instead of no additional claims
+pub type OidcClient = Client<
+    CustomClaims,
+    CoreAuthDisplay,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+    CoreJsonWebKeyType,
+    CoreJsonWebKeyUse,
+    CoreJsonWebKey,
+    CoreAuthPrompt,
+    StandardErrorResponse<CoreErrorResponseType>,
+    OidcTokenResponse,
+    CoreTokenType,
+    CoreTokenIntrospectionResponse,
+    CoreRevocableToken,
+    CoreRevocationErrorResponse
+>;
+
+/// The id token issued to the [`OidcClient`]
+pub type OidcIdToken = IdToken<
+    CustomClaims,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJwsSigningAlgorithm,
+    CoreJsonWebKeyType
+>;
+
+/// The claims of an [`OidcIdToken`]
+pub type OidcIdTokenClaims = IdTokenClaims<CustomClaims, CoreGenderClaim>;
+
+/// The claims returned by the userinfo endpoint to the [`OidcClient`]
+pub type OidcUserInfoClaims = UserInfoClaims<CustomClaims, CoreGenderClaim>;
+
+/// The token response returned to the [`OidcClient`]
+pub type OidcTokenResponse = StandardTokenResponse<
+    IdTokenFields<
+        CustomClaims,
+        EmptyExtraTokenFields,
+        CoreGenderClaim,
+        CoreJweContentEncryptionAlgorithm,
+        CoreJwsSigningAlgorithm,
+        CoreJsonWebKeyType
+    >,
+    CoreTokenType
+>;
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn reads_nested_claims() {
+        let claims: CustomClaims = serde_json::from_str(
+            r#"{"groups":["admins"],"realm_access":{"roles":["admin","user"]},"a.b":1}"#
+        ).unwrap();
+        assert_eq!(claims.get("groups"), Some(&serde_json::json!(["admins"])));
+        assert_eq!(claims.get("realm_access.roles"), Some(&serde_json::json!(["admin", "user"])));
+        assert_eq!(claims.get("a.b"), Some(&serde_json::json!(1)));
+        assert_eq!(claims.get("realm_access.groups"), None);
+        assert_eq!(claims.get("missing"), None);
+    }
+}
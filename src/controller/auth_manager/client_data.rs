@@ -1,21 +1,29 @@
-/// SPDX-License-Identifier: MIT
-/// SPDX-License-Identifier: APACHE
-/// 
-/// 2022, Patrick Schneider <patrick@itermori.de>
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
 
 use wasm_bindgen::prelude::*;
-use oauth2::{
+use openidconnect::{
     ClientId,
     AuthUrl,
+    ClientSecret,
+    IssuerUrl,
     RedirectUrl,
-    TokenUrl
+    Scope,
+    TokenUrl,
+    UserInfoUrl
 };
-use oauth2::basic::BasicClient;
+use openidconnect::core::CoreJsonWebKeySet;
+use openidconnect::url::Url;
 use super::auth_error::AuthError;
+use super::claims::OidcClient;
+use crate::controller::StorageKeys;
 
 /// The ClientData struct stores the relevant authentication provider data used in the authentication process.
 /// 
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct ClientData {
 
     /// The URL to redirect to.
@@ -28,8 +36,32 @@ pub struct ClientData {
     // The URL to fetch the token of the authentication provider.
     token_url: TokenUrl,
 
+    /// The URL to fetch the claims of the authenticated user from, if supported.
+    user_info_url: Option<UserInfoUrl>,
+
     /// The client id registered at the authentication provider.
-    client_id: ClientId
+    client_id: ClientId,
+
+    /// The secret of a confidential client, if any.
+    client_secret: Option<ClientSecret>,
+
+    /// The URL to end the session at the authentication provider on logout, if supported.
+    end_session_url: Option<Url>,
+
+    /// The issuer the id tokens have to be issued by, if known.
+    issuer_url: Option<IssuerUrl>,
+
+    /// The scopes to request in addition to `openid`.
+    scopes: Vec<Scope>,
+
+    /// The keys the id tokens are signed with, if known.
+    jwks: Option<CoreJsonWebKeySet>,
+
+    /// The seconds an initiated authentication can be completed in.
+    login_timeout: u32,
+
+    /// The namespace of the keys the state is stored under.
+    storage_namespace: String
 }
 
 #[wasm_bindgen]
@@ -72,13 +104,269 @@ impl ClientData {
                     redirect_url
                 )
             ),
-            _ => Err(JsValue::from(AuthError::from("The provided data is not correct!")))
+            _ => Err(JsValue::from(AuthError::InvalidConfiguration(String::from("The provided data is not correct!"))))
+        }
+    }
+
+    /// Set the end session endpoint of the authentication provider,
+    /// used to end the session at the provider on logout.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `end_session_url` - The end session endpoint of the used authentication provider
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_end_session_url(String::from("https://auth_provider.org/logout"))?;
+    /// ```
+    pub fn with_end_session_url(mut self, end_session_url: String) -> Result<ClientData, JsValue> {
+        match Url::parse(&end_session_url) {
+            Ok(url) => {
+                self.end_session_url = Some(url);
+                Ok(self)
+            },
+            Err(_) => Err(JsValue::from(AuthError::InvalidConfiguration(String::from("The provided end session url is not correct!"))))
         }
     }
+
+    /// Set the userinfo endpoint of the authentication provider,
+    /// used to fetch claims which are not contained in the id token.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `user_info_url` - The userinfo endpoint of the used authentication provider
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_user_info_url(String::from("https://auth_provider.org/userinfo"))?;
+    /// ```
+    pub fn with_user_info_url(mut self, user_info_url: String) -> Result<ClientData, JsValue> {
+        match UserInfoUrl::new(user_info_url) {
+            Ok(url) => {
+                self.user_info_url = Some(url);
+                Ok(self)
+            },
+            Err(_) => Err(JsValue::from(AuthError::InvalidConfiguration(String::from("The provided userinfo url is not correct!"))))
+        }
+    }
+
+    /// Set the issuer of the authentication provider, id tokens issued by anyone else are rejected.
+    /// Unless set, the origin of the authentication endpoint is expected as issuer,
+    /// so it has to be set for providers issuing their tokens under a path, e.g. Keycloak.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `issuer_url` - The issuer identifier of the used authentication provider
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_issuer(String::from("https://auth_provider.org/realms/kit"))?;
+    /// ```
+    pub fn with_issuer(mut self, issuer_url: String) -> Result<ClientData, JsValue> {
+        match IssuerUrl::new(issuer_url) {
+            Ok(url) => {
+                self.issuer_url = Some(url);
+                Ok(self)
+            },
+            Err(_) => Err(JsValue::from(AuthError::InvalidConfiguration(String::from("The provided issuer url is not correct!"))))
+        }
+    }
+
+    /// Set the keys the authentication provider signs the id tokens with, as JSON Web Key Set.
+    /// If set, the signature of the id tokens is checked.
+    /// The keys are given statically, e.g. for providers which do not allow fetching them cross-origin,
+    /// so they have to be updated when the provider rotates its keys.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `jwks` - The JSON Web Key Set, as served by the `jwks_uri` of the provider
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_jwks(String::from(r#"{"keys":[{"kty":"RSA","kid":"...","n":"...","e":"AQAB"}]}"#))?;
+    /// ```
+    pub fn with_jwks(mut self, jwks: String) -> Result<ClientData, JsValue> {
+        match serde_json::from_str(&jwks) {
+            Ok(jwks) => {
+                self.jwks = Some(jwks);
+                Ok(self)
+            },
+            Err(_) => Err(JsValue::from(AuthError::InvalidConfiguration(String::from("The provided key set is not correct!"))))
+        }
+    }
+
+    /// Set the secret of the client to authenticate as a confidential client at the token endpoint.
+    /// Only use this if the secret is not exposed to the users,
+    /// e.g. if the admin panel runs behind a gateway which requires a confidential client.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `client_secret` - The secret registered for the client at the authentication provider
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_client_secret(String::from("my-client-secret"));
+    /// ```
+    pub fn with_client_secret(mut self, client_secret: String) -> ClientData {
+        self.client_secret = Some(ClientSecret::new(client_secret));
+        self
+    }
+
+    /// Set the time the user has to complete an initiated authentication, 10 minutes by default.
+    /// The state of an abandoned authentication is removed from the storage afterwards.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `seconds` - The time in seconds
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_login_timeout(300);
+    /// ```
+    pub fn with_login_timeout(mut self, seconds: u32) -> ClientData {
+        self.login_timeout = seconds;
+        self
+    }
+
+    /// Set the namespace of the keys the state is stored under, `kifapwa` by default.
+    /// Within the namespace, the keys are scoped by the client and the authentication provider.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `namespace` - The namespace, e.g. to tell apart several panels sharing an origin
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_storage_namespace(String::from("admin-panel"));
+    /// ```
+    pub fn with_storage_namespace(mut self, namespace: String) -> ClientData {
+        self.storage_namespace = namespace;
+        self
+    }
+
+    /// Set the scopes to request from the authentication provider, e.g. `roles` or `offline_access`.
+    /// The `openid` scope is always requested.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `scopes` - The scopes to request in addition to `openid`
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client: ClientData = ClientData::from(/* */)?
+    ///     .with_scopes(vec![String::from("roles"), String::from("offline_access")]);
+    /// ```
+    pub fn with_scopes(mut self, scopes: Vec<String>) -> ClientData {
+        self.scopes = scopes.into_iter().map(Scope::new).collect();
+        self
+    }
+
+    /// Create the client data for a realm of a Keycloak server,
+    /// including the issuer, the userinfo and end session endpoints and the `profile` and `email` scopes.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `base_url` - The URL of the Keycloak server, e.g. `https://sso.kit.edu`
+    /// * `realm` - The name of the realm
+    /// * `client_id` - The at the authentication provider registered client id
+    /// * `redirect_url`- The at the authentication provider registered redirection url
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client = ClientData::keycloak(
+    ///     String::from("https://sso.kit.edu"),
+    ///     String::from("kit"),
+    ///     String::from("admin-panel"),
+    ///     String::from("https://my.site")
+    /// )?;
+    /// ```
+    pub fn keycloak(base_url: String, realm: String, client_id: String, redirect_url: String) -> Result<ClientData, JsValue> {
+        let issuer = format!("{}/realms/{}", base_url.trim_end_matches('/'), realm);
+        let endpoint = |name: &str| format!("{}/protocol/openid-connect/{}", issuer, name);
+        Ok(ClientData::from(endpoint("auth"), endpoint("token"), client_id, redirect_url)?
+            .with_user_info_url(endpoint("userinfo"))?
+            .with_end_session_url(endpoint("logout"))?
+            .with_issuer(issuer)?
+            .with_scopes(Self::preset_scopes(&["profile", "email"])))
+    }
+
+    /// Create the client data for an Auth0 tenant,
+    /// including the issuer, the userinfo and end session endpoints and the `profile`, `email` and `offline_access` scopes.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `domain` - The domain of the tenant, e.g. `kit.eu.auth0.com`
+    /// * `client_id` - The at the authentication provider registered client id
+    /// * `redirect_url`- The at the authentication provider registered redirection url
+    pub fn auth0(domain: String, client_id: String, redirect_url: String) -> Result<ClientData, JsValue> {
+        let base = format!("https://{}", domain.trim_end_matches('/'));
+        Ok(ClientData::from(format!("{}/authorize", base), format!("{}/oauth/token", base), client_id, redirect_url)?
+            .with_user_info_url(format!("{}/userinfo", base))?
+            .with_end_session_url(format!("{}/oidc/logout", base))?
+            // Auth0 issues its tokens with a trailing slash
+            .with_issuer(format!("{}/", base))?
+            .with_scopes(Self::preset_scopes(&["profile", "email", "offline_access"])))
+    }
+
+    /// Create the client data for a tenant of Azure Active Directory (Microsoft identity platform v2.0),
+    /// including the issuer, the userinfo and end session endpoints and the `profile`, `email` and `offline_access` scopes.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant` - The id or domain of the tenant. `common` and `organizations` are not supported,
+    ///   as their tokens are issued by the tenant of the user
+    /// * `client_id` - The at the authentication provider registered client id
+    /// * `redirect_url`- The at the authentication provider registered redirection url
+    pub fn azure(tenant: String, client_id: String, redirect_url: String) -> Result<ClientData, JsValue> {
+        let base = format!("https://login.microsoftonline.com/{}", tenant);
+        Ok(ClientData::from(format!("{}/oauth2/v2.0/authorize", base), format!("{}/oauth2/v2.0/token", base), client_id, redirect_url)?
+            .with_user_info_url(String::from("https://graph.microsoft.com/oidc/userinfo"))?
+            .with_end_session_url(format!("{}/oauth2/v2.0/logout", base))?
+            .with_issuer(format!("{}/v2.0", base))?
+            .with_scopes(Self::preset_scopes(&["profile", "email", "offline_access"])))
+    }
+
+    /// Create the client data for Google,
+    /// including the issuer, the userinfo endpoint and the `profile` and `email` scopes.
+    /// Google has no end session endpoint.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `client_id` - The at the authentication provider registered client id
+    /// * `redirect_url`- The at the authentication provider registered redirection url
+    pub fn google(client_id: String, redirect_url: String) -> Result<ClientData, JsValue> {
+        Ok(ClientData::from(
+            String::from("https://accounts.google.com/o/oauth2/v2/auth"),
+            String::from("https://oauth2.googleapis.com/token"),
+            client_id,
+            redirect_url
+        )?
+            .with_user_info_url(String::from("https://openidconnect.googleapis.com/v1/userinfo"))?
+            .with_issuer(String::from("https://accounts.google.com"))?
+            .with_scopes(Self::preset_scopes(&["profile", "email"])))
+    }
 }
 
 impl ClientData {
 
+    /// The seconds an initiated authentication can be completed in, unless configured otherwise
+    const DEFAULT_LOGIN_TIMEOUT: u32 = 600;
+
     /// Create a new ClientData instance with the given values
     /// 
     /// # Arguments
@@ -103,31 +391,106 @@ impl ClientData {
         ClientData {
             auth_url,
             token_url,
+            user_info_url: None,
             client_id,
-            redirect_url
+            client_secret: None,
+            redirect_url,
+            end_session_url: None,
+            issuer_url: None,
+            scopes: Vec::new(),
+            jwks: None,
+            login_timeout: Self::DEFAULT_LOGIN_TIMEOUT,
+            storage_namespace: String::from(StorageKeys::DEFAULT_NAMESPACE)
         }
     }
 
+    /// The endpoint of the authentication provider.
+    pub fn auth_url(&self) -> &AuthUrl {
+        &self.auth_url
+    }
+
+    /// The client id registered at the authentication provider.
+    pub fn client_id(&self) -> &ClientId {
+        &self.client_id
+    }
+
+    /// Whether the client is a confidential client, i.e. a client secret is set.
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret.is_some()
+    }
+
+    /// The end session endpoint of the authentication provider, if set.
+    pub fn end_session_url(&self) -> Option<&Url> {
+        self.end_session_url.as_ref()
+    }
+
+    /// The issuer of the authentication provider, if set.
+    pub fn issuer_url(&self) -> Option<&IssuerUrl> {
+        self.issuer_url.as_ref()
+    }
+
+    /// The issuer the id tokens have to be issued by.
+    /// The one set by [`ClientData::with_issuer`], otherwise the origin of the authentication endpoint.
+    pub fn expected_issuer(&self) -> IssuerUrl {
+        match &self.issuer_url {
+            Some(issuer_url) => issuer_url.clone(),
+            None => IssuerUrl::new(self.auth_url.url().origin().ascii_serialization())
+                .unwrap_or_else(|_| IssuerUrl::from_url(self.auth_url.url().clone()))
+        }
+    }
+
+    /// Whether the keys the id tokens are signed with are set.
+    pub fn has_jwks(&self) -> bool {
+        self.jwks.is_some()
+    }
+
+    /// The seconds an initiated authentication can be completed in.
+    pub fn login_timeout(&self) -> u32 {
+        self.login_timeout
+    }
+
+    /// The namespace of the keys the state is stored under.
+    pub fn storage_namespace(&self) -> &str {
+        &self.storage_namespace
+    }
+
+    /// The scopes to request in addition to `openid`.
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    /// The default scopes of a preset
+    fn preset_scopes(scopes: &[&str]) -> Vec<String> {
+        scopes.iter().map(|scope| String::from(*scope)).collect()
+    }
+
     /// Create the client represented by the data of this instance.
     /// Consumes this instance!
     /// 
+    /// The id tokens have to be issued by the [`ClientData::expected_issuer`].
+    /// 
     /// # Returns
-    /// [`BasicClient`](oauth2::basic::BasicClient)
+    /// [`OidcClient`](super::OidcClient)
     /// 
     /// # Example
     /// ```rust
-    /// let data = BasicClient::new(/** */)
-    /// let client: BasicClient = data.create();
+    /// let data = ClientData::new(/** */)
+    /// let client: OidcClient = data.create();
     /// // data cannot be used anymore!
     /// // do stuff with client
     /// ```
-    pub fn create(self) -> BasicClient {
-        
-        BasicClient::new(
+    pub fn create(self) -> OidcClient {
+
+        let issuer_url = self.expected_issuer();
+
+        OidcClient::new(
             self.client_id,
-            None,
+            self.client_secret,
+            issuer_url,
             self.auth_url,
-            Some(self.token_url)
+            Some(self.token_url),
+            self.user_info_url,
+            self.jwks.unwrap_or_default()
         ).set_redirect_uri(self.redirect_url)
     }
 }
@@ -138,4 +501,81 @@ impl ClientData {
 mod tests {
 
     use super::*;
-}
\ No newline at end of file
+
+    #[test]
+    fn from_valid_data() {
+        let data = ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from("my-client-id"),
+            String::from("https://my.site")
+        );
+        assert!(data.is_ok());
+    }
+
+    #[test]
+    fn create_client() {
+        let data = ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from("my-client-id"),
+            String::from("https://my.site")
+        ).ok().unwrap();
+        assert_eq!(data.client_id().as_str(), "my-client-id");
+        assert!(data.issuer_url().is_none());
+        assert_eq!(data.expected_issuer().as_str(), "https://auth_provider.org");
+        let _client: OidcClient = data.create();
+    }
+
+    #[test]
+    fn create_client_with_issuer() {
+        let data = ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from("my-client-id"),
+            String::from("https://my.site")
+        ).ok().unwrap()
+            .with_issuer(String::from("https://auth_provider.org/realms/kit"))
+            .ok().unwrap();
+        assert!(data.issuer_url().is_some());
+        assert_eq!(data.issuer_url().unwrap().as_str(), "https://auth_provider.org/realms/kit");
+        assert_eq!(data.expected_issuer().as_str(), "https://auth_provider.org/realms/kit");
+    }
+
+    #[test]
+    fn keycloak_preset() {
+        let data = ClientData::keycloak(
+            String::from("https://sso.kit.edu/"),
+            String::from("kit"),
+            String::from("admin-panel"),
+            String::from("https://my.site")
+        ).ok().unwrap();
+        assert_eq!(data.auth_url().as_str(), "https://sso.kit.edu/realms/kit/protocol/openid-connect/auth");
+        assert_eq!(data.issuer_url().unwrap().as_str(), "https://sso.kit.edu/realms/kit");
+        assert_eq!(data.end_session_url().unwrap().as_str(), "https://sso.kit.edu/realms/kit/protocol/openid-connect/logout");
+        assert_eq!(data.scopes().len(), 2);
+    }
+
+    #[test]
+    fn hosted_presets() {
+        let auth0 = ClientData::auth0(String::from("kit.eu.auth0.com"), String::from("id"), String::from("https://my.site")).ok().unwrap();
+        assert_eq!(auth0.issuer_url().unwrap().as_str(), "https://kit.eu.auth0.com/");
+        let azure = ClientData::azure(String::from("kit.edu"), String::from("id"), String::from("https://my.site")).ok().unwrap();
+        assert_eq!(azure.issuer_url().unwrap().as_str(), "https://login.microsoftonline.com/kit.edu/v2.0");
+        let google = ClientData::google(String::from("id"), String::from("https://my.site")).ok().unwrap();
+        assert!(google.end_session_url().is_none());
+        let _client = google.create();
+    }
+
+    #[test]
+    fn confidential_client() {
+        let data = ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from("my-client-id"),
+            String::from("https://my.site")
+        ).ok().unwrap();
+        assert!(!data.is_confidential());
+        assert!(data.with_client_secret(String::from("secret")).is_confidential());
+    }
+}
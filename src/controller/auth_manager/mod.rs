@@ -1,9 +1,7 @@
-/// SPDX-License-Identifier: MIT
-/// SPDX-License-Identifier: APACHE
-/// 
-/// 2022, Patrick Schneider <patrick@itermori.de>
-
-// use crate::console_log;
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
 
 mod pkce;
 pub use pkce::PKCE;
@@ -11,39 +9,113 @@ pub use pkce::PKCE;
 mod client_data;
 pub use client_data::ClientData;
 
+mod provider_registry;
+pub use provider_registry::AuthProviderRegistry;
+
 mod auth_error;
 pub use auth_error::AuthError;
 
-use wasm_bindgen::prelude::*;
-use wasm_bindgen_test::console_log;
-use web_sys::Storage;
-use oauth2::{
+mod claims;
+pub use claims::{
+    OidcClient,
+    OidcIdToken,
+    OidcIdTokenClaims,
+    OidcTokenResponse,
+    OidcUserInfoClaims
+};
+
+use super::Environment;
+use super::PersistentStore;
+use super::StorageKeys;
+use super::refresh::PendingRefresh;
+use super::security_log::{
+    SecurityEvent,
+    SecurityEventKind,
+    SecurityLog
+};
+
+use openidconnect::{
+    AccessToken,
+    HttpRequest,
+    HttpResponse,
     PkceCodeChallenge,
     CsrfToken,
+    Scope,
+    Nonce,
+    NonceVerifier,
     AuthorizationCode,
-    StandardTokenResponse,
-    EmptyExtraTokenFields,
+    OAuth2TokenResponse,
     TokenResponse
 };
-use oauth2::basic::{
-    BasicClient,
-    BasicTokenType
+use openidconnect::core::CoreAuthenticationFlow;
+use openidconnect::url::{
+    form_urlencoded,
+    Url
+};
+use openidconnect::reqwest::async_http_client;
+use chrono::{
+    DateTime,
+    Utc
 };
-use oauth2::url::Url;
-use oauth2::reqwest::async_http_client;
 
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::future::Future;
 
 pub struct AuthManager {
     pkce: Option<PKCE>,
-    client: BasicClient,
-    tokens: Option<StandardTokenResponse<EmptyExtraTokenFields, BasicTokenType>>
+    client: OidcClient,
+    tokens: Option<OidcTokenResponse>,
+
+    /// The verified claims of the last id token received
+    claims: Option<OidcIdTokenClaims>,
+
+    /// The claims fetched from the userinfo endpoint, if any
+    user_info: Option<OidcUserInfoClaims>,
+
+    /// Whether the signature of the id tokens is checked against the configured keys
+    signature_required: bool,
+
+    /// The scopes requested in addition to `openid`
+    scopes: Vec<Scope>,
+
+    /// The configured authentication provider and client
+    environment: Environment,
+
+    /// The time the access token expires at in milliseconds since the unix epoch, if known
+    expires_at: Option<f64>,
+
+    /// The keys of the state written by this instance.
+    /// Scoped by the client, so instances for different clients or issuers don't interfere.
+    keys: StorageKeys,
+
+    /// The keys of the [`SecurityLog`], shared by all clients of the namespace
+    security_keys: StorageKeys,
+
+    /// The URL to end the session at the authentication provider, if supported
+    end_session_url: Option<Url>,
+
+    /// The page the user wanted to visit before the last authentication, if known
+    return_url: Option<String>,
+
+    /// The time in milliseconds an initiated authentication can be completed in
+    login_timeout: f64,
+
+    /// The renewal of the tokens in progress, if any
+    pending_refresh: Option<PendingRefresh>
 }
 
 impl AuthManager {
     
     const URL_AUTH_CODE: &'static str = "code";
     const URL_STATE: &'static str = "state";
+    const URL_ERROR: &'static str = "error";
+    const URL_ERROR_DESCRIPTION: &'static str = "error_description";
+    const ID_TOKENS: &'static str = "tokens";
+    const ID_EXPIRES_AT: &'static str = "expires_at";
+    const ID_CLAIMS: &'static str = "claims";
+    const ID_USER_INFO: &'static str = "user_info";
+    const SECURITY_SCOPE: &'static str = "security";
 
     /// Create a new AuthManager instance with default values
     /// 
@@ -53,67 +125,177 @@ impl AuthManager {
     /// let auth: AuthManager = AuthManager::new(client);
     /// ```
     pub fn new(client_data: ClientData) -> Self {
+        let end_session_url = client_data.end_session_url().cloned();
+        let signature_required = client_data.has_jwks();
+        let login_timeout = f64::from(client_data.login_timeout()) * 1000.0;
+        let scopes = client_data.scopes().to_vec();
+        let environment = Environment::new(
+            client_data.auth_url().to_string(),
+            client_data.client_id().to_string()
+        );
         AuthManager {
             pkce: None,
+            keys: StorageKeys::new(client_data.storage_namespace(), &Self::storage_scope(&environment)),
+            security_keys: StorageKeys::new(client_data.storage_namespace(), Self::SECURITY_SCOPE),
             client: client_data.create(),
-            tokens: None
+            tokens: None,
+            claims: None,
+            user_info: None,
+            signature_required,
+            scopes,
+            environment,
+            expires_at: None,
+            end_session_url,
+            return_url: None,
+            login_timeout,
+            pending_refresh: None
         }
     }
 
+    /// Build the prefix of the storage keys for the given client
+    fn storage_scope(environment: &Environment) -> String {
+        format!("{}@{}", environment.client_id(), environment.auth_url())
+    }
+
+    /// Build the storage key for the given id, scoped to this instance
+    fn key(&self, id: &str) -> String {
+        self.keys.key(id)
+    }
+
     /// Store the state of the AuthManager in the provided storage.
-    /// Only set state will be stored.
+    /// Only set state will be stored, i.e. the data of an initiated authentication
+    /// process and the tokens of an authenticated session.
+    /// Stored tokens and claims which are not held anymore are removed.
     /// 
     /// # Arguments
     /// 
-    /// * `storage` - A [`Storage`](web_sys::Storage) to store the content
+    /// * `storage` - A [`PersistentStore`] to store the content
     /// 
     /// # Returns
     /// 
     /// * `Ok(())` - State could be stored
-    /// * `Err(JsValue)` - State could not be stored
+    /// * `Err(AuthError)` - State could not be stored
     /// 
     /// # Example
     /// 
     /// ```rust
     /// // The storage is provided elsewhere
-    /// let storage: Storage;
+    /// let storage: &dyn PersistentStore;
     /// let auth = AuthManager::new()
     /// if let Err(err) = auth.store(storage) {
     ///     // handle error
     /// }
     /// ```
-    pub fn store(&self, storage: &Storage) -> Result<(), JsValue> {
+    pub fn store(&self, storage: &dyn PersistentStore) -> Result<(), AuthError> {
         if let Some(pkce) = &self.pkce {
-            pkce.store(storage)?
+            pkce.store(storage, &self.keys)?
+        }
+
+        match &self.tokens {
+            Some(tokens) => {
+                let serialized = match serde_json::to_string(tokens) {
+                    Ok(serialized) => serialized,
+                    Err(err) => return Err(AuthError::CorruptedState(err.to_string()))
+                };
+                storage.set(&self.key(Self::ID_TOKENS), &serialized)?;
+                match self.expires_at {
+                    Some(expires_at) => storage.set(&self.key(Self::ID_EXPIRES_AT), &expires_at.to_string())?,
+                    None => storage.remove(&self.key(Self::ID_EXPIRES_AT))?
+                }
+            },
+            None => {
+                storage.remove(&self.key(Self::ID_TOKENS))?;
+                storage.remove(&self.key(Self::ID_EXPIRES_AT))?;
+            }
+        }
+
+        match &self.claims {
+            Some(claims) => {
+                let serialized = match serde_json::to_string(claims) {
+                    Ok(serialized) => serialized,
+                    Err(err) => return Err(AuthError::CorruptedState(err.to_string()))
+                };
+                storage.set(&self.key(Self::ID_CLAIMS), &serialized)?;
+            },
+            None => storage.remove(&self.key(Self::ID_CLAIMS))?
+        }
+
+        match &self.user_info {
+            Some(user_info) => {
+                let serialized = match serde_json::to_string(user_info) {
+                    Ok(serialized) => serialized,
+                    Err(err) => return Err(AuthError::CorruptedState(err.to_string()))
+                };
+                storage.set(&self.key(Self::ID_USER_INFO), &serialized)?;
+            },
+            None => storage.remove(&self.key(Self::ID_USER_INFO))?
         }
 
         Ok(())
     }
 
     /// Load the state of the AuthManager from the provided storage.
-    /// Only set state will be loaded.
+    /// Only set state will be loaded, so a session survives a page reload.
+    /// The state of an authentication initiated longer ago than the login timeout is removed,
+    /// see [`ClientData::with_login_timeout`].
     /// 
     /// # Arguments
     /// 
-    /// * `storage` - A [`Storage`](web_sys::Storage) to store the content
+    /// * `storage` - A [`PersistentStore`] to load the content from
     /// 
     /// # Returns
     /// 
     /// * `Ok(())` - State could be loaded
-    /// * `Err(JsValue)` - State could not be loaded
+    /// * `Err(AuthError)` - State could not be loaded
     /// 
     /// # Example
     /// 
     /// ```rust
     /// // The storage is provided elsewhere
-    /// let storage: Storage;
+    /// let storage: &dyn PersistentStore;
     /// let auth = AuthManager::new()
     /// if let Err(err) = auth.load(storage) {
     ///     // handle error
     /// }
     /// ```
-    pub fn load(&mut self, storage: &Storage) -> Result<(), JsValue> {
-        self.pkce = Some(PKCE::load_from(storage)?);
+    pub fn load(&mut self, storage: &dyn PersistentStore) -> Result<(), AuthError> {
+        self.load_at(storage, js_sys::Date::now())
+    }
+
+    /// Load the state of the AuthManager from the provided storage, see [`AuthManager::load`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `storage` - A [`PersistentStore`] to load the content from
+    /// * `now` - The current time in milliseconds since the unix epoch
+    pub fn load_at(&mut self, storage: &dyn PersistentStore, now: f64) -> Result<(), AuthError> {
+        if let Some(pkce) = PKCE::load_from(storage, &self.keys, now, self.login_timeout)? {
+            self.pkce = Some(pkce);
+        }
+
+        if let Some(serialized) = storage.get(&self.key(Self::ID_TOKENS))? {
+            self.tokens = match serde_json::from_str(&serialized) {
+                Ok(tokens) => Some(tokens),
+                Err(err) => return Err(AuthError::CorruptedState(err.to_string()))
+            };
+            self.expires_at = storage.get(&self.key(Self::ID_EXPIRES_AT))?
+                .and_then(|expires_at| expires_at.parse().ok());
+        }
+
+        if let Some(serialized) = storage.get(&self.key(Self::ID_CLAIMS))? {
+            self.claims = match serde_json::from_str(&serialized) {
+                Ok(claims) => Some(claims),
+                Err(err) => return Err(AuthError::CorruptedState(err.to_string()))
+            };
+        }
+
+        if let Some(serialized) = storage.get(&self.key(Self::ID_USER_INFO))? {
+            self.user_info = match OidcUserInfoClaims::from_json::<std::io::Error>(serialized.as_bytes(), None) {
+                Ok(user_info) => Some(user_info),
+                Err(err) => return Err(AuthError::CorruptedState(err.to_string()))
+            };
+        }
+        self.retain_user_info();
 
         Ok(())
     }
@@ -124,20 +306,21 @@ impl AuthManager {
     /// 
     /// # Arguments
     /// 
-    /// * `storage` - A [`Storage`](web_sys::Storage) to store the information of the authentication process
+    /// * `storage` - A [`PersistentStore`] to store the information of the authentication process
+    /// * `return_url` - The page to return to after the authentication, see [`AuthManager::return_url`]
     /// 
     /// # Returns
     /// 
     /// * `Ok(String)` - The URL the user can authenticate on
-    /// * `Err(JsValue)` - If an error occurred during the initialization
+    /// * `Err(AuthError)` - If an error occurred during the initialization
     /// 
     /// # Example
     /// 
     /// ```rust
     /// // The storage is provided elsewhere
-    /// let storage: Storage;
+    /// let storage: &dyn PersistentStore;
     /// let auth = AuthManager::new();
-    /// match auth.init_authentication() {
+    /// match auth.init_authentication(storage, None) {
     ///     Ok(url) => {
     ///         // do something
     ///     },
@@ -146,23 +329,46 @@ impl AuthManager {
     ///     }
     /// }
     /// ```
-    pub fn init_authentication(&mut self, storage: &Storage) -> Result<Url, JsValue>{
+    pub fn init_authentication(
+        &mut self,
+        storage: &dyn PersistentStore,
+        return_url: Option<String>
+    ) -> Result<Url, AuthError>{
+        self.init_authentication_at(storage, return_url, js_sys::Date::now())
+    }
+
+    /// Initialize the authentication process, see [`AuthManager::init_authentication`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `storage` - A [`PersistentStore`] to store the information of the authentication process
+    /// * `return_url` - The page to return to after the authentication, see [`AuthManager::return_url`]
+    /// * `now` - The current time in milliseconds since the unix epoch
+    pub fn init_authentication_at(
+        &mut self,
+        storage: &dyn PersistentStore,
+        return_url: Option<String>,
+        now: f64
+    ) -> Result<Url, AuthError>{
     
         // Generate a PKCE challenge.
         let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
     
-        // Generate the full authorization URL and the csrf token
-        let (redirect, csrf) = self.client
-            .authorize_url(CsrfToken::new_random)
+        // Generate the full authorization URL, the csrf token and the nonce
+        let (redirect, csrf, nonce) = self.client
+            .authorize_url(
+                CoreAuthenticationFlow::AuthorizationCode,
+                CsrfToken::new_random,
+                Nonce::new_random
+            )
             // Set the desired scopes.
-            // .add_scope(Scope::new("read".to_string()))
-            // .add_scope(Scope::new("write".to_string()))
+            .add_scopes(self.scopes.iter().cloned())
             // Set the PKCE code challenge.
             .set_pkce_challenge(challenge)
             .url();
 
-        // Store the verifier and the csrf token to verify server response
-        self.pkce = Some(PKCE::new(verifier, csrf));
+        // Store the verifier, the csrf token and the nonce to verify server response
+        self.pkce = Some(PKCE::new(verifier, csrf, nonce, now).with_return_url(return_url));
         self.store(storage)?;
 
         Ok(redirect)
@@ -173,9 +379,9 @@ impl AuthManager {
     /// 
     /// # Params
     /// 
-    /// * `code`  - The authorization code of the response. See [`AuthorizationCode`](oauth2::AuthorizationCode)
-    /// * `state` - The state code of the response. See [`CsrfToken`](oauth2::CsrfToken)
-    /// * `storage` - The storage to read the previously generated authorization data from. [`Some(&Storage)](Storage)
+    /// * `code`  - The authorization code of the response. See [`AuthorizationCode`](openidconnect::AuthorizationCode)
+    /// * `state` - The state code of the response. See [`CsrfToken`](openidconnect::CsrfToken)
+    /// * `storage` - The [`PersistentStore`] to read the previously generated authorization data from
     /// 
     /// # Returns
     /// 
@@ -186,7 +392,7 @@ impl AuthManager {
     /// ```rust
     /// let auth = AuthManager::new(/** */);
     /// let redirect = auth.init_authentication(/** */);
-    /// let storage: Storage; // already provided elsewhere
+    /// let storage: &dyn PersistentStore; // already provided elsewhere
     /// /* Authenticate and retreive code and state */
     /// let (auth, result) = auth.exchange_token(code, state, Some(&storage));
     /// if let Err(err) = result {
@@ -198,68 +404,531 @@ impl AuthManager {
         mut self, 
         code: AuthorizationCode, 
         state: CsrfToken,
-        storage: Option<&Storage>
+        storage: Option<&dyn PersistentStore>
     ) -> (Self, Result<(), AuthError>) {
-        
-        if let None = self.pkce {
+
+        let (request, nonce) = match self.exchange_request(code, state, storage, js_sys::Date::now()) {
+            Ok(request) => request,
+            Err(err) => return (self, Err(err))
+        };
+
+        let result = match request.await {
+            Ok(tokens) => self.apply_exchange(tokens, &nonce, js_sys::Date::now()),
+            Err(err) => Err(err)
+        };
+
+        match (result, storage) {
+            (Ok(()), Some(store)) => {
+                let result = self.store(store);
+                (self, result)
+            },
+            (result, _) => (self, result)
+        }
+    }
+
+    /// Prepare the exchange of the given authorization code, see [`AuthManager::exchange_token`].
+    /// The returned request does not borrow this instance, so other calls can be served while it is pending.
+    /// Its result has to be passed to [`AuthManager::apply_exchange`] together with the returned nonce.
+    /// The state of the authentication process is removed from the storage, as it can only be used once.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `code`  - The authorization code of the response
+    /// * `state` - The state code of the response
+    /// * `storage` - The [`PersistentStore`] to read the previously generated authorization data from
+    /// * `now` - The current time in milliseconds since the unix epoch
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok((request, Nonce))` - The pending token request and the nonce the id token has to be issued for
+    /// * `Err(AuthError)` - If no authentication was initiated, it expired or the state does not match
+    pub fn exchange_request(
+        &mut self,
+        code: AuthorizationCode,
+        state: CsrfToken,
+        storage: Option<&dyn PersistentStore>,
+        now: f64
+    ) -> Result<(impl Future<Output = Result<OidcTokenResponse, AuthError>> + 'static, Nonce), AuthError> {
+
+        if self.pkce.is_none() {
             if let Some(store) = storage {
-                if let Err(_) = self.load(&store) {
-                    return (
-                        self, 
-                        Err(AuthError::from("Could not load data from given store!"))
-                    )
-                }
-            } else {
-                return (
-                    self, 
-                    Err(AuthError::from("No authentication process was initiated!"))
-                );
+                self.load_at(store, now)?;
             }
         }
-        
-        let (verifier, csrf) = self.pkce.unwrap().destructure();
-        self.pkce = None;
-        
+
+        let (verifier, csrf, nonce) = match self.pkce.take() {
+            Some(pkce) => {
+                self.return_url = pkce.return_url().map(String::from);
+                pkce.destructure()
+            },
+            None => return Err(AuthError::NotInitiated)
+        };
+        if let Some(store) = storage {
+            PKCE::clear(store, &self.keys)?;
+        }
+
         if csrf.secret() != state.secret() {
-            return (
-                self,
-                Err(
-                    AuthError::from("Cross-Site Request Forgery detected! The returned state did not match!")
-                )
-            );
+            if let Some(store) = storage {
+                self.record_security_event(
+                    store,
+                    SecurityEventKind::CsrfMismatch,
+                    "The state returned by the authentication provider did not match the stored one",
+                    now
+                );
+            }
+            return Err(AuthError::CsrfMismatch);
         }
-        let token_result = self.client
-            .exchange_code(code)
-            .set_pkce_verifier(verifier)
-            .request_async(async_http_client)
-            .await;
-
-        self.tokens = match token_result {
-            Ok(tokens) => Some(tokens),
-            Err(err) => {
-                return (
-                    self,
-                    Err(AuthError::from(err.to_string()))
-                )
+
+        let client = self.client.clone();
+        let request = async move {
+            let status = Cell::new(None);
+            client
+                .exchange_code(code)
+                .set_pkce_verifier(verifier)
+                .request_async(|request| keep_status(async_http_client, request, &status))
+                .await
+                .map_err(|err| AuthError::token_exchange_failed(err, status.get()))
+        };
+
+        Ok((request, nonce))
+    }
+
+    /// Verify and hold the tokens received in exchange for the authorization code.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tokens` - The tokens received from the authentication provider
+    /// * `nonce` - The nonce returned by [`AuthManager::exchange_request`]
+    /// * `now` - The time the tokens were received at in milliseconds since the unix epoch
+    pub fn apply_exchange(&mut self, tokens: OidcTokenResponse, nonce: &Nonce, now: f64) -> Result<(), AuthError> {
+
+        let claims = match tokens.id_token() {
+            Some(id_token) => self.verify_id_token(id_token, nonce, now)?,
+            None => return Err(AuthError::MissingIdToken)
+        };
+        self.claims = Some(claims);
+        // The claims fetched for a previous session must not be attributed to the new one
+        self.user_info = None;
+        self.set_tokens(tokens, now);
+
+        Ok(())
+    }
+
+    /// Exchange the stored refresh token for new tokens at the authentication provider,
+    /// to renew the session without a new login.
+    /// If the provider does not rotate the refresh token, the current one is kept.
+    /// 
+    /// # Returns
+    /// 
+    /// * `(Self, Result<(), AuthError>)` - The instance itself and an [`AuthError`] if something fails.
+    /// 
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager; // already authenticated
+    /// let (auth, result) = auth.refresh_tokens().await;
+    /// if let Err(err) = result {
+    ///     // The session could not be renewed, a new login is required
+    /// }
+    /// ```
+    pub async fn refresh_tokens(mut self) -> (Self, Result<(), AuthError>) {
+
+        let request = match self.refresh_request() {
+            Ok(request) => request,
+            Err(err) => return (self, Err(err))
+        };
+
+        let result = match request.await {
+            Ok(tokens) => self.apply_refresh(tokens, js_sys::Date::now()),
+            Err(err) => Err(err)
+        };
+        (self, result)
+    }
+
+    /// Prepare the renewal of the tokens, see [`AuthManager::refresh_tokens`].
+    /// The returned request does not borrow this instance, so the tokens can be renewed in the background.
+    /// Its result has to be passed to [`AuthManager::apply_refresh`].
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(request)` - The pending token request
+    /// * `Err(AuthError)` - If no refresh token is held
+    pub fn refresh_request(&self) -> Result<impl Future<Output = Result<OidcTokenResponse, AuthError>> + 'static, AuthError> {
+
+        let refresh_token = match self.tokens.as_ref().and_then(|tokens| tokens.refresh_token()) {
+            Some(refresh_token) => refresh_token.clone(),
+            None => return Err(AuthError::MissingRefreshToken)
+        };
+
+        let client = self.client.clone();
+        Ok(async move {
+            let status = Cell::new(None);
+            let mut tokens = client
+                .exchange_refresh_token(&refresh_token)
+                .request_async(|request| keep_status(async_http_client, request, &status))
+                .await
+                .map_err(|err| AuthError::token_exchange_failed(err, status.get()))?;
+            if tokens.refresh_token().is_none() {
+                tokens.set_refresh_token(Some(refresh_token));
             }
+            Ok(tokens)
+        })
+    }
+
+    /// The renewal of the tokens in progress, see [`refresh_session`](super::refresh::refresh_session)
+    pub(crate) fn pending_refresh(&self) -> Option<PendingRefresh> {
+        self.pending_refresh.clone()
+    }
+
+    /// Set or clear the renewal of the tokens in progress
+    pub(crate) fn set_pending_refresh(&mut self, pending_refresh: Option<PendingRefresh>) {
+        self.pending_refresh = pending_refresh;
+    }
+
+    /// Verify and hold the renewed tokens.
+    /// A renewed id token has to be issued for the authenticated user, see
+    /// [OpenID Connect Core 12.2](https://openid.net/specs/openid-connect-core-1_0.html#RefreshTokenResponse).
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tokens` - The tokens received from the authentication provider
+    /// * `now` - The time the tokens were received at in milliseconds since the unix epoch
+    pub fn apply_refresh(&mut self, tokens: OidcTokenResponse, now: f64) -> Result<(), AuthError> {
+
+        // The nonce is only contained in the id token of the authentication,
+        // a renewed id token does not need to carry it.
+        if let Some(id_token) = tokens.id_token() {
+            let claims = self.verify_id_token(id_token, |_: Option<&Nonce>| Ok(()), now)?;
+            if self.claims.as_ref().is_some_and(|current| current.subject() != claims.subject()) {
+                return Err(AuthError::SubjectChanged);
+            }
+            self.claims = Some(claims);
+        }
+        self.set_tokens(tokens, now);
+        Ok(())
+    }
+
+    /// Verify the given id token and extract its claims.
+    /// 
+    /// The signature is only checked if the keys of the provider are configured.
+    /// Otherwise it is not needed: The id token is received directly from the token endpoint
+    /// over TLS, which validates the issuer instead, see
+    /// [OpenID Connect Core 3.1.3.7](https://openid.net/specs/openid-connect-core-1_0.html#IDTokenValidation).
+    /// The issuer, the audience, the expiry and the nonce are always checked,
+    /// the issuer against [`ClientData::expected_issuer`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `id_token` - The id token to verify
+    /// * `nonce` - The verifier of the nonce the id token has to be issued for
+    /// * `now` - The time to verify at in milliseconds since the unix epoch
+    fn verify_id_token<N: NonceVerifier>(
+        &self,
+        id_token: &OidcIdToken,
+        nonce: N,
+        now: f64
+    ) -> Result<OidcIdTokenClaims, AuthError> {
+
+        let now = match DateTime::<Utc>::from_timestamp_millis(now as i64) {
+            Some(now) => now,
+            None => return Err(AuthError::InvalidIdToken(String::from("The current time is invalid!")))
         };
+        let mut verifier = self.client.id_token_verifier()
+            .set_time_fn(move || now);
+        if !self.signature_required {
+            verifier = verifier.insecure_disable_signature_check();
+        }
 
-        console_log!("{:?}", self.tokens);
-        print!("{:?}", self.tokens);
-        
-        (self, Ok(()))
+        match id_token.claims(&verifier, nonce) {
+            Ok(claims) => Ok(claims.clone()),
+            Err(err) => Err(AuthError::InvalidIdToken(err.to_string()))
+        }
+    }
+
+    /// Hold the given tokens and track the expiry of the access token.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tokens` - The tokens received from the authentication provider
+    /// * `now` - The time the tokens were received at in milliseconds since the unix epoch
+    fn set_tokens(&mut self, tokens: OidcTokenResponse, now: f64) {
+        self.expires_at = tokens.expires_in()
+            .map(|expires_in| now + expires_in.as_millis() as f64);
+        self.tokens = Some(tokens);
+    }
+
+    /// Check whether an access token is held which is not expired at the given time.
+    /// An access token without known expiry is considered valid.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `now` - The time to check at in milliseconds since the unix epoch
+    pub fn is_authenticated_at(&self, now: f64) -> bool {
+        self.tokens.is_some() && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    /// Check whether an access token is held which is not expired yet.
+    /// 
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager;
+    /// if !auth.is_authenticated() {
+    ///     // refresh the tokens or authenticate again
+    /// }
+    /// ```
+    pub fn is_authenticated(&self) -> bool {
+        self.is_authenticated_at(js_sys::Date::now())
+    }
+
+    /// The remaining lifetime of the access token in seconds at the given time.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `now` - The time to check at in milliseconds since the unix epoch
+    /// 
+    /// # Returns
+    /// 
+    /// * `Some(u32)` - The remaining seconds, `0` if the access token is expired
+    /// * `None` - If no access token is held or its expiry is unknown
+    pub fn expires_in_seconds_at(&self, now: f64) -> Option<u32> {
+        self.tokens.as_ref()?;
+        self.expires_at
+            .map(|expires_at| ((expires_at - now) / 1000.0).max(0.0) as u32)
+    }
+
+    /// The remaining lifetime of the access token in seconds.
+    /// See [`AuthManager::expires_in_seconds_at`].
+    pub fn expires_in_seconds(&self) -> Option<u32> {
+        self.expires_in_seconds_at(js_sys::Date::now())
+    }
+
+    /// The verified claims of the id token of the authenticated user, e.g. the name or email.
+    /// 
+    /// # Returns
+    /// 
+    /// * `Some(&OidcIdTokenClaims)` - The claims of the last id token received
+    /// * `None` - If the user is not authenticated
+    /// 
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager; // already authenticated
+    /// if let Some(claims) = auth.claims() {
+    ///     let subject = claims.subject();
+    /// }
+    /// ```
+    pub fn claims(&self) -> Option<&OidcIdTokenClaims> {
+        self.claims.as_ref()
+    }
+
+    /// The page the user wanted to visit before the authentication,
+    /// as given to [`AuthManager::init_authentication`].
+    /// Known once the authorization code is exchanged, see [`AuthManager::exchange_request`].
+    pub fn return_url(&self) -> Option<&str> {
+        self.return_url.as_deref()
+    }
+
+    /// Get a claim which is not defined by the OpenID Connect standard, see [`CustomClaims::get`](claims::CustomClaims::get).
+    /// The claims fetched from the userinfo endpoint take precedence over the claims of the id token.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `name` - The name of the claim, e.g. `groups` or `realm_access.roles`
+    /// 
+    /// # Returns
+    /// 
+    /// * `Some(&Value)` - The value of the claim
+    /// * `None` - If the user is not authenticated or there is no such claim
+    pub fn custom_claim(&self, name: &str) -> Option<&serde_json::Value> {
+        self.user_info.as_ref()
+            .and_then(|user_info| user_info.additional_claims().get(name))
+            .or_else(|| self.claims.as_ref()?.additional_claims().get(name))
+    }
+
+    /// The claims of the id token, overridden by the claims fetched from the userinfo endpoint.
+    /// 
+    /// # Returns
+    /// 
+    /// * `Some(Value)` - The merged claims as JSON object
+    /// * `None` - If the user is not authenticated
+    pub fn merged_claims(&self) -> Option<serde_json::Value> {
+        let mut merged = serde_json::to_value(self.claims.as_ref()?).ok()?;
+        if let (Some(user_info), serde_json::Value::Object(merged)) = (&self.user_info, &mut merged) {
+            if let Ok(serde_json::Value::Object(user_info)) = serde_json::to_value(user_info) {
+                merged.extend(user_info);
+            }
+        }
+        Some(merged)
+    }
+
+    /// Fetch the claims of the authenticated user from the userinfo endpoint of the provider.
+    /// Some providers put e.g. roles or groups only into the userinfo response, not into the id token.
+    /// 
+    /// # Returns
+    /// 
+    /// * `(Self, Result<Value, AuthError>)` - The instance itself and the merged claims,
+    ///   see [`AuthManager::merged_claims`], or an [`AuthError`] if something fails.
+    /// 
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager; // already authenticated
+    /// let (auth, result) = auth.fetch_user_info().await;
+    /// if let Ok(claims) = result {
+    ///     // use the claims
+    /// }
+    /// ```
+    pub async fn fetch_user_info(mut self) -> (Self, Result<serde_json::Value, AuthError>) {
+
+        let request = match self.user_info_request() {
+            Ok(request) => request,
+            Err(err) => return (self, Err(err))
+        };
+
+        match request.await {
+            Ok(user_info) => {
+                self.apply_user_info(user_info);
+                let claims = self.merged_claims().ok_or(AuthError::NotAuthenticated);
+                (self, claims)
+            },
+            Err(err) => (self, Err(err))
+        }
+    }
+
+    /// Prepare fetching the claims from the userinfo endpoint, see [`AuthManager::fetch_user_info`].
+    /// The returned request does not borrow this instance.
+    /// Its result has to be passed to [`AuthManager::apply_user_info`].
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(request)` - The pending userinfo request
+    /// * `Err(AuthError)` - If the user is not authenticated
+    pub fn user_info_request(&self) -> Result<impl Future<Output = Result<OidcUserInfoClaims, AuthError>> + 'static, AuthError> {
+
+        let access_token = match &self.tokens {
+            Some(tokens) => tokens.access_token().clone(),
+            None => return Err(AuthError::NotAuthenticated)
+        };
+        // The userinfo response has to be about the user of the id token
+        let subject = self.claims.as_ref().map(|claims| claims.subject().clone());
+
+        let client = self.client.clone();
+        Ok(async move {
+            let request = match client.user_info(access_token, subject) {
+                Ok(request) => request,
+                Err(_) => return Err(AuthError::InvalidConfiguration(String::from("No userinfo endpoint is configured!")))
+            };
+            request
+                .request_async(async_http_client)
+                .await
+                .map_err(|err| AuthError::UserInfoFailed(err.to_string()))
+        })
+    }
+
+    /// Hold the claims fetched from the userinfo endpoint.
+    /// They are dropped if they are not about the user of the id token.
+    pub fn apply_user_info(&mut self, user_info: OidcUserInfoClaims) {
+        self.user_info = Some(user_info);
+        self.retain_user_info();
+    }
+
+    /// Drop the claims fetched from the userinfo endpoint, unless they are about the user of the id token.
+    /// Otherwise e.g. the roles of a previous user would be granted to the current one.
+    fn retain_user_info(&mut self) {
+        let same_subject = match (&self.user_info, &self.claims) {
+            (Some(user_info), Some(claims)) => user_info.subject() == claims.subject(),
+            _ => false
+        };
+        if !same_subject {
+            self.user_info = None;
+        }
+    }
+
+    /// Log the user out: drop all tokens, remove the stored authentication data
+    /// and build the URL to end the session at the authentication provider.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `storage` - The [`PersistentStore`] to remove the authentication data from
+    /// * `post_logout_redirect_url` - The URL the provider redirects to after logout.
+    ///   Must be known to the authentication provider
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(Some(Url))` - The URL to navigate to, to end the session at the provider
+    /// * `Ok(None)` - If the provider has no end session endpoint configured
+    /// * `Err(AuthError)` - If the redirect url is invalid or the storage could not be cleared
+    /// 
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager;
+    /// let storage: &dyn PersistentStore; // provided elsewhere
+    /// if let Some(url) = auth.logout(&storage, Some(String::from("https://my.site")))? {
+    ///     // navigate to url
+    /// }
+    /// ```
+    pub fn logout(
+        &mut self,
+        storage: &dyn PersistentStore,
+        post_logout_redirect_url: Option<String>
+    ) -> Result<Option<Url>, AuthError> {
+
+        let redirect = match post_logout_redirect_url.map(|url| Url::parse(&url)) {
+            Some(Ok(url)) => Some(url),
+            Some(Err(_)) => return Err(AuthError::InvalidConfiguration(String::from("The post logout redirect url is not valid!"))),
+            None => None
+        };
+
+        // The provider identifies the session to end by the last id token issued for it
+        let id_token_hint = self.tokens.as_ref()
+            .and_then(|tokens| tokens.id_token())
+            .map(|id_token| id_token.to_string());
+
+        self.tokens = None;
+        self.claims = None;
+        self.user_info = None;
+        self.expires_at = None;
+        self.pkce = None;
+        self.return_url = None;
+        PKCE::clear(storage, &self.keys)?;
+        storage.remove(&self.key(Self::ID_TOKENS))?;
+        storage.remove(&self.key(Self::ID_EXPIRES_AT))?;
+        storage.remove(&self.key(Self::ID_CLAIMS))?;
+        storage.remove(&self.key(Self::ID_USER_INFO))?;
+
+        Ok(self.end_session_request(id_token_hint.as_deref(), redirect.as_ref()))
+    }
+
+    /// Build the URL to end the session at the authentication provider, if supported.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `id_token_hint` - The raw id token of the session to end, if any
+    /// * `post_logout_redirect_url` - The URL the provider redirects to after logout, if any
+    fn end_session_request(&self, id_token_hint: Option<&str>, post_logout_redirect_url: Option<&Url>) -> Option<Url> {
+        let mut url = self.end_session_url.clone()?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("client_id", &self.environment.client_id());
+            if let Some(id_token) = id_token_hint {
+                query.append_pair("id_token_hint", id_token);
+            }
+            if let Some(redirect) = post_logout_redirect_url {
+                query.append_pair("post_logout_redirect_uri", redirect.as_str());
+            }
+        }
+        Some(url)
     }
 
     /// This function is used to retrieve the authorization code and the state token from the authorization response.
+    /// The parameters are read from the query, or from the fragment if the query does not contain them.
     /// 
     /// # Arguments
     /// 
-    /// * `url` - A [`oauth2::url::Url`] containing the response of the authorization provider
+    /// * `url` - A [`openidconnect::url::Url`] containing the response of the authorization provider
     /// 
     /// # Returns
     /// 
     /// * `Ok((AuthorizationCode, CsrfToken))` - Iff the authorization code and the state were present
-    ///                                          and could be retrieved.
+    ///   and could be retrieved.
+    /// * `Err(AuthError::ProviderError)` - If the provider returned an error, e.g. `access_denied`
     /// * `Err(AuthError)` - Otherwise
     /// 
     /// # Example
@@ -271,19 +940,37 @@ impl AuthManager {
     /// ```
     pub fn get_response(url: Url) -> Result<(AuthorizationCode, CsrfToken), AuthError> {
 
-        let queries: HashMap<String, String> = 
+        let mut queries: HashMap<String, String> = 
             url.query_pairs()
             .map(|(key, value)| (key.to_string(), value.to_string()))
             .collect();
+
+        // Some providers and response modes return the parameters in the fragment instead
+        if !queries.contains_key(Self::URL_AUTH_CODE) && !queries.contains_key(Self::URL_ERROR) {
+            if let Some(fragment) = url.fragment() {
+                queries.extend(
+                    form_urlencoded::parse(fragment.as_bytes())
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                );
+            }
+        }
+
         if queries.is_empty() {
-            return Err(AuthError::from("No response is present in the given url!"))
+            return Err(AuthError::InvalidResponse(String::from("No response is present in the given url!")))
+        }
+
+        if let Some(error) = queries.get(Self::URL_ERROR) {
+            return Err(AuthError::ProviderError {
+                error: error.clone(),
+                description: queries.get(Self::URL_ERROR_DESCRIPTION).cloned()
+            })
         }
         
         let auth_code: AuthorizationCode = match queries.get(Self::URL_AUTH_CODE) {
 
             Some(code) => AuthorizationCode::new(String::from(code)),
             None => {
-                return Err(AuthError::from("There was no authorization code present in the provided url!"))
+                return Err(AuthError::InvalidResponse(String::from("There was no authorization code present in the provided url!")))
             }
         };
 
@@ -291,33 +978,609 @@ impl AuthManager {
 
             Some(token) => CsrfToken::new(String::from(token)),
             None => {
-                return Err(AuthError::from("There was no state present in the provided url!"))
+                return Err(AuthError::InvalidResponse(String::from("There was no state present in the provided url!")))
             }
         };
 
         Ok((auth_code, state))
     }
 
-    // TODO: Remove this function since it is disabling any security regarding the access token
-    //       Debugging only!
-    // 
-    // pub fn access_token(&self) -> Result<&String, AuthError> {
-    //     match &self.tokens {
-    //         Some(tokens) => {
-    //             Ok(tokens.access_token().secret())
-    //         }
-    //         None => {
-    //             Err(AuthError::new(String::from("No access token available!")))
-    //         }
-    //     }
-    // }
+    /// Query the security relevant events recorded in the given storage, oldest first.
+    /// The events of all clients sharing the storage namespace are returned.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `storage` - The [`PersistentStore`] the events are recorded in
+    /// * `kind` - Only return events of this [`SecurityEventKind`], if given
+    /// 
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager;
+    /// for event in auth.security_events(storage, Some(SecurityEventKind::CsrfMismatch)) {
+    ///     // report the event
+    /// }
+    /// ```
+    pub fn security_events(&self, storage: &dyn PersistentStore, kind: Option<SecurityEventKind>) -> Vec<SecurityEvent> {
+        SecurityLog::load(storage, &self.security_keys).events(kind)
+    }
+
+    /// Record a security relevant event in the given storage, see [`SecurityLog::record_in`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `storage` - The [`PersistentStore`] to record the event in
+    /// * `kind` - The [`SecurityEventKind`] of the event
+    /// * `detail` - A description of the event
+    /// * `now` - The time the event occurred at in milliseconds since the unix epoch
+    pub fn record_security_event(&self, storage: &dyn PersistentStore, kind: SecurityEventKind, detail: &str, now: f64) {
+        SecurityLog::record_in(storage, &self.security_keys, kind, detail, now);
+    }
+
+    /// Describe the authentication provider and client this instance is configured for.
+    /// 
+    /// # Returns
+    /// 
+    /// * [`Environment`] - The configured environment
+    pub fn environment(&self) -> Environment {
+        self.environment.clone()
+    }
+
+    /// The access token to authorize requests to the backend with.
+    /// Not exposed to JS, requests are sent by the [`ApiClient`](super::ApiClient).
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(&AccessToken)` - The current access token
+    /// * `Err(AuthError)` - If the user is not authenticated
+    pub(crate) fn access_token(&self) -> Result<&AccessToken, AuthError> {
+        match &self.tokens {
+            Some(tokens) => Ok(tokens.access_token()),
+            None => Err(AuthError::NotAuthenticated)
+        }
+    }
 
 }
 
+/// Send a request to the token endpoint with the given HTTP client,
+/// keeping the HTTP status of the response for a failed exchange, see [`AuthError::TokenExchangeFailed`]
+///
+/// # Arguments
+///
+/// * `http_client` - The HTTP client to send the request with, e.g. [`async_http_client`]
+/// * `request` - The request to the token endpoint
+/// * `status` - Set to the HTTP status of the response, if one is received
+async fn keep_status<C, F, RE>(http_client: C, request: HttpRequest, status: &Cell<Option<u16>>) -> Result<HttpResponse, RE>
+where
+    C: FnOnce(HttpRequest) -> F,
+    F: Future<Output = Result<HttpResponse, RE>>
+{
+    let response = http_client(request).await?;
+    status.set(Some(response.status_code.as_u16()));
+    Ok(response)
+}
+
 // ********************** Unit Tests *************************
 
 #[cfg(test)]
 mod tests {
 
+    use super::*;
+    use crate::controller::{
+        MemoryStore,
+        Roles
+    };
+    use futures::executor::block_on;
+    use openidconnect::{
+        EmptyExtraTokenFields,
+        IdTokenFields
+    };
+
+    fn client_data(auth_url: &str, client_id: &str) -> ClientData {
+        ClientData::from(
+            String::from(auth_url),
+            String::from("https://auth_provider.org/token"),
+            String::from(client_id),
+            String::from("https://my.site")
+        ).ok().unwrap()
+    }
+
+    #[test]
+    fn storage_keys_differ_per_client() {
+        let first = AuthManager::new(client_data("https://first.org/auth", "client"));
+        let second = AuthManager::new(client_data("https://second.org/auth", "client"));
+        let third = AuthManager::new(client_data("https://first.org/auth", "other-client"));
+        assert_ne!(first.keys, second.keys);
+        assert_ne!(first.keys, third.keys);
+        assert_eq!(first.key(AuthManager::ID_TOKENS), "kifapwa.client@https://first.org/auth.tokens");
+
+        let namespaced = AuthManager::new(client_data("https://first.org/auth", "client").with_storage_namespace(String::from("panel")));
+        assert_eq!(namespaced.key(AuthManager::ID_TOKENS), "panel.client@https://first.org/auth.tokens");
+    }
+
+    fn tokens(expires_in: Option<u64>) -> OidcTokenResponse {
+        let mut tokens = OidcTokenResponse::new(
+            openidconnect::AccessToken::new(String::from("access")),
+            openidconnect::core::CoreTokenType::Bearer,
+            IdTokenFields::new(None, EmptyExtraTokenFields {})
+        );
+        tokens.set_expires_in(expires_in.map(std::time::Duration::from_secs).as_ref());
+        tokens
+    }
+
+    #[test]
+    fn tracks_expiry() {
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert!(!auth.is_authenticated_at(0.0));
+        assert_eq!(auth.expires_in_seconds_at(0.0), None);
+
+        auth.set_tokens(tokens(Some(300)), 1000.0);
+        assert!(auth.is_authenticated_at(1000.0));
+        assert_eq!(auth.expires_in_seconds_at(61_000.0), Some(240));
+        assert!(!auth.is_authenticated_at(301_000.0));
+        assert_eq!(auth.expires_in_seconds_at(400_000.0), Some(0));
+    }
+
+    #[test]
+    fn unknown_expiry_is_valid() {
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        auth.set_tokens(tokens(None), 1000.0);
+        assert!(auth.is_authenticated_at(f64::MAX));
+        assert_eq!(auth.expires_in_seconds_at(1000.0), None);
+    }
+
+    #[test]
+    fn end_session_request() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert_eq!(auth.end_session_request(Some("token"), None), None);
+
+        let data = client_data("https://auth_provider.org/auth", "client")
+            .with_end_session_url(String::from("https://auth_provider.org/logout"))
+            .ok()
+            .unwrap();
+        let auth = AuthManager::new(data);
+        let redirect = Url::parse("https://my.site/").unwrap();
+        assert_eq!(
+            auth.end_session_request(None, Some(&redirect)).unwrap().as_str(),
+            "https://auth_provider.org/logout?client_id=client&post_logout_redirect_uri=https%3A%2F%2Fmy.site%2F"
+        );
+        assert_eq!(
+            auth.end_session_request(Some("header.claims.signature"), Some(&redirect)).unwrap().as_str(),
+            "https://auth_provider.org/logout?client_id=client&id_token_hint=header.claims.signature&post_logout_redirect_uri=https%3A%2F%2Fmy.site%2F"
+        );
+    }
+
+    #[test]
+    fn logout_sends_id_token_hint() {
+        let data = client_data("https://auth_provider.org/auth", "client")
+            .with_end_session_url(String::from("https://auth_provider.org/logout"))
+            .ok()
+            .unwrap();
+        let mut auth = AuthManager::new(data);
+        let id_token = id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"user","iat":0,"exp":2}"#);
+        let raw = id_token.to_string();
+        let mut tokens = tokens(Some(300));
+        tokens.set_extra_fields(IdTokenFields::new(Some(id_token), EmptyExtraTokenFields {}));
+        auth.set_tokens(tokens, 1000.0);
+
+        let url = auth.logout(&MemoryStore::new(), None).unwrap().unwrap();
+        let hint = url.query_pairs().find(|(key, _)| key == "id_token_hint").map(|(_, value)| value.into_owned());
+        assert_eq!(hint, Some(raw));
+        assert!(auth.tokens.is_none());
+    }
+
+    #[test]
+    fn init_authentication_stores_state() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let url = auth.init_authentication_at(&store, None, 1000.0).unwrap();
+        assert!(url.as_str().starts_with("https://auth_provider.org/auth?"));
+        assert!(url.as_str().contains("nonce="));
+        assert!(url.as_str().contains("scope=openid&"));
+        assert_eq!(store.len(), 4);
+
+        // A new instance, e.g. after the redirect, picks up the stored state
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert!(restored.load_at(&store, 1000.0).is_ok());
+
+        restored.logout(&store, None).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn merges_user_info_into_claims() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert_eq!(auth.merged_claims(), None);
+
+        auth.claims = Some(
+            auth.verify_id_token(
+                &id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"user","iat":0,"exp":2,"email":"old@kit.edu"}"#),
+                |_: Option<&Nonce>| Ok(()),
+                1000.0
+            ).unwrap()
+        );
+        auth.apply_user_info(
+            OidcUserInfoClaims::from_json::<std::io::Error>(
+                br#"{"sub":"user","email":"user@kit.edu","groups":["admins"]}"#,
+                None
+            ).unwrap()
+        );
+        let merged = auth.merged_claims().unwrap();
+        assert_eq!(merged["email"], "user@kit.edu");
+        assert_eq!(merged["iss"], "https://auth_provider.org");
+
+        auth.store(&store).unwrap();
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        restored.load_at(&store, 1000.0).unwrap();
+        assert_eq!(restored.merged_claims(), Some(merged));
+    }
+
+    #[test]
+    fn reads_custom_claims() {
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert_eq!(auth.custom_claim("groups"), None);
+
+        auth.claims = Some(
+            auth.verify_id_token(
+                &id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"user","iat":0,"exp":2,"groups":["users"],"realm_access":{"roles":["admin"]}}"#),
+                |_: Option<&Nonce>| Ok(()),
+                1000.0
+            ).unwrap()
+        );
+        assert_eq!(auth.custom_claim("realm_access.roles"), Some(&serde_json::json!(["admin"])));
+        assert_eq!(auth.custom_claim("sub"), None);
+
+        auth.apply_user_info(
+            OidcUserInfoClaims::from_json::<std::io::Error>(br#"{"sub":"user","groups":["admins"]}"#, None).unwrap()
+        );
+        assert_eq!(auth.custom_claim("groups"), Some(&serde_json::json!(["admins"])));
+    }
+
+    #[test]
+    fn new_login_drops_user_info_of_previous_user() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        auth.claims = Some(
+            auth.verify_id_token(
+                &id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"alice","iat":0,"exp":2}"#),
+                |_: Option<&Nonce>| Ok(()),
+                1000.0
+            ).unwrap()
+        );
+        auth.set_tokens(tokens(Some(300)), 1000.0);
+        auth.apply_user_info(
+            OidcUserInfoClaims::from_json::<std::io::Error>(br#"{"sub":"alice","roles":["admin"]}"#, None).unwrap()
+        );
+        auth.store(&store).unwrap();
+        assert_eq!(Roles::of(&auth).names(), vec![String::from("admin")]);
+
+        // bob logs in without alice logging out before
+        let mut tokens = tokens(Some(300));
+        tokens.set_extra_fields(IdTokenFields::new(
+            Some(id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"bob","iat":0,"exp":2,"nonce":"nonce"}"#)),
+            EmptyExtraTokenFields {}
+        ));
+        auth.apply_exchange(tokens, &Nonce::new(String::from("nonce")), 1000.0).unwrap();
+        auth.store(&store).unwrap();
+        assert!(Roles::of(&auth).names().is_empty());
+        assert!(store.get(&auth.key(AuthManager::ID_USER_INFO)).unwrap().is_none());
+
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        restored.load_at(&store, 1000.0).unwrap();
+        assert_eq!(restored.claims().unwrap().subject().as_str(), "bob");
+        assert!(Roles::of(&restored).names().is_empty());
+    }
+
+    #[test]
+    fn user_info_of_other_subject_is_dropped() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        auth.claims = Some(
+            auth.verify_id_token(
+                &id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"bob","iat":0,"exp":2}"#),
+                |_: Option<&Nonce>| Ok(()),
+                1000.0
+            ).unwrap()
+        );
+        auth.store(&store).unwrap();
+        store.set(&auth.key(AuthManager::ID_USER_INFO), r#"{"sub":"alice","roles":["admin"]}"#).unwrap();
+
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        restored.load_at(&store, 1000.0).unwrap();
+        assert_eq!(restored.custom_claim("roles"), None);
+
+        restored.apply_user_info(
+            OidcUserInfoClaims::from_json::<std::io::Error>(br#"{"sub":"alice","roles":["admin"]}"#, None).unwrap()
+        );
+        assert_eq!(restored.custom_claim("roles"), None);
+    }
+
+    #[test]
+    fn user_info_requires_authentication() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert_eq!(auth.user_info_request().err(), Some(AuthError::NotAuthenticated));
+    }
+
+    #[test]
+    fn requests_configured_scopes() {
+        let data = client_data("https://auth_provider.org/auth", "client")
+            .with_scopes(vec![String::from("roles"), String::from("offline_access")]);
+        let mut auth = AuthManager::new(data);
+        let url = auth.init_authentication_at(&MemoryStore::new(), None, 1000.0).unwrap();
+        assert!(url.as_str().contains("scope=openid+roles+offline_access"));
+    }
+
+    #[test]
+    fn tokens_survive_reload() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let mut tokens = tokens(Some(300));
+        tokens.set_refresh_token(Some(openidconnect::RefreshToken::new(String::from("refresh"))));
+        auth.set_tokens(tokens, 1000.0);
+        auth.claims = Some(
+            auth.verify_id_token(
+                &id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"user","iat":0,"exp":2}"#),
+                |_: Option<&Nonce>| Ok(()),
+                1000.0
+            ).unwrap()
+        );
+        auth.store(&store).unwrap();
+
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        restored.load_at(&store, 1000.0).unwrap();
+        assert!(restored.is_authenticated_at(1000.0));
+        assert_eq!(restored.expires_in_seconds_at(1000.0), Some(300));
+        assert_eq!(
+            restored.tokens.unwrap().refresh_token().unwrap().secret(),
+            "refresh"
+        );
+        assert_eq!(restored.claims.unwrap().subject().as_str(), "user");
+
+        auth.logout(&store, None).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn corrupted_tokens_are_rejected() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        store.set(&auth.key(AuthManager::ID_TOKENS), "{").unwrap();
+        assert_eq!(auth.load_at(&store, 1000.0).err().map(|err| err.code()), Some("corrupted_state"));
+    }
+
+    /// Build an unsigned id token carrying the given claims
+    fn id_token(claims: &str) -> OidcIdToken {
+        let encode = |part: &str| base64::encode_config(part, base64::URL_SAFE_NO_PAD);
+        format!("{}.{}.{}", encode(r#"{"alg":"RS256"}"#), encode(claims), encode("signature"))
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn checks_signature_with_configured_keys() {
+        let data = client_data("https://auth_provider.org/auth", "client")
+            .with_jwks(String::from(r#"{"keys":[{"kty":"RSA","use":"sig","kid":"key","n":"AQAB","e":"AQAB"}]}"#))
+            .ok().unwrap();
+        let auth = AuthManager::new(data);
+        let id_token = id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"user","iat":0,"exp":2}"#);
+
+        let result = auth.verify_id_token(&id_token, |_: Option<&Nonce>| Ok(()), 1000.0);
+        assert!(matches!(result, Err(AuthError::InvalidIdToken(_))));
+    }
+
+    #[test]
+    fn verifies_id_token() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let id_token = id_token(r#"{
+            "iss": "https://auth_provider.org",
+            "aud": ["client"],
+            "sub": "user",
+            "iat": 1000,
+            "exp": 1300,
+            "nonce": "nonce",
+            "email": "user@kit.edu"
+        }"#);
+        let nonce = Nonce::new(String::from("nonce"));
+
+        let claims = auth.verify_id_token(&id_token, &nonce, 1_000_000.0).unwrap();
+        assert_eq!(claims.subject().as_str(), "user");
+        assert_eq!(claims.email().unwrap().as_str(), "user@kit.edu");
+
+        let other = Nonce::new(String::from("other"));
+        assert!(auth.verify_id_token(&id_token, &other, 1_000_000.0).is_err());
+        assert!(auth.verify_id_token(&id_token, &nonce, 1_400_000.0).is_err());
+    }
+
+    #[test]
+    fn rejects_id_token_of_other_client() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let id_token = id_token(r#"{
+            "iss": "https://auth_provider.org",
+            "aud": ["other-client"],
+            "sub": "user",
+            "iat": 1000,
+            "exp": 1300
+        }"#);
+        assert!(auth.verify_id_token(&id_token, |_: Option<&Nonce>| Ok(()), 1_000_000.0).is_err());
+    }
+
+    #[test]
+    fn rejects_id_token_of_other_issuer() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let other_issuer = id_token(r#"{"iss":"https://attacker.org","aud":["client"],"sub":"user","iat":0,"exp":2}"#);
+        assert!(matches!(
+            auth.verify_id_token(&other_issuer, |_: Option<&Nonce>| Ok(()), 1000.0),
+            Err(AuthError::InvalidIdToken(_))
+        ));
+
+        let data = client_data("https://auth_provider.org/auth", "client")
+            .with_issuer(String::from("https://auth_provider.org/realms/kit"))
+            .ok()
+            .unwrap();
+        let auth = AuthManager::new(data);
+        let origin_issuer = id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"user","iat":0,"exp":2}"#);
+        assert!(auth.verify_id_token(&origin_issuer, |_: Option<&Nonce>| Ok(()), 1000.0).is_err());
+    }
+
+    #[test]
+    fn csrf_mismatch_is_recorded_in_storage() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        auth.init_authentication_at(&store, None, 1000.0).unwrap();
+
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let result = restored.exchange_request(
+            AuthorizationCode::new(String::from("code")),
+            CsrfToken::new(String::from("forged")),
+            Some(&store),
+            2000.0
+        );
+        assert_eq!(result.err(), Some(AuthError::CsrfMismatch));
+
+        // Another client of the namespace, e.g. after switching the provider, sees the event
+        let other = AuthManager::new(client_data("https://other.org/auth", "other-client"));
+        let events = other.security_events(&store, Some(SecurityEventKind::CsrfMismatch));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].timestamp, 2000.0);
+    }
+
+    #[test]
+    fn refresh_for_other_subject_is_rejected() {
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        auth.claims = Some(
+            auth.verify_id_token(
+                &id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"alice","iat":0,"exp":2}"#),
+                |_: Option<&Nonce>| Ok(()),
+                1000.0
+            ).unwrap()
+        );
+        let mut tokens = tokens(Some(300));
+        tokens.set_extra_fields(IdTokenFields::new(
+            Some(id_token(r#"{"iss":"https://auth_provider.org","aud":["client"],"sub":"bob","iat":0,"exp":2}"#)),
+            EmptyExtraTokenFields {}
+        ));
+        assert_eq!(auth.apply_refresh(tokens, 1000.0), Err(AuthError::SubjectChanged));
+        assert_eq!(auth.claims().unwrap().subject().as_str(), "alice");
+    }
 
+    #[test]
+    fn exchange_without_initiation_fails() {
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let result = auth.exchange_request(
+            AuthorizationCode::new(String::from("code")),
+            CsrfToken::new(String::from("state")),
+            Some(&MemoryStore::new()),
+            1000.0
+        );
+        assert_eq!(result.err(), Some(AuthError::NotInitiated));
+    }
+
+    #[test]
+    fn expired_authentication_is_rejected() {
+        let store = MemoryStore::new();
+        let data = client_data("https://auth_provider.org/auth", "client").with_login_timeout(60);
+        let mut auth = AuthManager::new(data.clone());
+        let url = auth.init_authentication_at(&store, None, 1000.0).unwrap();
+        let state = url.query_pairs().find(|(key, _)| key == "state").unwrap().1.into_owned();
+
+        let mut restored = AuthManager::new(data);
+        let result = restored.exchange_request(
+            AuthorizationCode::new(String::from("code")),
+            CsrfToken::new(state),
+            Some(&store),
+            62_000.0
+        );
+        assert_eq!(result.err(), Some(AuthError::NotInitiated));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn restores_return_url_after_redirect() {
+        let store = MemoryStore::new();
+        let mut auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let url = auth.init_authentication_at(&store, Some(String::from("https://my.site/users/42")), 1000.0).unwrap();
+        let state = url.query_pairs().find(|(key, _)| key == "state").unwrap().1.into_owned();
+
+        let mut restored = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        assert_eq!(restored.return_url(), None);
+        let result = restored.exchange_request(
+            AuthorizationCode::new(String::from("code")),
+            CsrfToken::new(state),
+            Some(&store),
+            2000.0
+        );
+        assert!(result.is_ok());
+        assert_eq!(restored.return_url(), Some("https://my.site/users/42"));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn parses_response() {
+        let url = Url::parse("https://my.site/?state=abc123&code=qwert12345").unwrap();
+        let (code, state) = AuthManager::get_response(url).unwrap();
+        assert_eq!(code.secret(), "qwert12345");
+        assert_eq!(state.secret(), "abc123");
+
+        let url = Url::parse("https://my.site/?state=abc123").unwrap();
+        assert_eq!(AuthManager::get_response(url).err().map(|err| err.code()), Some("invalid_response"));
+    }
+
+    #[test]
+    fn parses_response_from_fragment() {
+        let url = Url::parse("https://my.site/?tab=users#state=abc123&code=qwert12345").unwrap();
+        let (code, state) = AuthManager::get_response(url).unwrap();
+        assert_eq!(code.secret(), "qwert12345");
+        assert_eq!(state.secret(), "abc123");
+
+        let url = Url::parse("https://my.site/#error=access_denied").unwrap();
+        assert_eq!(AuthManager::get_response(url).err().map(|err| err.code()), Some("provider_error"));
+    }
+
+    #[test]
+    fn parses_provider_error() {
+        let url = Url::parse("https://my.site/?error=access_denied&error_description=User+denied&state=abc123").unwrap();
+        assert_eq!(
+            AuthManager::get_response(url).err(),
+            Some(AuthError::ProviderError {
+                error: String::from("access_denied"),
+                description: Some(String::from("User denied"))
+            })
+        );
+    }
+
+    #[test]
+    fn refresh_without_tokens_fails() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let (_, result) = block_on(auth.refresh_tokens());
+        assert_eq!(result, Err(AuthError::MissingRefreshToken));
+    }
+
+    #[test]
+    fn failed_exchange_keeps_status() {
+        let auth = AuthManager::new(client_data("https://auth_provider.org/auth", "client"));
+        let provider = |_: HttpRequest| async {
+            let mut headers = openidconnect::http::HeaderMap::new();
+            headers.insert(
+                openidconnect::http::header::CONTENT_TYPE,
+                openidconnect::http::HeaderValue::from_static("application/json")
+            );
+            Ok::<_, std::io::Error>(HttpResponse {
+                status_code: openidconnect::http::StatusCode::BAD_REQUEST,
+                headers,
+                body: br#"{"error":"invalid_grant","error_description":"Token is not active"}"#.to_vec()
+            })
+        };
+
+        let status = Cell::new(None);
+        let result = block_on(auth.client
+            .exchange_refresh_token(&openidconnect::RefreshToken::new(String::from("refresh")))
+            .request_async(|request| keep_status(provider, request, &status)));
+        match AuthError::token_exchange_failed(result.unwrap_err(), status.get()) {
+            AuthError::TokenExchangeFailed { status, body } => {
+                assert_eq!(status, Some(400));
+                assert!(body.contains("invalid_grant"));
+            },
+            other => panic!("unexpected error {:?}", other)
+        }
+    }
 }
\ No newline at end of file
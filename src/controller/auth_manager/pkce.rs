@@ -1,112 +1,203 @@
-/// SPDX-License-Identifier: MIT
-/// SPDX-License-Identifier: APACHE
-/// 
-/// 2022, Patrick Schneider <patrick@itermori.de>
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
 
-use wasm_bindgen::prelude::*;
-use web_sys::Storage;
-use oauth2::{
+use openidconnect::{
     CsrfToken,
+    Nonce,
     PkceCodeVerifier
 };
 
 use super::AuthError;
+use crate::controller::{
+    PersistentStore,
+    StorageKeys
+};
 
 /// The PKCE structs holds the data involved in the authentication process
 /// 
+#[allow(clippy::upper_case_acronyms)]
 pub struct PKCE {
 
     /// The verifier used to verify the response of the authentication process
     verifier: PkceCodeVerifier,
 
     /// The csrf token involved in the authentication process
-    csrf: CsrfToken
+    csrf: CsrfToken,
+
+    /// The nonce the id token has to be issued for
+    nonce: Nonce,
+
+    /// The page to return to after the authentication, if any
+    return_url: Option<String>,
+
+    /// The time the authentication was initiated at in milliseconds since the unix epoch
+    created_at: f64
 }
 
 impl PKCE {
     const ID_VERIFIER: &'static str = "verifier";
     const ID_CSRF: &'static str = "csrf";
+    const ID_NONCE: &'static str = "nonce";
+    const ID_RETURN_URL: &'static str = "return_url";
+    const ID_CREATED_AT: &'static str = "created_at";
 }
 
 impl PKCE {
 
     /// Create a new pkce instance with default values
     /// 
+    /// # Arguments
+    /// 
+    /// * `verifier` - The verifier of the PKCE challenge
+    /// * `csrf` - The csrf token sent as state
+    /// * `nonce` - The nonce the id token has to be issued for
+    /// * `created_at` - The time the authentication is initiated at in milliseconds since the unix epoch
+    /// 
     /// # Example
     /// ```rust
-    /// let pkce: PKCE = PKCE::new()
+    /// let pkce: PKCE = PKCE::new(verifier, csrf, nonce, js_sys::Date::now())
     /// ```
-    pub fn new(verifier: PkceCodeVerifier, csrf: CsrfToken) -> Self {
+    pub fn new(verifier: PkceCodeVerifier, csrf: CsrfToken, nonce: Nonce, created_at: f64) -> Self {
         PKCE {
             verifier,
-            csrf
+            csrf,
+            nonce,
+            return_url: None,
+            created_at
         }
     }
 
+    /// Set the page to return to after the authentication.
+    /// Consumes this instance!
+    /// 
+    /// # Arguments
+    /// 
+    /// * `return_url` - The URL of the page, if any
+    pub fn with_return_url(mut self, return_url: Option<String>) -> Self {
+        self.return_url = return_url;
+        self
+    }
+
+    /// The page to return to after the authentication, if any
+    pub fn return_url(&self) -> Option<&str> {
+        self.return_url.as_deref()
+    }
+
     /// Store the state of the pkce in the provided storage.
     /// 
     /// # Arguments
     /// 
-    /// * `storage` - A [`Storage`](web_sys::Storage) to store the content
+    /// * `storage` - A [`PersistentStore`] to store the content
+    /// * `keys` - The [`StorageKeys`] scoping the state to the owning instance
     /// 
     /// # Returns
     /// 
     /// * `Ok(())` - State could be stored
-    /// * `Err(JsValue)` - State could not be stored
+    /// * `Err(AuthError)` - State could not be stored
     /// 
     /// # Example
     /// 
     /// ```rust
     /// // The storage is provided elsewhere
-    /// let storage: Storage;
+    /// let storage: &dyn PersistentStore;
     /// let pkce = PKCE::new()
-    /// if let Err(err) = pkce.store(storage) {
+    /// if let Err(err) = pkce.store(storage, &keys) {
     ///     // handle error
     /// }
     /// ```
-    pub fn store(&self, storage: &Storage) -> Result<(), JsValue> {
+    pub fn store(&self, storage: &dyn PersistentStore, keys: &StorageKeys) -> Result<(), AuthError> {
 
-        storage.set(PKCE::ID_VERIFIER, &self.verifier.secret())?;
-        storage.set(PKCE::ID_CSRF, &self.csrf.secret())?;
+        storage.set(&keys.key(PKCE::ID_VERIFIER), self.verifier.secret())?;
+        storage.set(&keys.key(PKCE::ID_CSRF), self.csrf.secret())?;
+        storage.set(&keys.key(PKCE::ID_NONCE), self.nonce.secret())?;
+        storage.set(&keys.key(PKCE::ID_CREATED_AT), &self.created_at.to_string())?;
+        match &self.return_url {
+            Some(return_url) => storage.set(&keys.key(PKCE::ID_RETURN_URL), return_url)?,
+            None => storage.remove(&keys.key(PKCE::ID_RETURN_URL))?
+        }
         Ok(())
     }
 
     /// Loads the state of the pkce from the provided storage.
     /// Only set state will be loaded.
+    /// State older than the given age, e.g. of an abandoned authentication, is removed instead.
     /// 
     /// # Arguments
     /// 
-    /// * `storage` - A [`Storage`](web_sys::Storage) to load the content
+    /// * `storage` - A [`PersistentStore`] to load the content
+    /// * `keys` - The [`StorageKeys`] scoping the state to the owning instance
+    /// * `now` - The current time in milliseconds since the unix epoch
+    /// * `max_age` - The age in milliseconds after which the state expires
     /// 
     /// # Returns
     /// 
-    /// * `Ok(())` - State could be loaded
-    /// * `Err(JsValue)` - State could not be loaded
+    /// * `Ok(Some(PKCE))` - State could be loaded
+    /// * `Ok(None)` - No state is stored or it expired
+    /// * `Err(AuthError)` - State could not be loaded
     /// 
     /// # Example
     /// 
     /// ```rust
     /// // The storage is provided elsewhere
     /// // and contains some stored values from pkce
-    /// let storage: Storage;
-    /// let pkce = PKCE::new()
-    /// if let Err(err) = pkce.store(storage) {
-    ///     // handle error
+    /// let storage: &dyn PersistentStore;
+    /// if let Some(pkce) = PKCE::load_from(storage, &keys, js_sys::Date::now(), 600_000.0)? {
+    ///     // continue the authentication process
     /// }
     /// ```
-    pub fn load_from(storage: &Storage) -> Result<PKCE, JsValue> {
+    pub fn load_from(
+        storage: &dyn PersistentStore,
+        keys: &StorageKeys,
+        now: f64,
+        max_age: f64
+    ) -> Result<Option<PKCE>, AuthError> {
 
-        let (verifier, csrf) = match (
-            storage.get(PKCE::ID_VERIFIER),
-            storage.get(PKCE::ID_CSRF)
+        let (verifier, csrf, nonce) = match (
+            storage.get(&keys.key(PKCE::ID_VERIFIER))?,
+            storage.get(&keys.key(PKCE::ID_CSRF))?,
+            storage.get(&keys.key(PKCE::ID_NONCE))?
         ) {
-            (Ok(Some(verifier)), Ok(Some(csrf))) => {
-                (PkceCodeVerifier::new(verifier), CsrfToken::new(csrf))
+            (Some(verifier), Some(csrf), Some(nonce)) => {
+                (PkceCodeVerifier::new(verifier), CsrfToken::new(csrf), Nonce::new(nonce))
             },
-            (Ok(None), _) | (_, Ok(None)) => return Err(JsValue::from(AuthError::from("No authentication data in storage found!"))),
-            (Err(e), _) | (_, Err(e)) => return Err(e)
+            _ => return Ok(None)
+        };
+
+        // State without a time stems from a version not recording it and is treated as expired
+        let created_at = match storage.get(&keys.key(PKCE::ID_CREATED_AT))?
+            .and_then(|created_at| created_at.parse::<f64>().ok())
+        {
+            Some(created_at) if now - created_at <= max_age => created_at,
+            _ => {
+                PKCE::clear(storage, keys)?;
+                return Ok(None);
+            }
         };
-        Ok(PKCE::new(verifier, csrf))
+        let return_url = storage.get(&keys.key(PKCE::ID_RETURN_URL))?;
+        Ok(Some(PKCE::new(verifier, csrf, nonce, created_at).with_return_url(return_url)))
+    }
+
+    /// Remove the state of the pkce from the provided storage.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `storage` - A [`PersistentStore`] to remove the content from
+    /// * `keys` - The [`StorageKeys`] scoping the state to the owning instance
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(())` - State could be removed
+    /// * `Err(AuthError)` - State could not be removed
+    pub fn clear(storage: &dyn PersistentStore, keys: &StorageKeys) -> Result<(), AuthError> {
+
+        storage.remove(&keys.key(PKCE::ID_VERIFIER))?;
+        storage.remove(&keys.key(PKCE::ID_CSRF))?;
+        storage.remove(&keys.key(PKCE::ID_NONCE))?;
+        storage.remove(&keys.key(PKCE::ID_RETURN_URL))?;
+        storage.remove(&keys.key(PKCE::ID_CREATED_AT))?;
+        Ok(())
     }
 
     /// Destructure this pkce data into its components to use.
@@ -114,20 +205,20 @@ impl PKCE {
     /// 
     /// # Returns
     /// 
-    /// * `(PkceCodeVerifier, CsrfToken)` - The used verifier and csrf token.
+    /// * `(PkceCodeVerifier, CsrfToken, Nonce)` - The used verifier, csrf token and nonce.
     /// 
     /// # Example 
     /// ```rust
-    /// let pkce = PKCE::new(verifier, csrf);
+    /// let pkce = PKCE::new(verifier, csrf, nonce, js_sys::Date::now());
     /// 
-    /// // Cannot use verifier and csrf here due to move
+    /// // Cannot use verifier, csrf and nonce here due to move
     /// 
-    /// let (verifier, csrf) = pkce.destructure;
+    /// let (verifier, csrf, nonce) = pkce.destructure;
     /// 
-    /// // Can use verifer and csrf here, but not pkce anymore
+    /// // Can use verifer, csrf and nonce here, but not pkce anymore
     /// ```
-    pub fn destructure(self) -> (PkceCodeVerifier, CsrfToken) {
-        (self.verifier, self.csrf)
+    pub fn destructure(self) -> (PkceCodeVerifier, CsrfToken, Nonce) {
+        (self.verifier, self.csrf, self.nonce)
     }
 }
 
@@ -137,4 +228,58 @@ impl PKCE {
 mod tests {
 
     use super::*;
-}
\ No newline at end of file
+    use crate::controller::MemoryStore;
+
+    #[test]
+    fn destructure_returns_components() {
+        let pkce = PKCE::new(
+            PkceCodeVerifier::new(String::from("verifier")),
+            CsrfToken::new(String::from("csrf")),
+            Nonce::new(String::from("nonce")),
+            1000.0
+        );
+        let (verifier, csrf, nonce) = pkce.destructure();
+        assert_eq!(verifier.secret(), "verifier");
+        assert_eq!(csrf.secret(), "csrf");
+        assert_eq!(nonce.secret(), "nonce");
+    }
+
+    #[test]
+    fn store_and_load() {
+        let store = MemoryStore::new();
+        let keys = StorageKeys::new("kifapwa", "client");
+        let pkce = PKCE::new(
+            PkceCodeVerifier::new(String::from("verifier")),
+            CsrfToken::new(String::from("csrf")),
+            Nonce::new(String::from("nonce")),
+            1000.0
+        ).with_return_url(Some(String::from("https://my.site/users/42")));
+        pkce.store(&store, &keys).unwrap();
+
+        let loaded = PKCE::load_from(&store, &keys, 2000.0, 1000.0).unwrap().unwrap();
+        assert_eq!(loaded.return_url(), Some("https://my.site/users/42"));
+        let (verifier, csrf, nonce) = loaded.destructure();
+        assert_eq!(verifier.secret(), "verifier");
+        assert_eq!(csrf.secret(), "csrf");
+        assert_eq!(nonce.secret(), "nonce");
+        assert!(PKCE::load_from(&store, &StorageKeys::new("kifapwa", "other"), 2000.0, 1000.0).unwrap().is_none());
+
+        PKCE::clear(&store, &keys).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn expired_state_is_removed() {
+        let store = MemoryStore::new();
+        let keys = StorageKeys::new("kifapwa", "client");
+        PKCE::new(
+            PkceCodeVerifier::new(String::from("verifier")),
+            CsrfToken::new(String::from("csrf")),
+            Nonce::new(String::from("nonce")),
+            1000.0
+        ).store(&store, &keys).unwrap();
+
+        assert!(PKCE::load_from(&store, &keys, 2001.0, 1000.0).unwrap().is_none());
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use super::ClientData;
+
+/// The AuthProviderRegistry holds the identity providers the user may authenticate at,
+/// e.g. the university SSO and a fallback, keyed by name.
+/// The provider registered first is the default one.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct AuthProviderRegistry {
+
+    /// The names and client data of the providers, in order of registration
+    providers: Vec<(String, ClientData)>
+}
+
+impl AuthProviderRegistry {
+
+    /// The name of the only provider of a registry created by [`AuthProviderRegistry::single`]
+    pub const DEFAULT_NAME: &'static str = "default";
+
+    /// Create a registry holding only the given provider, named [`AuthProviderRegistry::DEFAULT_NAME`]
+    pub fn single(client_data: ClientData) -> AuthProviderRegistry {
+        AuthProviderRegistry::new(String::from(Self::DEFAULT_NAME), client_data)
+    }
+
+    /// The client data of the provider with the given name, if registered
+    pub fn get(&self, name: &str) -> Option<&ClientData> {
+        self.providers.iter()
+            .find(|(registered, _)| registered == name)
+            .map(|(_, client_data)| client_data)
+    }
+
+    /// The name of the default provider
+    pub fn default_name(&self) -> &str {
+        &self.providers[0].0
+    }
+
+    /// The client data of the default provider
+    pub fn default_client_data(&self) -> &ClientData {
+        &self.providers[0].1
+    }
+}
+
+#[wasm_bindgen]
+impl AuthProviderRegistry {
+
+    /// Create a registry with the given provider as default
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the provider, e.g. `kit`
+    /// * `client_data` - See [`ClientData`]
+    ///
+    /// # Example
+    /// ```rust
+    /// let providers = AuthProviderRegistry::new(String::from("kit"), kit_client_data)
+    ///     .register(String::from("google"), google_client_data);
+    /// let framework = Framework::with_providers(providers, storage);
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: String, client_data: ClientData) -> AuthProviderRegistry {
+        AuthProviderRegistry {
+            providers: vec![(name, client_data)]
+        }
+    }
+
+    /// Register another provider. A provider registered with the same name before is replaced.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the provider
+    /// * `client_data` - See [`ClientData`]
+    pub fn register(mut self, name: String, client_data: ClientData) -> AuthProviderRegistry {
+        match self.providers.iter_mut().find(|(registered, _)| *registered == name) {
+            Some(provider) => provider.1 = client_data,
+            None => self.providers.push((name, client_data))
+        }
+        self
+    }
+
+    /// The names of the registered providers, the default one first
+    pub fn names(&self) -> Vec<String> {
+        self.providers.iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn client_data(client_id: &str) -> ClientData {
+        ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from(client_id),
+            String::from("https://my.site")
+        ).ok().unwrap()
+    }
+
+    #[test]
+    fn registers_providers() {
+        let providers = AuthProviderRegistry::new(String::from("kit"), client_data("kit-client"))
+            .register(String::from("google"), client_data("google-client"))
+            .register(String::from("kit"), client_data("other-client"));
+        assert_eq!(providers.names(), vec!["kit", "google"]);
+        assert_eq!(providers.default_name(), "kit");
+        assert_eq!(providers.get("kit").unwrap().client_id().as_str(), "other-client");
+        assert!(providers.get("github").is_none());
+    }
+
+    #[test]
+    fn single_provider() {
+        let providers = AuthProviderRegistry::single(client_data("client"));
+        assert_eq!(providers.names(), vec![AuthProviderRegistry::DEFAULT_NAME]);
+    }
+}
@@ -0,0 +1,100 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use std::collections::BTreeSet;
+use serde_json::Value;
+
+use super::AuthManager;
+
+/// The Roles granted to the authenticated user, read from the claims of the id token or userinfo response.
+/// Collected are the realm roles (`realm_access.roles`) and client roles (`resource_access.<client_id>.roles`)
+/// issued by Keycloak, as well as a plain `roles` claim issued by other providers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Roles {
+
+    /// The names of the granted roles
+    roles: BTreeSet<String>
+}
+
+impl Roles {
+
+    /// Collect the roles granted to the user authenticated at the given [`AuthManager`].
+    /// If the user is not authenticated, no roles are granted.
+    ///
+    /// # Example
+    /// ```rust
+    /// let auth: AuthManager; // already authenticated
+    /// if Roles::of(&auth).contains("admin") {
+    ///     // show the admin routes
+    /// }
+    /// ```
+    pub fn of(auth: &AuthManager) -> Roles {
+        let client_id = auth.environment().client_id();
+        Roles::from_claims(&[
+            auth.custom_claim("realm_access.roles"),
+            auth.custom_claim("resource_access")
+                .and_then(|resources| resources.get(&client_id))
+                .and_then(|resource| resource.get("roles")),
+            auth.custom_claim("roles")
+        ])
+    }
+
+    /// Collect the roles from the given claims, each an array of role names.
+    /// Claims of another form are ignored.
+    fn from_claims(claims: &[Option<&Value>]) -> Roles {
+        let roles = claims.iter()
+            .flatten()
+            .filter_map(|claim| claim.as_array())
+            .flatten()
+            .filter_map(|role| role.as_str())
+            .map(String::from)
+            .collect();
+        Roles { roles }
+    }
+
+    /// Whether the given role is granted
+    pub fn contains(&self, role: &str) -> bool {
+        self.roles.contains(role)
+    }
+
+    /// Whether any of the given roles is granted
+    pub fn contains_any(&self, roles: &[String]) -> bool {
+        roles.iter().any(|role| self.contains(role))
+    }
+
+    /// The names of the granted roles in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        self.roles.iter().cloned().collect()
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn collects_roles_from_claims() {
+        let realm = json!(["admin", "user"]);
+        let resource = json!(["moderator"]);
+        let malformed = json!("owner");
+        let roles = Roles::from_claims(&[Some(&realm), Some(&resource), Some(&malformed), None]);
+
+        assert!(roles.contains("admin"));
+        assert!(roles.contains("moderator"));
+        assert!(!roles.contains("owner"));
+        assert!(roles.contains_any(&[String::from("owner"), String::from("user")]));
+        assert!(!roles.contains_any(&[]));
+        assert_eq!(roles.names(), vec!["admin", "moderator", "user"]);
+    }
+
+    #[test]
+    fn unauthenticated_user_has_no_roles() {
+        assert_eq!(Roles::from_claims(&[None, None]), Roles::default());
+    }
+}
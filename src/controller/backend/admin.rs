@@ -0,0 +1,198 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    json,
+    Value
+};
+
+use super::BackendError;
+use super::GraphQLClient;
+use crate::controller::NetworkConditions;
+
+/// An administrator of the panel
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Admin {
+
+    /// The subject identifier at the authentication provider, unknown until the first login
+    #[serde(default)]
+    pub subject: Option<String>,
+
+    /// The email address
+    pub email: String
+}
+
+/// A page of the administrators
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminPage {
+
+    /// The administrators on this page
+    pub items: Vec<Admin>,
+
+    /// The number of administrators on all pages
+    pub total: u32,
+
+    /// The number of this page, starting at `0`
+    #[serde(default)]
+    pub page: u32,
+
+    /// The maximum number of administrators per page
+    #[serde(default)]
+    pub per_page: u32
+}
+
+/// The AdminManager maintains the administrators of the panel, backing the "manage administrators" page.
+#[wasm_bindgen]
+pub struct AdminManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl AdminManager {
+
+    const QUERY_LIST: &'static str = "query($page: Int!, $perPage: Int!) { admins(page: $page, perPage: $perPage) { items { subject email } total } }";
+    const MUTATION_ADD: &'static str = "mutation($email: String, $subject: String) { addAdmin(email: $email, subject: $subject) }";
+    const MUTATION_REMOVE: &'static str = "mutation($email: String, $subject: String) { removeAdmin(email: $email, subject: $subject) }";
+
+    /// The maximum number of administrators per page the backend accepts
+    const MAX_PER_PAGE: u32 = 100;
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> AdminManager {
+        AdminManager {
+            client
+        }
+    }
+
+    /// The variables identifying an administrator by email address, if the identifier contains an `@`,
+    /// or else by subject identifier
+    fn identify(identifier: &str) -> Result<Value, BackendError> {
+        let identifier = identifier.trim();
+        if identifier.is_empty() {
+            return Err(BackendError::InvalidArgument(String::from("No email address or subject given")));
+        }
+        if identifier.contains('@') {
+            Ok(json!({ "email": identifier }))
+        } else {
+            Ok(json!({ "subject": identifier }))
+        }
+    }
+
+    /// The variables requesting the given page
+    fn page(page: u32, per_page: u32) -> Result<Value, BackendError> {
+        if per_page == 0 || per_page > Self::MAX_PER_PAGE {
+            return Err(BackendError::InvalidArgument(
+                format!("The page size has to be between 1 and {}", Self::MAX_PER_PAGE)
+            ));
+        }
+        Ok(json!({ "page": page, "perPage": per_page }))
+    }
+
+    /// Send the given mutation for the given administrator
+    fn mutate(&self, mutation: &str, field: &'static str, identifier: &str) -> Result<Promise, JsValue> {
+        Ok(self.client.apply(mutation, field, Self::identify(identifier)?))
+    }
+}
+
+#[wasm_bindgen]
+impl AdminManager {
+
+    /// Fetch a page of the administrators
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - The number of the page, starting at `0`
+    /// * `per_page` - The maximum number of administrators per page, at most 100.
+    ///   Reduced on slow connections, see [`NetworkConditions::page_size`]
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<object>)` - Resolves to the page with `items`, `total`, `page` and the used `perPage`,
+    ///   rejects with a structured [`BackendError`] or [`AuthError`](crate::controller::auth_manager::AuthError)
+    /// * `Err(JsValue)` - A structured [`BackendError`], if the page size is not valid
+    ///
+    /// # Example
+    /// ```rust
+    /// let admins = framework.graphql_client(String::from("https://api.my.site/graphql"))?.admins();
+    /// const page = await admins.list(0, 20);
+    /// ```
+    pub fn list(&self, page: u32, per_page: u32) -> Result<Promise, JsValue> {
+        Self::page(page, per_page)?;
+        let per_page = NetworkConditions::current().page_size(per_page);
+        let request = self.client.execute(Self::QUERY_LIST, Some(Self::page(page, per_page)?));
+        Ok(future_to_promise(async move {
+            let mut admins: AdminPage = GraphQLClient::field(&request.await?, "admins")?;
+            admins.page = page;
+            admins.per_page = per_page;
+            GraphQLClient::to_js(&admins)
+        }))
+    }
+
+    /// Grant the given account administrator rights
+    ///
+    /// # Arguments
+    ///
+    /// * `identifier` - The email address or subject identifier of the account
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<boolean>)` - Resolves to whether the account was added, `false` if it already is an administrator
+    /// * `Err(JsValue)` - A structured [`BackendError`], if no identifier is given
+    pub fn add(&self, identifier: String) -> Result<Promise, JsValue> {
+        self.mutate(Self::MUTATION_ADD, "addAdmin", &identifier)
+    }
+
+    /// Revoke the administrator rights of the given account, see [`AdminManager::add`]
+    pub fn remove(&self, identifier: String) -> Result<Promise, JsValue> {
+        self.mutate(Self::MUTATION_REMOVE, "removeAdmin", &identifier)
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(AdminManager::QUERY_LIST));
+        assert!(declares_root_field(AdminManager::MUTATION_ADD));
+        assert!(declares_root_field(AdminManager::MUTATION_REMOVE));
+    }
+
+    #[test]
+    fn identifies_by_email_or_subject() {
+        assert_eq!(AdminManager::identify(" admin@kit.edu "), Ok(json!({ "email": "admin@kit.edu" })));
+        assert_eq!(AdminManager::identify("f81d4fae-7dec"), Ok(json!({ "subject": "f81d4fae-7dec" })));
+        assert!(matches!(AdminManager::identify("  "), Err(BackendError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn validates_page_size() {
+        assert_eq!(AdminManager::page(2, 20), Ok(json!({ "page": 2, "perPage": 20 })));
+        assert!(AdminManager::page(0, 0).is_err());
+        assert!(AdminManager::page(0, 101).is_err());
+    }
+
+    #[test]
+    fn reads_page() {
+        let data = json!({ "admins": { "items": [{ "subject": null, "email": "admin@kit.edu" }], "total": 41 } });
+        let page: AdminPage = GraphQLClient::field(&data, "admins").unwrap();
+        assert_eq!(page.total, 41);
+        assert_eq!(page.items[0].subject, None);
+    }
+}
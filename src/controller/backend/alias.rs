@@ -0,0 +1,127 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::json;
+
+use super::GraphQLClient;
+
+/// An alias suggested by a user, waiting for moderation
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AliasSuggestion {
+
+    /// The identifier of the suggestion
+    pub id: String,
+
+    /// The suggested alias
+    pub alias: String,
+
+    /// The name of the entry the alias is suggested for
+    pub target: String,
+
+    /// The point in time the alias was suggested at, as ISO 8601 date-time
+    pub created_at: String
+}
+
+/// The AliasManager lets moderators approve or reject the aliases suggested by users.
+#[wasm_bindgen]
+pub struct AliasManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl AliasManager {
+
+    const QUERY_OPEN: &'static str = "query { openAliasSuggestions { id alias target createdAt } }";
+    const MUTATION_APPROVE: &'static str = "mutation($id: ID!) { approveAliasSuggestion(id: $id) }";
+    const MUTATION_REJECT: &'static str = "mutation($id: ID!) { rejectAliasSuggestion(id: $id) }";
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> AliasManager {
+        AliasManager {
+            client
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl AliasManager {
+
+    /// Fetch the suggestions which are neither approved nor rejected yet
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the open suggestions with `id`, `alias`, `target` and `createdAt`,
+    ///   rejects with a structured [`BackendError`](super::BackendError) or [`AuthError`](crate::controller::auth_manager::AuthError)
+    ///
+    /// # Example
+    /// ```rust
+    /// let aliases = framework.graphql_client(String::from("https://api.my.site/graphql"))?.aliases();
+    /// for (const suggestion of await aliases.get_open_suggestions()) {
+    ///     // show suggestion.alias for suggestion.target
+    /// }
+    /// ```
+    pub fn get_open_suggestions(&self) -> Promise {
+        let request = self.client.execute(Self::QUERY_OPEN, None);
+        future_to_promise(async move {
+            let suggestions: Vec<AliasSuggestion> = GraphQLClient::field(&request.await?, "openAliasSuggestions")?;
+            GraphQLClient::to_js(&suggestions)
+        })
+    }
+
+    /// Approve the suggestion with the given identifier, the alias becomes visible
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the suggestion was approved, `false` if it is not open anymore
+    pub fn approve(&self, alias_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_APPROVE, "approveAliasSuggestion", json!({ "id": alias_id }))
+    }
+
+    /// Reject the suggestion with the given identifier
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the suggestion was rejected, `false` if it is not open anymore
+    pub fn reject(&self, alias_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_REJECT, "rejectAliasSuggestion", json!({ "id": alias_id }))
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(AliasManager::QUERY_OPEN));
+        assert!(declares_root_field(AliasManager::MUTATION_APPROVE));
+        assert!(declares_root_field(AliasManager::MUTATION_REJECT));
+    }
+
+    #[test]
+    fn reads_suggestions() {
+        let data = json!({
+            "openAliasSuggestions": [
+                { "id": "7", "alias": "Audimax", "target": "Building 30.95", "createdAt": "2022-11-14T10:00:00Z" }
+            ]
+        });
+        let suggestions: Vec<AliasSuggestion> = GraphQLClient::field(&data, "openAliasSuggestions").unwrap();
+        assert_eq!(suggestions[0].alias, "Audimax");
+        assert_eq!(serde_json::to_value(&suggestions[0]).unwrap()["createdAt"], "2022-11-14T10:00:00Z");
+    }
+}
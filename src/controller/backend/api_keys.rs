@@ -0,0 +1,339 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use chrono::DateTime;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    json,
+    Value
+};
+
+use super::BackendError;
+use super::GraphQLClient;
+
+/// Whether an API key can be used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyStatus {
+
+    /// The key can be used
+    #[default]
+    Active,
+
+    /// The key is past its expiry
+    Expired,
+
+    /// The key was revoked
+    Revoked
+}
+
+/// A scope an API key can be restricted to
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApiKeyScope {
+
+    /// The name of the scope, e.g. `read:users`
+    pub name: String,
+
+    /// What the scope allows, for the selection
+    #[serde(default)]
+    pub description: String
+}
+
+/// An API key of an external integration, as a row of the table of keys.
+/// The key itself is not available anymore, only its prefix to recognize it.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+
+    /// The identifier of the key
+    pub id: String,
+
+    /// The name of the integration the key is issued for
+    pub name: String,
+
+    /// The first characters of the key
+    pub prefix: String,
+
+    /// The scopes the key is restricted to
+    pub scopes: Vec<String>,
+
+    /// The point in time the key was issued at, as ISO 8601 date-time
+    pub created_at: String,
+
+    /// The point in time the key expires at, as ISO 8601 date-time, `None` if it does not expire
+    #[serde(default)]
+    pub expires_at: Option<String>,
+
+    /// The point in time the key was last used at, as ISO 8601 date-time, `None` if it was never used
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+
+    /// Whether the key was revoked
+    #[serde(default)]
+    pub revoked: bool,
+
+    /// Whether the key can be used, see [`ApiKey::status_at`]
+    #[serde(default, skip_deserializing)]
+    pub status: ApiKeyStatus
+}
+
+impl ApiKey {
+
+    /// Whether the key can be used at the given time.
+    /// A key whose expiry cannot be read is treated as expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The point in time in seconds since the unix epoch
+    pub fn status_at(&self, now: u64) -> ApiKeyStatus {
+        if self.revoked {
+            return ApiKeyStatus::Revoked;
+        }
+        match &self.expires_at {
+            Some(expires_at) => match DateTime::parse_from_rfc3339(expires_at) {
+                Ok(expires_at) if expires_at.timestamp() > now as i64 => ApiKeyStatus::Active,
+                _ => ApiKeyStatus::Expired
+            },
+            None => ApiKeyStatus::Active
+        }
+    }
+
+    /// Fill in the status of the key at the given time
+    fn with_status_at(mut self, now: u64) -> Self {
+        self.status = self.status_at(now);
+        self
+    }
+}
+
+/// An API key which was just issued, with the key which is only available once
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedApiKey {
+
+    /// The key, has to be handed to the integration now
+    pub key: String,
+
+    /// The issued key, see [`ApiKey`]
+    pub api_key: ApiKey
+}
+
+/// The ApiKeysManager issues and revokes the API keys of external integrations.
+/// A key is only returned once when it is issued, afterwards it is only recognizable by its prefix.
+#[wasm_bindgen]
+pub struct ApiKeysManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl ApiKeysManager {
+
+    const FIELDS: &'static str = "id name prefix scopes createdAt expiresAt lastUsedAt revoked";
+    const QUERY_SCOPES: &'static str = "query { apiKeyScopes { name description } }";
+    const MUTATION_REVOKE: &'static str = "mutation($id: ID!) { revokeApiKey(id: $id) }";
+
+    /// The longest validity of a key in days
+    const MAX_EXPIRY_DAYS: u32 = 365;
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> ApiKeysManager {
+        ApiKeysManager {
+            client
+        }
+    }
+
+    fn query_list() -> String {
+        format!("query {{ apiKeys {{ {} }} }}", Self::FIELDS)
+    }
+
+    fn mutation_issue() -> String {
+        format!(
+            "mutation($name: String!, $scopes: [String!]!, $expiresInDays: Int) {{ issueApiKey(name: $name, scopes: $scopes, expiresInDays: $expiresInDays) {{ key apiKey {{ {} }} }} }}",
+            Self::FIELDS
+        )
+    }
+
+    /// The variables issuing a key, if the name, the scopes and the expiry are valid
+    fn issue_variables(name: &str, scopes: &[String], expires_in_days: Option<u32>) -> Result<Value, BackendError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(BackendError::InvalidArgument(String::from("No name given for the key")));
+        }
+        let mut scopes: Vec<&str> = scopes.iter()
+            .map(|scope| scope.trim())
+            .filter(|scope| !scope.is_empty())
+            .collect();
+        scopes.sort_unstable();
+        scopes.dedup();
+        if scopes.is_empty() {
+            return Err(BackendError::InvalidArgument(String::from("No scopes selected for the key")));
+        }
+        if expires_in_days.is_some_and(|days| days == 0 || days > Self::MAX_EXPIRY_DAYS) {
+            return Err(BackendError::InvalidArgument(
+                format!("The key has to expire within 1 to {} days", Self::MAX_EXPIRY_DAYS)
+            ));
+        }
+        Ok(json!({ "name": name, "scopes": scopes, "expiresInDays": expires_in_days }))
+    }
+}
+
+#[wasm_bindgen]
+impl ApiKeysManager {
+
+    /// Fetch the scopes a key can be restricted to
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the scopes with `name` and `description`,
+    ///   rejects with a structured [`BackendError`] or [`AuthError`](crate::controller::auth_manager::AuthError)
+    pub fn scopes(&self) -> Promise {
+        let request = self.client.execute(Self::QUERY_SCOPES, None);
+        future_to_promise(async move {
+            let scopes: Vec<ApiKeyScope> = GraphQLClient::field(&request.await?, "apiKeyScopes")?;
+            GraphQLClient::to_js(&scopes)
+        })
+    }
+
+    /// Fetch the issued keys, including the revoked and expired ones
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the keys with `id`, `name`, `prefix`, `scopes`, `createdAt`, `expiresAt`,
+    ///   `lastUsedAt`, `revoked` and the `status` `active`, `expired` or `revoked`
+    ///
+    /// # Example
+    /// ```rust
+    /// let api_keys = framework.graphql_client(String::from("https://api.my.site/graphql"))?.api_keys();
+    /// for (const key of await api_keys.list()) {
+    ///     // show key.name, key.prefix and key.status
+    /// }
+    /// ```
+    pub fn list(&self) -> Promise {
+        let request = self.client.execute(&Self::query_list(), None);
+        future_to_promise(async move {
+            let keys: Vec<ApiKey> = GraphQLClient::field(&request.await?, "apiKeys")?;
+            let now = now();
+            let keys: Vec<ApiKey> = keys.into_iter().map(|key| key.with_status_at(now)).collect();
+            GraphQLClient::to_js(&keys)
+        })
+    }
+
+    /// Issue a key for an external integration
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the integration
+    /// * `scopes` - The scopes the key is restricted to, see [`ApiKeysManager::scopes`]
+    /// * `expires_in_days` - The validity of the key, at most 365 days, or `undefined` for a key which does not expire
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<object>)` - Resolves to the `key`, which is not available later on,
+    ///   and the issued `apiKey`, see [`ApiKeysManager::list`]
+    /// * `Err(JsValue)` - A structured [`BackendError`], if the name, the scopes or the expiry are not valid
+    ///
+    /// # Example
+    /// ```rust
+    /// const { key, apiKey } = await api_keys.issue("CI", ["read:users"], 90);
+    /// // show the key once, so it can be configured at the integration
+    /// ```
+    pub fn issue(&self, name: String, scopes: Vec<String>, expires_in_days: Option<u32>) -> Result<Promise, JsValue> {
+        let variables = Self::issue_variables(&name, &scopes, expires_in_days)?;
+        let request = self.client.execute(&Self::mutation_issue(), Some(variables));
+        Ok(future_to_promise(async move {
+            let mut issued: IssuedApiKey = GraphQLClient::field(&request.await?, "issueApiKey")?;
+            issued.api_key = issued.api_key.with_status_at(now());
+            GraphQLClient::to_js(&issued)
+        }))
+    }
+
+    /// Revoke the given key, the integration cannot use it anymore
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the key was revoked, `false` if it already was
+    pub fn revoke(&self, key_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_REVOKE, "revokeApiKey", json!({ "id": key_id }))
+    }
+}
+
+fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    // 2022-11-14 10:00:00 UTC
+    const NOW: u64 = 1668420000;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(&ApiKeysManager::query_list()));
+        assert!(declares_root_field(&ApiKeysManager::mutation_issue()));
+        assert!(declares_root_field(ApiKeysManager::QUERY_SCOPES));
+        assert!(declares_root_field(ApiKeysManager::MUTATION_REVOKE));
+    }
+
+    #[test]
+    fn validates_keys() {
+        let scopes = vec![String::from("write:users"), String::from(" read:users "), String::from("read:users")];
+        assert_eq!(
+            ApiKeysManager::issue_variables(" CI ", &scopes, Some(90)),
+            Ok(json!({ "name": "CI", "scopes": ["read:users", "write:users"], "expiresInDays": 90 }))
+        );
+        assert_eq!(ApiKeysManager::issue_variables("CI", &scopes, None).unwrap()["expiresInDays"], Value::Null);
+        assert!(ApiKeysManager::issue_variables(" ", &scopes, None).is_err());
+        assert!(ApiKeysManager::issue_variables("CI", &[], None).is_err());
+        assert!(ApiKeysManager::issue_variables("CI", &scopes, Some(0)).is_err());
+        assert!(ApiKeysManager::issue_variables("CI", &scopes, Some(366)).is_err());
+    }
+
+    #[test]
+    fn reads_issued_key() {
+        let data = json!({
+            "issueApiKey": {
+                "key": "kif_3f9a_secret",
+                "apiKey": {
+                    "id": "5", "name": "CI", "prefix": "kif_3f9a", "scopes": ["read:users"],
+                    "createdAt": "2022-11-14T10:00:00Z", "expiresAt": "2023-02-12T10:00:00Z"
+                }
+            }
+        });
+        let issued: IssuedApiKey = GraphQLClient::field(&data, "issueApiKey").unwrap();
+        assert_eq!(issued.key, "kif_3f9a_secret");
+        assert_eq!(issued.api_key.last_used_at, None);
+        assert!(!issued.api_key.revoked);
+        let row = serde_json::to_value(issued.api_key.with_status_at(NOW)).unwrap();
+        assert_eq!(row["status"], "active");
+        assert_eq!(row["prefix"], "kif_3f9a");
+    }
+
+    #[test]
+    fn determines_status() {
+        let key: ApiKey = serde_json::from_value(json!({
+            "id": "5", "name": "CI", "prefix": "kif_3f9a", "scopes": [],
+            "createdAt": "2022-11-14T10:00:00Z", "expiresAt": "2022-11-15T10:00:00+00:00"
+        })).unwrap();
+        assert_eq!(key.status_at(NOW), ApiKeyStatus::Active);
+        assert_eq!(key.status_at(NOW + 86400), ApiKeyStatus::Expired);
+
+        let unreadable = ApiKey { expires_at: Some(String::from("tomorrow")), ..key.clone() };
+        assert_eq!(unreadable.status_at(NOW), ApiKeyStatus::Expired);
+        let revoked = ApiKey { revoked: true, expires_at: None, ..key };
+        assert_eq!(revoked.status_at(NOW), ApiKeyStatus::Revoked);
+    }
+}
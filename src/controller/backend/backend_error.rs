@@ -0,0 +1,146 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use js_sys::{
+    Object,
+    Reflect,
+    JSON
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+/// A single error reported by the GraphQL backend, see the
+/// [GraphQL specification](https://spec.graphql.org/October2021/#sec-Errors)
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GraphQLError {
+
+    /// The description of the error
+    pub message: String,
+
+    /// The path of the field which failed, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<Value>>,
+
+    /// Additional information, e.g. an error code, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Value>
+}
+
+/// The BackendError represents an error which occurs when querying the backend
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackendError {
+
+    /// The backend answered with an unsuccessful HTTP status and no GraphQL response
+    Http {
+
+        /// The HTTP status of the response
+        status: u16,
+
+        /// The body of the response
+        body: String
+    },
+
+    /// The backend reported errors while executing the query
+    GraphQL(Vec<GraphQLError>),
+
+    /// The response of the backend could not be read
+    InvalidResponse(String),
+
+    /// The arguments of the request are not valid, so it was not sent
+    InvalidArgument(String)
+}
+
+impl BackendError {
+
+    /// A stable identifier of the kind of the error, for the frontend to branch on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BackendError::Http { .. } => "http_error",
+            BackendError::GraphQL(_) => "graphql_error",
+            BackendError::InvalidResponse(_) => "invalid_response",
+            BackendError::InvalidArgument(_) => "invalid_argument"
+        }
+    }
+
+    /// A description of the error, without the common prefix of [`Display`](fmt::Display).
+    pub fn message(&self) -> String {
+        match self {
+            BackendError::Http { status, .. } => format!("The backend answered with status {}", status),
+            BackendError::GraphQL(errors) => errors.iter()
+                .map(|error| error.message.as_str())
+                .collect::<Vec<&str>>()
+                .join("; "),
+            BackendError::InvalidResponse(cause) => format!("The response of the backend is not valid: {}", cause),
+            BackendError::InvalidArgument(cause) => format!("The request is not valid: {}", cause)
+        }
+    }
+
+    /// Convert into a structured JS object carrying `code` and `message`,
+    /// plus `status` and `body` for an HTTP error and `errors` for GraphQL errors.
+    ///
+    /// # Example
+    /// ```rust
+    /// let err = BackendError::Http { status: 503, body: String::new() };
+    /// let value: JsValue = err.to_js_value();
+    /// // { code: "http_error", message: "The backend answered with status 503", status: 503, body: "" }
+    /// ```
+    pub fn to_js_value(&self) -> JsValue {
+        let object = Object::new();
+        let set = |key: &str, value: JsValue| {
+            let _ = Reflect::set(&object, &JsValue::from_str(key), &value);
+        };
+        set("code", JsValue::from_str(self.code()));
+        set("message", JsValue::from_str(&self.message()));
+        if let BackendError::Http { status, body } = self {
+            set("status", JsValue::from(*status));
+            set("body", JsValue::from_str(body));
+        }
+        if let BackendError::GraphQL(errors) = self {
+            let errors = serde_json::to_string(errors).ok()
+                .and_then(|errors| JSON::parse(&errors).ok())
+                .unwrap_or(JsValue::UNDEFINED);
+            set("errors", errors);
+        }
+        object.into()
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error, cannot query the backend: {}", self.message())
+    }
+}
+
+impl Error for BackendError {}
+
+impl From<BackendError> for JsValue {
+    fn from(err: BackendError) -> JsValue {
+        err.to_js_value()
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn message_joins_errors() {
+        let err = BackendError::GraphQL(vec![
+            GraphQLError { message: String::from("Forbidden"), path: None, extensions: None },
+            GraphQLError { message: String::from("Not found"), path: None, extensions: None }
+        ]);
+        assert_eq!(err.code(), "graphql_error");
+        assert_eq!(err.to_string(), "Error, cannot query the backend: Forbidden; Not found");
+    }
+}
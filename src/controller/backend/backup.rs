@@ -0,0 +1,211 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::json;
+
+use super::BackendError;
+use super::GraphQLClient;
+use crate::confirmation::ConfirmationToken;
+
+/// A backup set of the backend
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+
+    /// The identifier of the backup
+    pub id: String,
+
+    /// The point in time the backup was started at, as ISO 8601 date-time
+    pub created_at: String,
+
+    /// The size of the backup in bytes, unknown while it is running
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+
+    /// The state of the backup, e.g. `running`, `completed` or `failed`
+    pub status: String
+}
+
+/// The impact a restore would have, determined by a dry run
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreImpact {
+
+    /// The identifier of the backup to restore
+    pub backup_id: String,
+
+    /// The number of records which would be overwritten or removed
+    pub affected_records: u64,
+
+    /// The warnings of the backend, e.g. about data created after the backup
+    #[serde(default)]
+    pub warnings: Vec<String>
+}
+
+/// The BackupManager triggers backups of the backend and restores them.
+/// A restore first has to be planned by a dry run showing its impact,
+/// and is then only performed with a [`ConfirmationToken`] for exactly this backup.
+#[wasm_bindgen]
+pub struct BackupManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl BackupManager {
+
+    const FIELDS: &'static str = "id createdAt sizeBytes status";
+    const MUTATION_RESTORE_DRY_RUN: &'static str = "mutation($id: ID!) { restoreBackup(id: $id, dryRun: true) { backupId affectedRecords warnings } }";
+    const MUTATION_RESTORE: &'static str = "mutation($id: ID!, $confirmation: String!) { confirmRestore(id: $id, confirmation: $confirmation) }";
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> BackupManager {
+        BackupManager {
+            client
+        }
+    }
+
+    /// The action a [`ConfirmationToken`] has to confirm to restore the given backup
+    fn action_for(backup_id: &str) -> String {
+        format!("restore_backup:{}", backup_id)
+    }
+
+    /// Check that the given action confirms restoring the given backup
+    fn check_confirmation(backup_id: &str, action: &str) -> Result<(), BackendError> {
+        if action != Self::action_for(backup_id) {
+            return Err(BackendError::InvalidArgument(format!("The confirmation is not for restoring backup {}", backup_id)));
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl BackupManager {
+
+    /// Start a new backup
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object>` - Resolves to the started backup with `id`, `createdAt`, `sizeBytes` and `status`,
+    ///   rejects with a structured [`BackendError`] or [`AuthError`](crate::controller::auth_manager::AuthError)
+    pub fn trigger_backup(&self) -> Promise {
+        let request = self.client.execute(&format!("mutation {{ createBackup {{ {} }} }}", Self::FIELDS), None);
+        future_to_promise(async move {
+            let backup: Backup = GraphQLClient::field(&request.await?, "createBackup")?;
+            GraphQLClient::to_js(&backup)
+        })
+    }
+
+    /// Fetch the existing backups
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the backups, see [`BackupManager::trigger_backup`]
+    pub fn list_backups(&self) -> Promise {
+        let request = self.client.execute(&format!("query {{ backups {{ {} }} }}", Self::FIELDS), None);
+        future_to_promise(async move {
+            let backups: Vec<Backup> = GraphQLClient::field(&request.await?, "backups")?;
+            GraphQLClient::to_js(&backups)
+        })
+    }
+
+    /// Determine the impact of restoring the given backup by a dry run, nothing is changed yet
+    ///
+    /// # Arguments
+    ///
+    /// * `backup_id` - The identifier of the backup
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object>` - Resolves to the impact with `backupId`, `affectedRecords` and `warnings`
+    ///
+    /// # Example
+    /// ```rust
+    /// let backups = framework.graphql_client(String::from("https://api.my.site/graphql"))?.backups();
+    /// const impact = await backups.plan_restore(id);
+    /// // show the impact, and if the admin confirms:
+    /// const token = signer.issue(backups.restore_action(id));
+    /// await backups.restore(id, token);
+    /// ```
+    pub fn plan_restore(&self, backup_id: String) -> Promise {
+        let request = self.client.execute(Self::MUTATION_RESTORE_DRY_RUN, Some(json!({ "id": backup_id })));
+        future_to_promise(async move {
+            let impact: RestoreImpact = GraphQLClient::field(&request.await?, "restoreBackup")?;
+            GraphQLClient::to_js(&impact)
+        })
+    }
+
+    /// The action to issue a [`ConfirmationToken`] for, to restore the given backup
+    pub fn restore_action(&self, backup_id: String) -> String {
+        Self::action_for(&backup_id)
+    }
+
+    /// Restore the given backup, replacing the current data of the backend
+    ///
+    /// # Arguments
+    ///
+    /// * `backup_id` - The identifier of the backup
+    /// * `confirmation` - A token confirming the action returned by `restore_action` for this backup
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<boolean>)` - Resolves to whether the restore was started
+    /// * `Err(JsValue)` - A structured [`BackendError`], if the confirmation is expired or not for this backup
+    pub fn restore(&self, backup_id: String, confirmation: &ConfirmationToken) -> Result<Promise, JsValue> {
+        BackupManager::check_confirmation(&backup_id, &confirmation.action())?;
+        if confirmation.is_expired() {
+            return Err(BackendError::InvalidArgument(String::from("The confirmation is expired")).into());
+        }
+        Ok(self.client.apply(
+            Self::MUTATION_RESTORE,
+            "confirmRestore",
+            json!({ "id": backup_id, "confirmation": confirmation.to_string() })
+        ))
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(&format!("mutation {{ createBackup {{ {} }} }}", BackupManager::FIELDS)));
+        assert!(declares_root_field(&format!("query {{ backups {{ {} }} }}", BackupManager::FIELDS)));
+        assert!(declares_root_field(BackupManager::MUTATION_RESTORE_DRY_RUN));
+        assert!(declares_root_field(BackupManager::MUTATION_RESTORE));
+    }
+
+    #[test]
+    fn confirmation_has_to_match_backup() {
+        assert_eq!(BackupManager::check_confirmation("42", "restore_backup:42"), Ok(()));
+        assert!(BackupManager::check_confirmation("42", "restore_backup:7").is_err());
+        assert!(BackupManager::check_confirmation("42", "delete_user").is_err());
+    }
+
+    #[test]
+    fn reads_backups_and_impact() {
+        let data = json!({
+            "backups": [{ "id": "42", "createdAt": "2022-11-14T03:00:00Z", "sizeBytes": null, "status": "running" }],
+            "restoreBackup": { "backupId": "42", "affectedRecords": 1200 }
+        });
+        let backups: Vec<Backup> = GraphQLClient::field(&data, "backups").unwrap();
+        assert_eq!(backups[0].size_bytes, None);
+        let impact: RestoreImpact = GraphQLClient::field(&data, "restoreBackup").unwrap();
+        assert_eq!(impact.affected_records, 1200);
+        assert!(impact.warnings.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use serde_json::json;
+
+use super::GraphQLClient;
+
+/// The BlacklistManager maintains the terms which must not be used, e.g. in alias suggestions.
+#[wasm_bindgen]
+pub struct BlacklistManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl BlacklistManager {
+
+    const QUERY_BLACKLIST: &'static str = "query { blacklist }";
+    const MUTATION_ADD: &'static str = "mutation($term: String!) { addBlacklistEntry(term: $term) }";
+    const MUTATION_REMOVE: &'static str = "mutation($term: String!) { removeBlacklistEntry(term: $term) }";
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> BlacklistManager {
+        BlacklistManager {
+            client
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl BlacklistManager {
+
+    /// Fetch the blacklisted terms
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<string[]>` - Resolves to the blacklisted terms,
+    ///   rejects with a structured [`BackendError`](super::BackendError) or [`AuthError`](crate::controller::auth_manager::AuthError)
+    ///
+    /// # Example
+    /// ```rust
+    /// let blacklist = framework.graphql_client(String::from("https://api.my.site/graphql"))?.blacklist();
+    /// const terms = await blacklist.get_blacklist();
+    /// ```
+    pub fn get_blacklist(&self) -> Promise {
+        let request = self.client.execute(Self::QUERY_BLACKLIST, None);
+        future_to_promise(async move {
+            let terms: Vec<String> = GraphQLClient::field(&request.await?, "blacklist")?;
+            GraphQLClient::to_js(&terms)
+        })
+    }
+
+    /// Add the given term to the blacklist
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the term was added, `false` if it was already blacklisted
+    pub fn add_entry(&self, term: String) -> Promise {
+        self.client.apply(Self::MUTATION_ADD, "addBlacklistEntry", json!({ "term": term }))
+    }
+
+    /// Remove the given term from the blacklist
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the term was removed, `false` if it was not blacklisted
+    pub fn remove_entry(&self, term: String) -> Promise {
+        self.client.apply(Self::MUTATION_REMOVE, "removeBlacklistEntry", json!({ "term": term }))
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn reads_blacklist() {
+        let data = json!({ "blacklist": ["spam", "admin"] });
+        let terms: Vec<String> = GraphQLClient::field(&data, "blacklist").unwrap();
+        assert_eq!(terms, vec![String::from("spam"), String::from("admin")]);
+        assert!(GraphQLClient::field::<Vec<String>>(&json!({ "blacklist": null }), "blacklist").is_err());
+    }
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(BlacklistManager::QUERY_BLACKLIST));
+        assert!(declares_root_field(BlacklistManager::MUTATION_ADD));
+        assert!(declares_root_field(BlacklistManager::MUTATION_REMOVE));
+    }
+}
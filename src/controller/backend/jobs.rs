@@ -0,0 +1,242 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{
+    future_to_promise,
+    spawn_local
+};
+use js_sys::{
+    Function,
+    Promise
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::json;
+use std::cell::Cell;
+use std::rc::Rc;
+
+use super::BackendError;
+use super::GraphQLClient;
+use crate::controller::NetworkConditions;
+
+/// A background job of the backend, e.g. an import or a recalculation
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+
+    /// The identifier of the job
+    pub id: String,
+
+    /// The kind of the job, e.g. `import`
+    pub kind: String,
+
+    /// The state of the job: `queued`, `running`, `completed`, `failed` or `cancelled`
+    pub status: String,
+
+    /// The progress between `0` and `1`
+    #[serde(default)]
+    pub progress: f64,
+
+    /// The point in time the job was created at, as ISO 8601 date-time
+    pub created_at: String,
+
+    /// The cause of the failure of a failed job
+    #[serde(default)]
+    pub error: Option<String>
+}
+
+impl Job {
+
+    /// Whether the job is still queued or running
+    pub fn is_active(&self) -> bool {
+        self.status == "queued" || self.status == "running"
+    }
+}
+
+/// The JobsManager lists the background jobs of the backend and cancels or retries them.
+#[wasm_bindgen]
+pub struct JobsManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl JobsManager {
+
+    const QUERY_JOBS: &'static str = "query { jobs { id kind status progress createdAt error } }";
+    const MUTATION_CANCEL: &'static str = "mutation($id: ID!) { cancelJob(id: $id) }";
+    const MUTATION_RETRY: &'static str = "mutation($id: ID!) { retryJob(id: $id) }";
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> JobsManager {
+        JobsManager {
+            client
+        }
+    }
+
+    /// Fetch the jobs, the active ones first
+    async fn fetch(client: GraphQLClient) -> Result<JsValue, JsValue> {
+        let mut jobs: Vec<Job> = GraphQLClient::field(&client.execute(Self::QUERY_JOBS, None).await?, "jobs")?;
+        // stable, so the order of the backend is kept otherwise
+        jobs.sort_by_key(|job| !job.is_active());
+        GraphQLClient::to_js(&jobs)
+    }
+}
+
+#[wasm_bindgen]
+impl JobsManager {
+
+    /// Fetch the background jobs
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the jobs with `id`, `kind`, `status`, `progress`, `createdAt` and `error`,
+    ///   the queued and running ones first.
+    ///   Rejects with a structured [`BackendError`](super::BackendError) or [`AuthError`](crate::controller::auth_manager::AuthError)
+    pub fn list_jobs(&self) -> Promise {
+        future_to_promise(JobsManager::fetch(self.client.clone()))
+    }
+
+    /// Cancel the given job
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the job was cancelled, `false` if it is not active anymore
+    pub fn cancel(&self, job_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_CANCEL, "cancelJob", json!({ "id": job_id }))
+    }
+
+    /// Run the given failed or cancelled job again
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the job was queued again
+    pub fn retry(&self, job_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_RETRY, "retryJob", json!({ "id": job_id }))
+    }
+
+    /// Poll the jobs in the given interval, to keep the table of the jobs page up to date.
+    /// The callback is invoked with the jobs, see [`JobsManager::list_jobs`], and `undefined`,
+    /// or with `undefined` and the error if the jobs could not be fetched.
+    /// While a poll is still in progress, e.g. on a slow connection, the next ones are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The interval in milliseconds, extended on slow connections,
+    ///   see [`NetworkConditions::poll_interval`]
+    /// * `callback` - The JS function invoked after each poll
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(JobsWatcher)` - The watcher, polling until it is stopped or freed
+    /// * `Err(JsValue)` - A structured [`BackendError`], if the interval is not positive
+    ///   or no window is available to schedule the polls
+    ///
+    /// # Example
+    /// ```rust
+    /// const watcher = jobs.watch(2000, (jobs, error) => table.update(jobs));
+    /// // when leaving the page
+    /// watcher.stop();
+    /// ```
+    pub fn watch(&self, interval: i32, callback: Function) -> Result<JobsWatcher, JsValue> {
+        if interval <= 0 {
+            return Err(BackendError::InvalidArgument(String::from("The interval has to be positive")).into());
+        }
+        let window = web_sys::window()
+            .ok_or_else(|| BackendError::InvalidArgument(String::from("No window is available to schedule the polls")))?;
+        let client = self.client.clone();
+        let polling = Rc::new(Cell::new(false));
+        let poll = move || {
+            if polling.replace(true) {
+                return;
+            }
+            let client = client.clone();
+            let callback = callback.clone();
+            let polling = polling.clone();
+            spawn_local(async move {
+                let result = JobsManager::fetch(client).await;
+                polling.set(false);
+                let _ = match result {
+                    Ok(jobs) => callback.call2(&JsValue::NULL, &jobs, &JsValue::UNDEFINED),
+                    Err(err) => callback.call2(&JsValue::NULL, &JsValue::UNDEFINED, &err)
+                };
+            });
+        };
+        poll();
+
+        let closure = Closure::<dyn FnMut()>::new(poll);
+        let handle = window.set_interval_with_callback_and_timeout_and_arguments_0(
+            closure.as_ref().unchecked_ref(),
+            NetworkConditions::current().poll_interval(interval)
+        ).map_err(|_| BackendError::InvalidArgument(String::from("The polls could not be scheduled")))?;
+        Ok(JobsWatcher {
+            handle: Some(handle),
+            _closure: closure
+        })
+    }
+}
+
+/// The JobsWatcher polls the background jobs, see [`JobsManager::watch`]
+#[wasm_bindgen]
+pub struct JobsWatcher {
+
+    /// The handle of the interval, until stopped
+    handle: Option<i32>,
+
+    /// The closure invoked by the interval, kept alive as long as the watcher
+    _closure: Closure<dyn FnMut()>
+}
+
+#[wasm_bindgen]
+impl JobsWatcher {
+
+    /// Stop polling
+    pub fn stop(&mut self) {
+        if let (Some(handle), Some(window)) = (self.handle.take(), web_sys::window()) {
+            window.clear_interval_with_handle(handle);
+        }
+    }
+}
+
+impl Drop for JobsWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(JobsManager::QUERY_JOBS));
+        assert!(declares_root_field(JobsManager::MUTATION_CANCEL));
+        assert!(declares_root_field(JobsManager::MUTATION_RETRY));
+    }
+
+    #[test]
+    fn reads_jobs() {
+        let data = json!({
+            "jobs": [
+                { "id": "1", "kind": "import", "status": "failed", "createdAt": "2022-11-14T10:00:00Z", "error": "timeout" },
+                { "id": "2", "kind": "recalculation", "status": "running", "progress": 0.4, "createdAt": "2022-11-14T11:00:00Z" }
+            ]
+        });
+        let jobs: Vec<Job> = GraphQLClient::field(&data, "jobs").unwrap();
+        assert!(!jobs[0].is_active());
+        assert_eq!(jobs[0].progress, 0.0);
+        assert!(jobs[1].is_active());
+        assert_eq!(jobs[1].error, None);
+    }
+}
@@ -0,0 +1,391 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+mod backend_error;
+pub use backend_error::{
+    BackendError,
+    GraphQLError
+};
+
+mod blacklist;
+pub use blacklist::BlacklistManager;
+
+mod alias;
+pub use alias::AliasManager;
+
+mod admin;
+pub use admin::AdminManager;
+
+mod backup;
+pub use backup::BackupManager;
+
+mod jobs;
+pub use jobs::JobsManager;
+
+mod webhooks;
+pub use webhooks::WebhooksManager;
+
+mod api_keys;
+pub use api_keys::ApiKeysManager;
+
+mod service_accounts;
+pub use service_accounts::ServiceAccountsManager;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{
+    future_to_promise,
+    JsFuture
+};
+use js_sys::{
+    Promise,
+    JSON
+};
+use openidconnect::url::Url;
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    json,
+    Value
+};
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+
+use super::AuthManager;
+use super::PersistentStore;
+use super::api::send;
+use super::auth_manager::AuthError;
+use crate::stats::Latencies;
+
+/// The response of the GraphQL backend
+#[derive(Debug, Deserialize)]
+struct GraphQLResponse {
+
+    /// The result of the query, if it could be executed at least partially
+    #[serde(default)]
+    data: Option<Value>,
+
+    /// The errors which occurred, if any
+    #[serde(default)]
+    errors: Vec<GraphQLError>
+}
+
+/// The GraphQLClient sends queries and mutations to the GraphQL backend of the admin panel
+/// on behalf of the authenticated user, see [`ApiClient`](super::ApiClient) for the authorization.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct GraphQLClient {
+
+    /// The GraphQL endpoint of the backend
+    endpoint: Url,
+
+    /// The [`AuthManager`] holding the tokens of the user
+    auth: Rc<RefCell<AuthManager>>,
+
+    /// The store to persist renewed tokens in
+    session: Rc<dyn PersistentStore>,
+
+    /// The durations of the requests sent
+    latencies: Rc<RefCell<Latencies>>
+}
+
+impl GraphQLClient {
+
+    /// Create a client for the given GraphQL endpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The URL of the GraphQL endpoint, e.g. `https://api.my.site/graphql`
+    /// * `auth` - The [`AuthManager`] holding the tokens of the user
+    /// * `session` - The [`PersistentStore`] to persist renewed tokens in
+    /// * `latencies` - The [`Latencies`] to record the durations of the requests in
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(GraphQLClient)` - The client
+    /// * `Err(AuthError)` - If the endpoint is not valid
+    pub fn new(
+        endpoint: &str,
+        auth: Rc<RefCell<AuthManager>>,
+        session: Rc<dyn PersistentStore>,
+        latencies: Rc<RefCell<Latencies>>
+    ) -> Result<GraphQLClient, AuthError> {
+        match Url::parse(endpoint) {
+            Ok(endpoint) => Ok(GraphQLClient {
+                endpoint,
+                auth,
+                session,
+                latencies
+            }),
+            Err(_) => Err(AuthError::InvalidConfiguration(String::from("The provided graphql url is not correct!")))
+        }
+    }
+
+    /// Send the given query or mutation to the backend.
+    /// The returned request does not borrow this instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The GraphQL document
+    /// * `variables` - The variables of the document, if any
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Value)` - The `data` of the response
+    /// * `Err(JsValue)` - A structured [`BackendError`] or [`AuthError`], or the error of `fetch`
+    pub fn execute(&self, query: &str, variables: Option<Value>) -> impl Future<Output = Result<Value, JsValue>> + 'static {
+        let body = GraphQLClient::body(query, variables);
+        let endpoint = self.endpoint.clone();
+        let auth = self.auth.clone();
+        let session = self.session.clone();
+        let latencies = self.latencies.clone();
+
+        async move {
+            let response = send(auth, session, latencies, "POST", endpoint, Some(body)).await?;
+            let text = JsFuture::from(response.text()?).await?;
+            Ok(GraphQLClient::parse(response.status(), &text.as_string().unwrap_or_default())?)
+        }
+    }
+
+    /// Read the field with the given name from the `data` of a response
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` - The value of the field
+    /// * `Err(BackendError)` - If the field is missing or has an unexpected form
+    pub fn field<T: DeserializeOwned>(data: &Value, name: &str) -> Result<T, BackendError> {
+        match data.get(name) {
+            Some(value) => T::deserialize(value)
+                .map_err(|err| BackendError::InvalidResponse(format!("The field {} is not valid: {}", name, err))),
+            None => Err(BackendError::InvalidResponse(format!("The field {} is missing", name)))
+        }
+    }
+
+    /// Send the given mutation and resolve to the boolean field with the given name,
+    /// i.e. whether the backend applied the mutation
+    pub fn apply(&self, mutation: &str, field: &'static str, variables: Value) -> Promise {
+        let request = self.execute(mutation, Some(variables));
+        future_to_promise(async move {
+            let applied: bool = GraphQLClient::field(&request.await?, field)?;
+            Ok(JsValue::from_bool(applied))
+        })
+    }
+
+    /// Convert the given value into a JS value, e.g. a struct into an object or a vector into an array
+    pub fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+        match serde_json::to_string(value) {
+            Ok(json) => JSON::parse(&json),
+            Err(err) => Err(BackendError::InvalidResponse(err.to_string()).into())
+        }
+    }
+
+    /// Build the body of a request for the given query and variables
+    fn body(query: &str, variables: Option<Value>) -> String {
+        match variables {
+            Some(variables) => json!({ "query": query, "variables": variables }),
+            None => json!({ "query": query })
+        }.to_string()
+    }
+
+    /// Read the result from the response of the backend.
+    /// Errors reported by the backend take precedence over partial data.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status of the response
+    /// * `body` - The body of the response
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Value)` - The `data` of the response
+    /// * `Err(BackendError)` - If the backend reported errors or the response is not a GraphQL response
+    fn parse(status: u16, body: &str) -> Result<Value, BackendError> {
+        let successful = (200..300).contains(&status);
+        let response: GraphQLResponse = match serde_json::from_str(body) {
+            Ok(response) => response,
+            Err(_) if !successful => return Err(BackendError::Http { status, body: String::from(body) }),
+            Err(err) => return Err(BackendError::InvalidResponse(err.to_string()))
+        };
+        if !response.errors.is_empty() {
+            return Err(BackendError::GraphQL(response.errors));
+        }
+        match response.data {
+            Some(data) => Ok(data),
+            None if !successful => Err(BackendError::Http { status, body: String::from(body) }),
+            None => Err(BackendError::InvalidResponse(String::from("The response contains no data")))
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl GraphQLClient {
+
+    /// Send a query or mutation to the backend
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The GraphQL document
+    /// * `variables` - The variables of the document as object, or `undefined`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<any>)` - Resolves to the `data` of the response.
+    ///   Rejects with a structured [`BackendError`] if the backend reports errors,
+    ///   or with a structured [`AuthError`] if the user is not authenticated
+    /// * `Err(JsValue)` - A structured [`BackendError`], if the variables cannot be serialized
+    ///
+    /// # Example
+    /// ```rust
+    /// let graphql = framework.graphql_client(String::from("https://api.my.site/graphql"))?;
+    /// const data = await graphql.query("query($id: ID!) { user(id: $id) { name } }", { id: 42 });
+    /// ```
+    pub fn query(&self, query: String, variables: &JsValue) -> Result<Promise, JsValue> {
+        let variables = if variables.is_undefined() || variables.is_null() {
+            None
+        } else {
+            let variables = match JSON::stringify(variables) {
+                Ok(variables) => String::from(variables),
+                Err(_) => return Err(BackendError::InvalidArgument(String::from("The variables cannot be serialized as JSON")).into())
+            };
+            match serde_json::from_str(&variables) {
+                Ok(variables) => Some(variables),
+                Err(err) => return Err(BackendError::InvalidArgument(format!("The variables are not valid: {}", err)).into())
+            }
+        };
+        let request = self.execute(&query, variables);
+
+        Ok(future_to_promise(async move {
+            GraphQLClient::to_js(&request.await?)
+        }))
+    }
+
+    /// Send a mutation to the backend, see [`GraphQLClient::query`]
+    pub fn mutate(&self, mutation: String, variables: &JsValue) -> Result<Promise, JsValue> {
+        self.query(mutation, variables)
+    }
+
+    /// Create a manager for the blacklisted terms, see [`BlacklistManager`]
+    pub fn blacklist(&self) -> BlacklistManager {
+        BlacklistManager::new(self.clone())
+    }
+
+    /// Create a manager for the moderation of alias suggestions, see [`AliasManager`]
+    pub fn aliases(&self) -> AliasManager {
+        AliasManager::new(self.clone())
+    }
+
+    /// Create a manager for the administrators of the panel, see [`AdminManager`]
+    pub fn admins(&self) -> AdminManager {
+        AdminManager::new(self.clone())
+    }
+
+    /// Create a manager for the backups of the backend, see [`BackupManager`]
+    pub fn backups(&self) -> BackupManager {
+        BackupManager::new(self.clone())
+    }
+
+    /// Create a manager for the background jobs of the backend, see [`JobsManager`]
+    pub fn jobs(&self) -> JobsManager {
+        JobsManager::new(self.clone())
+    }
+
+    /// Create a manager for the webhooks the backend calls on events, see [`WebhooksManager`]
+    pub fn webhooks(&self) -> WebhooksManager {
+        WebhooksManager::new(self.clone())
+    }
+
+    /// Create a manager for the API keys of external integrations, see [`ApiKeysManager`]
+    pub fn api_keys(&self) -> ApiKeysManager {
+        ApiKeysManager::new(self.clone())
+    }
+
+    /// Create a manager for the service accounts of machine clients, see [`ServiceAccountsManager`]
+    pub fn service_accounts(&self) -> ServiceAccountsManager {
+        ServiceAccountsManager::new(self.clone())
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+pub(super) mod tests {
+
+    use super::*;
+
+    /// The part of the schema of the backend the managers rely on
+    const SCHEMA: &str = include_str!("schema.graphql");
+
+    /// Whether the root field of the given document is declared for its kind of operation in the schema
+    pub fn declares_root_field(document: &str) -> bool {
+        let operation = if document.trim_start().starts_with("mutation") { "Mutation" } else { "Query" };
+        let field: String = match document.split_once('{') {
+            Some((_, selection)) => selection.trim_start()
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect(),
+            None => return false
+        };
+        let declarations = match SCHEMA.split_once(&format!("type {} {{", operation)).and_then(|(_, rest)| rest.split_once('}')) {
+            Some((declarations, _)) => declarations,
+            None => return false
+        };
+        !field.is_empty() && declarations.lines()
+            .filter_map(|line| line.trim().strip_prefix(field.as_str()))
+            .any(|rest| rest.starts_with('(') || rest.starts_with(':'))
+    }
+
+    #[test]
+    fn checks_schema() {
+        assert!(declares_root_field("query { blacklist }"));
+        assert!(declares_root_field("mutation($id: ID!) { cancelJob(id: $id) }"));
+        assert!(!declares_root_field("query($id: ID!) { cancelJob(id: $id) }"));
+        assert!(!declares_root_field("query { black }"));
+        assert!(!declares_root_field("query { }"));
+    }
+
+    #[test]
+    fn builds_body() {
+        assert_eq!(GraphQLClient::body("{ users { id } }", None), r#"{"query":"{ users { id } }"}"#);
+        let body: Value = serde_json::from_str(&GraphQLClient::body("q", Some(json!({ "id": 42 })))).unwrap();
+        assert_eq!(body["variables"]["id"], 42);
+    }
+
+    #[test]
+    fn parses_data() {
+        assert_eq!(GraphQLClient::parse(200, r#"{"data":{"users":[]}}"#), Ok(json!({ "users": [] })));
+    }
+
+    #[test]
+    fn parses_errors() {
+        let result = GraphQLClient::parse(200, r#"{"data":null,"errors":[{"message":"Forbidden","path":["users"],"extensions":{"code":"FORBIDDEN"}}]}"#);
+        match result {
+            Err(BackendError::GraphQL(errors)) => {
+                assert_eq!(errors[0].message, "Forbidden");
+                assert_eq!(errors[0].extensions, Some(json!({ "code": "FORBIDDEN" })));
+            },
+            other => panic!("unexpected result {:?}", other)
+        }
+    }
+
+    #[test]
+    fn reads_fields() {
+        let data = json!({ "blacklist": ["spam"] });
+        assert_eq!(GraphQLClient::field::<Vec<String>>(&data, "blacklist"), Ok(vec![String::from("spam")]));
+        assert!(matches!(GraphQLClient::field::<bool>(&data, "blacklist"), Err(BackendError::InvalidResponse(_))));
+        assert!(matches!(GraphQLClient::field::<bool>(&data, "removed"), Err(BackendError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn parses_http_errors() {
+        assert_eq!(
+            GraphQLClient::parse(502, "Bad Gateway"),
+            Err(BackendError::Http { status: 502, body: String::from("Bad Gateway") })
+        );
+        assert!(matches!(GraphQLClient::parse(200, "<html>"), Err(BackendError::InvalidResponse(_))));
+    }
+}
@@ -0,0 +1,262 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    json,
+    Value
+};
+
+use super::BackendError;
+use super::GraphQLClient;
+
+/// A service account, a machine client authenticating with the client credentials grant
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccount {
+
+    /// The identifier of the service account
+    pub id: String,
+
+    /// The name of the service account, e.g. of the integration using it
+    pub name: String,
+
+    /// The client id the service account authenticates with
+    pub client_id: String,
+
+    /// The roles assigned to the service account
+    pub roles: Vec<String>,
+
+    /// The point in time the service account was created at, as ISO 8601 date-time
+    pub created_at: String,
+
+    /// The point in time the credentials were last rotated at, as ISO 8601 date-time, `None` if never
+    #[serde(default)]
+    pub credentials_rotated_at: Option<String>
+}
+
+/// The credentials of a service account, the secret is only available once
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccountCredentials {
+
+    /// The client id the service account authenticates with
+    pub client_id: String,
+
+    /// The client secret, has to be handed to the machine client now
+    pub client_secret: String
+}
+
+/// A service account which was just created, with its credentials
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedServiceAccount {
+
+    /// The created service account
+    pub service_account: ServiceAccount,
+
+    /// The credentials of the service account
+    pub credentials: ServiceAccountCredentials
+}
+
+/// The ServiceAccountsManager maintains the service accounts of the backend,
+/// which operators otherwise have to create and rotate by hand.
+/// The client secret is only returned once, when the account is created or its credentials are rotated.
+#[wasm_bindgen]
+pub struct ServiceAccountsManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl ServiceAccountsManager {
+
+    const FIELDS: &'static str = "id name clientId roles createdAt credentialsRotatedAt";
+    const MUTATION_ROTATE: &'static str = "mutation($id: ID!) { rotateServiceAccountCredentials(id: $id) { clientId clientSecret } }";
+    const MUTATION_DELETE: &'static str = "mutation($id: ID!) { deleteServiceAccount(id: $id) }";
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> ServiceAccountsManager {
+        ServiceAccountsManager {
+            client
+        }
+    }
+
+    fn query_list() -> String {
+        format!("query {{ serviceAccounts {{ {} }} }}", Self::FIELDS)
+    }
+
+    fn mutation_create() -> String {
+        format!(
+            "mutation($name: String!, $roles: [String!]!) {{ createServiceAccount(name: $name, roles: $roles) {{ serviceAccount {{ {} }} credentials {{ clientId clientSecret }} }} }}",
+            Self::FIELDS
+        )
+    }
+
+    fn mutation_assign_roles() -> String {
+        format!(
+            "mutation($id: ID!, $roles: [String!]!) {{ setServiceAccountRoles(id: $id, roles: $roles) {{ {} }} }}",
+            Self::FIELDS
+        )
+    }
+
+    /// The given roles without blanks and duplicates, in alphabetical order
+    fn roles(roles: &[String]) -> Vec<&str> {
+        let mut roles: Vec<&str> = roles.iter()
+            .map(|role| role.trim())
+            .filter(|role| !role.is_empty())
+            .collect();
+        roles.sort_unstable();
+        roles.dedup();
+        roles
+    }
+
+    /// The variables creating a service account, if the name is valid
+    fn create_variables(name: &str, roles: &[String]) -> Result<Value, BackendError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(BackendError::InvalidArgument(String::from("No name given for the service account")));
+        }
+        Ok(json!({ "name": name, "roles": Self::roles(roles) }))
+    }
+}
+
+#[wasm_bindgen]
+impl ServiceAccountsManager {
+
+    /// Fetch the service accounts
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the service accounts with `id`, `name`, `clientId`, `roles`, `createdAt`
+    ///   and `credentialsRotatedAt`, rejects with a structured [`BackendError`] or [`AuthError`](crate::controller::auth_manager::AuthError)
+    ///
+    /// # Example
+    /// ```rust
+    /// let accounts = framework.graphql_client(String::from("https://api.my.site/graphql"))?.service_accounts();
+    /// for (const account of await accounts.list()) {
+    ///     // show account.name, account.clientId and account.roles
+    /// }
+    /// ```
+    pub fn list(&self) -> Promise {
+        let request = self.client.execute(&Self::query_list(), None);
+        future_to_promise(async move {
+            let accounts: Vec<ServiceAccount> = GraphQLClient::field(&request.await?, "serviceAccounts")?;
+            GraphQLClient::to_js(&accounts)
+        })
+    }
+
+    /// Create a service account with the given roles
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the service account
+    /// * `roles` - The roles assigned to the service account
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<object>)` - Resolves to the created `serviceAccount`, see [`ServiceAccountsManager::list`],
+    ///   and its `credentials` with `clientId` and `clientSecret`, which is not available later on
+    /// * `Err(JsValue)` - A structured [`BackendError`], if no name is given
+    pub fn create(&self, name: String, roles: Vec<String>) -> Result<Promise, JsValue> {
+        let variables = Self::create_variables(&name, &roles)?;
+        let request = self.client.execute(&Self::mutation_create(), Some(variables));
+        Ok(future_to_promise(async move {
+            let created: CreatedServiceAccount = GraphQLClient::field(&request.await?, "createServiceAccount")?;
+            GraphQLClient::to_js(&created)
+        }))
+    }
+
+    /// Issue a new client secret for the given service account, the previous one stops working
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object>` - Resolves to the new credentials with `clientId` and `clientSecret`,
+    ///   which is not available later on
+    pub fn rotate_credentials(&self, account_id: String) -> Promise {
+        let request = self.client.execute(Self::MUTATION_ROTATE, Some(json!({ "id": account_id })));
+        future_to_promise(async move {
+            let credentials: ServiceAccountCredentials = GraphQLClient::field(&request.await?, "rotateServiceAccountCredentials")?;
+            GraphQLClient::to_js(&credentials)
+        })
+    }
+
+    /// Replace the roles of the given service account
+    ///
+    /// # Arguments
+    ///
+    /// * `account_id` - The identifier of the service account
+    /// * `roles` - The roles assigned from now on, none to revoke all
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object>` - Resolves to the updated service account, see [`ServiceAccountsManager::list`]
+    pub fn assign_roles(&self, account_id: String, roles: Vec<String>) -> Promise {
+        let variables = json!({ "id": account_id, "roles": Self::roles(&roles) });
+        let request = self.client.execute(&Self::mutation_assign_roles(), Some(variables));
+        future_to_promise(async move {
+            let account: ServiceAccount = GraphQLClient::field(&request.await?, "setServiceAccountRoles")?;
+            GraphQLClient::to_js(&account)
+        })
+    }
+
+    /// Delete the given service account, its credentials stop working
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the service account was deleted, `false` if it did not exist
+    pub fn delete(&self, account_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_DELETE, "deleteServiceAccount", json!({ "id": account_id }))
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(&ServiceAccountsManager::query_list()));
+        assert!(declares_root_field(&ServiceAccountsManager::mutation_create()));
+        assert!(declares_root_field(&ServiceAccountsManager::mutation_assign_roles()));
+        assert!(declares_root_field(ServiceAccountsManager::MUTATION_ROTATE));
+        assert!(declares_root_field(ServiceAccountsManager::MUTATION_DELETE));
+    }
+
+    #[test]
+    fn validates_accounts() {
+        let roles = vec![String::from("importer"), String::from(" "), String::from(" exporter "), String::from("importer")];
+        assert_eq!(
+            ServiceAccountsManager::create_variables(" ci ", &roles),
+            Ok(json!({ "name": "ci", "roles": ["exporter", "importer"] }))
+        );
+        assert!(ServiceAccountsManager::create_variables("  ", &roles).is_err());
+        assert!(ServiceAccountsManager::roles(&[]).is_empty());
+    }
+
+    #[test]
+    fn reads_created_account() {
+        let data = json!({
+            "createServiceAccount": {
+                "serviceAccount": { "id": "9", "name": "ci", "clientId": "svc-ci", "roles": ["importer"], "createdAt": "2022-11-14T10:00:00Z" },
+                "credentials": { "clientId": "svc-ci", "clientSecret": "s3cr3t" }
+            }
+        });
+        let created: CreatedServiceAccount = GraphQLClient::field(&data, "createServiceAccount").unwrap();
+        assert_eq!(created.service_account.credentials_rotated_at, None);
+        assert_eq!(created.credentials.client_secret, "s3cr3t");
+        assert_eq!(serde_json::to_value(&created).unwrap()["serviceAccount"]["clientId"], "svc-ci");
+    }
+}
@@ -0,0 +1,301 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+use openidconnect::url::Url;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use serde_json::{
+    json,
+    Value
+};
+
+use super::BackendError;
+use super::GraphQLClient;
+
+/// A webhook the backend calls on events, as shown in the list of webhooks
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+
+    /// The identifier of the webhook
+    pub id: String,
+
+    /// The URL the events are posted to
+    pub url: String,
+
+    /// The events the webhook is called on, e.g. `backup.completed`
+    pub events: Vec<String>,
+
+    /// The point in time the webhook was created at, as ISO 8601 date-time
+    pub created_at: String,
+
+    /// The last characters of the secret the payloads are signed with, to tell the secrets apart
+    #[serde(default)]
+    pub secret_hint: String,
+
+    /// The secret for display, only its hint is readable, see [`WebhooksManager::mask`]
+    #[serde(default, skip_deserializing)]
+    pub masked_secret: String
+}
+
+/// A webhook which was just created, with the secret which is only available once
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedWebhook {
+
+    /// The created webhook
+    pub webhook: Webhook,
+
+    /// The secret the payloads are signed with, has to be stored by the receiver now
+    pub secret: String
+}
+
+/// The result of a test event sent to a webhook
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+
+    /// Whether the receiver accepted the event
+    pub delivered: bool,
+
+    /// The HTTP status the receiver responded with, if it responded
+    #[serde(default)]
+    pub status_code: Option<u16>,
+
+    /// The reason the event was not delivered, if any
+    #[serde(default)]
+    pub error: Option<String>
+}
+
+/// The WebhooksManager configures the webhooks the backend calls on events, e.g. when a backup completed.
+/// The secret the payloads are signed with is generated in the browser and only shown once after creating a webhook.
+#[wasm_bindgen]
+pub struct WebhooksManager {
+
+    /// The client to query the backend with
+    client: GraphQLClient
+}
+
+impl WebhooksManager {
+
+    const FIELDS: &'static str = "id url events createdAt secretHint";
+    const MUTATION_TEST: &'static str = "mutation($id: ID!) { testWebhook(id: $id) { delivered statusCode error } }";
+    const MUTATION_DELETE: &'static str = "mutation($id: ID!) { deleteWebhook(id: $id) }";
+
+    /// The number of random bytes of a secret
+    const SECRET_LENGTH: usize = 32;
+
+    /// The prefix of a secret, to recognize it e.g. in a leaked configuration
+    const SECRET_PREFIX: &'static str = "whsec_";
+
+    /// The number of characters of a secret the backend keeps as hint
+    const HINT_LENGTH: usize = 4;
+
+    /// Create a manager querying the backend with the given client
+    pub fn new(client: GraphQLClient) -> WebhooksManager {
+        WebhooksManager {
+            client
+        }
+    }
+
+    fn query_list() -> String {
+        format!("query {{ webhooks {{ {} }} }}", Self::FIELDS)
+    }
+
+    fn mutation_create() -> String {
+        format!(
+            "mutation($url: String!, $events: [String!]!, $secret: String!) {{ createWebhook(url: $url, events: $events, secret: $secret) {{ {} }} }}",
+            Self::FIELDS
+        )
+    }
+
+    /// Generate a secret to sign the payloads with, from the randomness of the WebCrypto API
+    fn generate_secret() -> Result<String, BackendError> {
+        let mut secret = [0; Self::SECRET_LENGTH];
+        getrandom::getrandom(&mut secret)
+            .map_err(|err| BackendError::InvalidArgument(format!("No secret could be generated: {}", err)))?;
+        Ok(format!("{}{}", Self::SECRET_PREFIX, base64::encode_config(secret, base64::URL_SAFE_NO_PAD)))
+    }
+
+    /// The last characters of the given secret, which the backend keeps to tell the secrets apart
+    fn hint(secret: &str) -> String {
+        let skip = secret.chars().count().saturating_sub(Self::HINT_LENGTH);
+        secret.chars().skip(skip).collect()
+    }
+
+    /// The secret for display, with everything but the given hint masked
+    fn mask(hint: &str) -> String {
+        format!("{}{}", "•".repeat(8), hint)
+    }
+
+    /// Fill in the display model of the given webhook
+    fn masked(mut webhook: Webhook) -> Webhook {
+        webhook.masked_secret = Self::mask(&webhook.secret_hint);
+        webhook
+    }
+
+    /// The variables creating a webhook, if the URL and the events are valid
+    fn create_variables(url: &str, events: &[String], secret: &str) -> Result<Value, BackendError> {
+        let url = Url::parse(url.trim())
+            .map_err(|_| BackendError::InvalidArgument(format!("The URL {} is not valid", url)))?;
+        if url.scheme() != "https" {
+            return Err(BackendError::InvalidArgument(String::from("The webhook has to be called by https")));
+        }
+        let events: Vec<&str> = events.iter()
+            .map(|event| event.trim())
+            .filter(|event| !event.is_empty())
+            .collect();
+        if events.is_empty() {
+            return Err(BackendError::InvalidArgument(String::from("No events given to call the webhook on")));
+        }
+        Ok(json!({ "url": url.as_str(), "events": events, "secret": secret }))
+    }
+}
+
+#[wasm_bindgen]
+impl WebhooksManager {
+
+    /// Fetch the configured webhooks
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object[]>` - Resolves to the webhooks with `id`, `url`, `events`, `createdAt`, `secretHint`
+    ///   and `maskedSecret`, rejects with a structured [`BackendError`] or [`AuthError`](crate::controller::auth_manager::AuthError)
+    ///
+    /// # Example
+    /// ```rust
+    /// let webhooks = framework.graphql_client(String::from("https://api.my.site/graphql"))?.webhooks();
+    /// for (const webhook of await webhooks.list()) {
+    ///     // show webhook.url with webhook.maskedSecret
+    /// }
+    /// ```
+    pub fn list(&self) -> Promise {
+        let request = self.client.execute(&Self::query_list(), None);
+        future_to_promise(async move {
+            let webhooks: Vec<Webhook> = GraphQLClient::field(&request.await?, "webhooks")?;
+            let webhooks: Vec<Webhook> = webhooks.into_iter().map(Self::masked).collect();
+            GraphQLClient::to_js(&webhooks)
+        })
+    }
+
+    /// Create a webhook with a newly generated secret
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL the events are posted to, has to use https
+    /// * `events` - The events the webhook is called on
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Promise<object>)` - Resolves to the created `webhook`, see [`WebhooksManager::list`],
+    ///   and its `secret`, which is not available later on
+    /// * `Err(JsValue)` - A structured [`BackendError`], if the URL or the events are not valid
+    ///
+    /// # Example
+    /// ```rust
+    /// const { webhook, secret } = await webhooks.create("https://ci.my.site/hook", ["backup.completed"]);
+    /// // show the secret once, so it can be configured at the receiver
+    /// ```
+    pub fn create(&self, url: String, events: Vec<String>) -> Result<Promise, JsValue> {
+        let secret = Self::generate_secret()?;
+        let variables = Self::create_variables(&url, &events, &secret)?;
+        let request = self.client.execute(&Self::mutation_create(), Some(variables));
+        Ok(future_to_promise(async move {
+            let mut webhook: Webhook = GraphQLClient::field(&request.await?, "createWebhook")?;
+            if webhook.secret_hint.is_empty() {
+                webhook.secret_hint = Self::hint(&secret);
+            }
+            GraphQLClient::to_js(&CreatedWebhook {
+                webhook: Self::masked(webhook),
+                secret
+            })
+        }))
+    }
+
+    /// Let the backend send a test event to the given webhook
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<object>` - Resolves to whether the event was `delivered`, the `statusCode` and the `error`, if any
+    pub fn test_fire(&self, webhook_id: String) -> Promise {
+        let request = self.client.execute(Self::MUTATION_TEST, Some(json!({ "id": webhook_id })));
+        future_to_promise(async move {
+            let delivery: WebhookDelivery = GraphQLClient::field(&request.await?, "testWebhook")?;
+            GraphQLClient::to_js(&delivery)
+        })
+    }
+
+    /// Delete the given webhook, the backend stops calling it
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<boolean>` - Resolves to whether the webhook was deleted, `false` if it did not exist
+    pub fn delete(&self, webhook_id: String) -> Promise {
+        self.client.apply(Self::MUTATION_DELETE, "deleteWebhook", json!({ "id": webhook_id }))
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::backend::tests::declares_root_field;
+
+    #[test]
+    fn documents_match_schema() {
+        assert!(declares_root_field(&WebhooksManager::query_list()));
+        assert!(declares_root_field(&WebhooksManager::mutation_create()));
+        assert!(declares_root_field(WebhooksManager::MUTATION_TEST));
+        assert!(declares_root_field(WebhooksManager::MUTATION_DELETE));
+    }
+
+    #[test]
+    fn generates_secrets() {
+        let secret = WebhooksManager::generate_secret().unwrap();
+        assert!(secret.starts_with("whsec_"));
+        assert_eq!(secret.len(), 6 + 43);
+        assert_ne!(secret, WebhooksManager::generate_secret().unwrap());
+    }
+
+    #[test]
+    fn masks_secrets() {
+        assert_eq!(WebhooksManager::hint("whsec_abcdef"), "cdef");
+        assert_eq!(WebhooksManager::hint("ab"), "ab");
+        assert_eq!(WebhooksManager::mask("cdef"), "••••••••cdef");
+    }
+
+    #[test]
+    fn validates_webhooks() {
+        let events = vec![String::from(" backup.completed ")];
+        assert_eq!(
+            WebhooksManager::create_variables("https://ci.my.site/hook", &events, "whsec_x"),
+            Ok(json!({ "url": "https://ci.my.site/hook", "events": ["backup.completed"], "secret": "whsec_x" }))
+        );
+        assert!(WebhooksManager::create_variables("http://ci.my.site/hook", &events, "whsec_x").is_err());
+        assert!(WebhooksManager::create_variables("ci.my.site", &events, "whsec_x").is_err());
+        assert!(WebhooksManager::create_variables("https://ci.my.site/hook", &[String::from(" ")], "whsec_x").is_err());
+    }
+
+    #[test]
+    fn reads_webhooks() {
+        let data = json!({
+            "webhooks": [{ "id": "3", "url": "https://ci.my.site/hook", "events": ["backup.completed"], "createdAt": "2022-11-14T10:00:00Z", "secretHint": "cdef" }],
+            "testWebhook": { "delivered": false, "statusCode": 502 }
+        });
+        let webhooks: Vec<Webhook> = GraphQLClient::field(&data, "webhooks").unwrap();
+        let webhook = WebhooksManager::masked(webhooks[0].clone());
+        assert_eq!(serde_json::to_value(&webhook).unwrap()["maskedSecret"], "••••••••cdef");
+        let delivery: WebhookDelivery = GraphQLClient::field(&data, "testWebhook").unwrap();
+        assert_eq!(delivery.status_code, Some(502));
+        assert_eq!(delivery.error, None);
+    }
+}
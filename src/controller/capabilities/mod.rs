@@ -0,0 +1,229 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use js_sys::{
+    Array,
+    Object,
+    Reflect
+};
+use std::collections::BTreeSet;
+use super::Diagnostics;
+
+/// The browser APIs the framework relies on
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+
+    /// `BroadcastChannel`, to keep the session in sync across tabs
+    BroadcastChannel,
+
+    /// `crypto.subtle`, to hash and encrypt on the client
+    SubtleCrypto,
+
+    /// `indexedDB`, to keep larger data on the client
+    IndexedDb,
+
+    /// `WeakRef`, to release callbacks registered from JS
+    WeakRef
+}
+
+impl Capability {
+
+    /// All capabilities, in the order they are reported
+    const ALL: [Capability; 4] = [
+        Capability::BroadcastChannel,
+        Capability::SubtleCrypto,
+        Capability::IndexedDb,
+        Capability::WeakRef
+    ];
+
+    /// The path of the API on the global object
+    fn path(&self) -> &'static [&'static str] {
+        match self {
+            Capability::BroadcastChannel => &["BroadcastChannel"],
+            Capability::SubtleCrypto => &["crypto", "subtle"],
+            Capability::IndexedDb => &["indexedDB"],
+            Capability::WeakRef => &["WeakRef"]
+        }
+    }
+
+    /// The name of the API as it is accessed in JS, e.g. `crypto.subtle`
+    pub fn name(&self) -> String {
+        self.path().join(".")
+    }
+
+    /// What the framework needs the API for
+    fn purpose(&self) -> &'static str {
+        match self {
+            Capability::BroadcastChannel => "keeping the session in sync across tabs",
+            Capability::SubtleCrypto => "hashing and encrypting on the client",
+            Capability::IndexedDb => "keeping larger data on the client",
+            Capability::WeakRef => "releasing callbacks registered from JS"
+        }
+    }
+}
+
+/// The Capabilities check whether the browser provides the APIs the framework relies on,
+/// e.g. to fail early with a precise report on outdated kiosk browsers.
+/// Polyfills can be registered for the APIs, which are installed on the global object if the API is missing.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+
+    /// The capabilities which have to be available
+    required: BTreeSet<Capability>,
+
+    /// The polyfills to install if a capability is missing
+    polyfills: Vec<(Capability, JsValue)>
+}
+
+impl Capabilities {
+
+    /// The code of the error returned if capabilities are missing, see [`Capabilities::ensure`]
+    const ERROR_CODE: &'static str = "missing_capabilities";
+
+    /// The capabilities which are not available and could not be polyfilled
+    fn check(&self) -> Vec<Capability> {
+        let global = js_sys::global();
+        self.required.iter()
+            .filter(|capability| !Diagnostics::has_property(&global, capability.path()))
+            .filter(|capability| !self.polyfill(&global, **capability))
+            .copied()
+            .collect()
+    }
+
+    /// Install the registered polyfill of the capability, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `true` - If the capability is available after installing the polyfill
+    /// * `false` - If there is no polyfill or it could not be installed, e.g. as its parent object is missing
+    fn polyfill(&self, global: &JsValue, capability: Capability) -> bool {
+        let polyfill = match self.polyfills.iter().rev().find(|(registered, _)| *registered == capability) {
+            Some((_, polyfill)) => polyfill,
+            None => return false
+        };
+        let (property, parents) = match capability.path().split_last() {
+            Some(split) => split,
+            None => return false
+        };
+        let mut parent = global.clone();
+        for name in parents {
+            parent = match Reflect::get(&parent, &JsValue::from_str(name)) {
+                Ok(value) if value.is_object() => value,
+                _ => return false
+            };
+        }
+        Reflect::set(&parent, &JsValue::from_str(property), polyfill).unwrap_or(false)
+            && Diagnostics::has_property(global, capability.path())
+    }
+
+    /// Describe the missing capabilities, e.g. to show them on the kiosk
+    fn report(missing: &[Capability]) -> String {
+        let lines: Vec<String> = missing.iter()
+            .map(|capability| format!("{}, needed for {}", capability.name(), capability.purpose()))
+            .collect();
+        format!("The browser lacks the following APIs and no polyfill is registered:\n{}", lines.join("\n"))
+    }
+}
+
+#[wasm_bindgen]
+impl Capabilities {
+
+    /// Create the check for all capabilities
+    ///
+    /// # Example
+    /// ```rust
+    /// let capabilities = Capabilities::new()
+    ///     .without(Capability::IndexedDb)
+    ///     .with_polyfill(Capability::WeakRef, weak_ref_polyfill);
+    /// capabilities.ensure()?;
+    /// let framework = Framework::new(client_data, storage);
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Capabilities {
+        Capabilities {
+            required: Capability::ALL.iter().copied().collect(),
+            polyfills: Vec::new()
+        }
+    }
+
+    /// Do not require the given capability, e.g. as the page does not use the feature relying on it.
+    /// Consumes this instance!
+    pub fn without(mut self, capability: Capability) -> Capabilities {
+        self.required.remove(&capability);
+        self
+    }
+
+    /// Register a polyfill or an alternative implementation for the given capability,
+    /// which is installed on the global object if the browser lacks the API.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `capability` - The capability to polyfill
+    /// * `polyfill` - The value to install, e.g. a constructor for `BroadcastChannel` or an object for `crypto.subtle`
+    pub fn with_polyfill(mut self, capability: Capability, polyfill: JsValue) -> Capabilities {
+        self.polyfills.push((capability, polyfill));
+        self
+    }
+
+    /// The names of the required APIs which are not available and could not be polyfilled.
+    /// Registered polyfills of missing APIs are installed.
+    pub fn missing(&self) -> Vec<String> {
+        self.check().iter().map(Capability::name).collect()
+    }
+
+    /// Ensure all required APIs are available, installing the registered polyfills of missing ones.
+    /// Call it before the [`Framework`](super::Framework) is created.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If all required APIs are available
+    /// * `Err(JsValue)` - An object carrying `code` `"missing_capabilities"`, a `message` describing the missing APIs
+    ///   and what they are needed for, and their names as `missing`
+    pub fn ensure(&self) -> Result<(), JsValue> {
+        let missing = self.check();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let object = Object::new();
+        let names: Array = missing.iter().map(|capability| JsValue::from(capability.name())).collect();
+        let _ = Reflect::set(&object, &JsValue::from_str("code"), &JsValue::from_str(Self::ERROR_CODE));
+        let _ = Reflect::set(&object, &JsValue::from_str("message"), &JsValue::from(Self::report(&missing)));
+        let _ = Reflect::set(&object, &JsValue::from_str("missing"), &names);
+        Err(object.into())
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn report_names_missing_apis() {
+        let report = Capabilities::report(&[Capability::SubtleCrypto, Capability::WeakRef]);
+        assert!(report.contains("crypto.subtle, needed for hashing and encrypting on the client"));
+        assert!(report.contains("WeakRef, needed for releasing callbacks registered from JS"));
+        assert!(!report.contains("BroadcastChannel"));
+    }
+
+    #[test]
+    fn without_removes_requirement() {
+        let capabilities = Capabilities::new().without(Capability::IndexedDb);
+        assert_eq!(capabilities.required.len(), 3);
+        assert!(!capabilities.required.contains(&Capability::IndexedDb));
+    }
+}
@@ -0,0 +1,148 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use crate::utils;
+use super::PersistentStore;
+use super::StorageKeys;
+use js_sys::{
+    Reflect,
+    Uint8Array,
+    WebAssembly
+};
+
+/// The Diagnostics struct holds the results of the environment checks run on startup
+/// and can be rendered into a report for support requests.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Diagnostics {
+
+    /// Whether the WebCrypto API (`crypto.subtle`) is available
+    pub web_crypto: bool,
+
+    /// Whether values can be written to and read from the used storage
+    pub storage_writable: bool,
+
+    /// Whether the browser accepts cookies at all
+    pub cookies_enabled: bool,
+
+    /// Whether the browser is able to run WebAssembly SIMD instructions
+    pub wasm_simd: bool
+}
+
+impl Diagnostics {
+
+    const ID_PROBE: &'static str = "diagnostics_probe";
+
+    /// A minimal WebAssembly module using a SIMD instruction.
+    /// Only browsers supporting SIMD consider it valid.
+    const SIMD_MODULE: [u8; 31] = [
+        0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3,
+        2, 1, 0, 10, 10, 1, 8, 0, 65, 0, 253, 15, 253, 98, 11
+    ];
+
+    /// Run all environment checks.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The [`PersistentStore`] which is used by the framework
+    /// * `keys` - The [`StorageKeys`] to probe the storage under
+    ///
+    /// # Example
+    /// ```rust
+    /// let storage: &dyn PersistentStore; // provided elsewhere
+    /// let diagnostics = Diagnostics::run(storage, &keys);
+    /// if !diagnostics.storage_writable {
+    ///     // warn the user
+    /// }
+    /// ```
+    pub fn run(storage: &dyn PersistentStore, keys: &StorageKeys) -> Self {
+        let global = js_sys::global();
+        Diagnostics {
+            web_crypto: Self::has_property(&global, &["crypto", "subtle"]),
+            storage_writable: Self::check_storage(storage, keys),
+            cookies_enabled: Self::check_cookies(&global),
+            wasm_simd: Self::check_simd()
+        }
+    }
+
+    /// Check whether the given path of properties is present and defined on the object
+    pub(crate) fn has_property(object: &JsValue, path: &[&str]) -> bool {
+        let mut current = object.clone();
+        for property in path {
+            current = match Reflect::get(&current, &JsValue::from_str(property)) {
+                Ok(value) if !value.is_undefined() && !value.is_null() => value,
+                _ => return false
+            };
+        }
+        true
+    }
+
+    fn check_storage(storage: &dyn PersistentStore, keys: &StorageKeys) -> bool {
+        let key = keys.key(Self::ID_PROBE);
+        let written = storage.set(&key, Self::ID_PROBE).is_ok()
+            && matches!(storage.get(&key), Ok(Some(value)) if value == Self::ID_PROBE);
+        let removed = storage.remove(&key).is_ok();
+        written && removed
+    }
+
+    fn check_cookies(global: &JsValue) -> bool {
+        Reflect::get(global, &JsValue::from_str("navigator"))
+            .and_then(|navigator| Reflect::get(&navigator, &JsValue::from_str("cookieEnabled")))
+            .map(|enabled| enabled.as_bool().unwrap_or(false))
+            .unwrap_or(false)
+    }
+
+    fn check_simd() -> bool {
+        let module = Uint8Array::from(&Self::SIMD_MODULE[..]);
+        WebAssembly::validate(&module).unwrap_or(false)
+    }
+}
+
+#[wasm_bindgen]
+impl Diagnostics {
+
+    /// Render the diagnostics into a human readable report,
+    /// suitable to be copied into a support request.
+    ///
+    /// # Example
+    /// ```rust
+    /// let report: String = framework.diagnostics().report();
+    /// ```
+    pub fn report(&self) -> String {
+        let check = |ok: bool| if ok { "ok" } else { "unavailable" };
+        format!(
+            "kifapwa {}\nweb crypto: {}\nstorage writable: {}\ncookies enabled: {}\nwasm simd: {}",
+            utils::version(),
+            check(self.web_crypto),
+            check(self.storage_writable),
+            check(self.cookies_enabled),
+            check(self.wasm_simd)
+        )
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn report_lists_all_checks() {
+        let diagnostics = Diagnostics {
+            web_crypto: true,
+            storage_writable: false,
+            cookies_enabled: true,
+            wasm_simd: false
+        };
+        let report = diagnostics.report();
+        assert!(report.contains("web crypto: ok"));
+        assert!(report.contains("storage writable: unavailable"));
+        assert!(report.contains("cookies enabled: ok"));
+        assert!(report.contains("wasm simd: unavailable"));
+    }
+}
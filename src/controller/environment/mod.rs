@@ -0,0 +1,122 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use crate::utils;
+
+/// The Environment describes what the framework was configured for,
+/// so the host page can show it and detect a configuration drift.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Environment {
+
+    /// The URL of the authentication provider
+    auth_url: String,
+
+    /// The client id registered at the authentication provider
+    client_id: String
+}
+
+impl Environment {
+
+    /// The name of the meta tag the host page declares the expected authentication provider in
+    const META_AUTH_URL: &'static str = "kifapwa-auth-url";
+
+    /// Create a new Environment instance with the given values
+    pub fn new(auth_url: String, client_id: String) -> Self {
+        Environment {
+            auth_url,
+            client_id
+        }
+    }
+
+    /// Compare against the expected authentication provider.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(String)` - A warning describing the mismatch
+    /// * `None` - If the expectation matches or nothing is expected
+    pub fn compare(&self, expected_auth_url: Option<String>) -> Option<String> {
+        match expected_auth_url {
+            Some(expected) if expected.trim_end_matches('/') != self.auth_url.trim_end_matches('/') => Some(
+                format!(
+                    "The framework is configured for {}, but the page expects {}!",
+                    self.auth_url,
+                    expected
+                )
+            ),
+            _ => None
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Environment {
+
+    /// The URL of the authentication provider
+    pub fn auth_url(&self) -> String {
+        self.auth_url.clone()
+    }
+
+    /// The client id registered at the authentication provider
+    pub fn client_id(&self) -> String {
+        self.client_id.clone()
+    }
+
+    /// The version of this crate, including the git revision it was built from
+    pub fn version(&self) -> String {
+        utils::version()
+    }
+
+    /// Compare the configuration against the one the host page expects in its
+    /// `<meta name="kifapwa-auth-url" content="...">` tag.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(String)` - A warning to show if the configuration does not match the page
+    /// * `None` - If it matches or the page declares no expectation
+    ///
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if let Some(warning) = framework.environment().check() {
+    ///     // show a warning banner
+    /// }
+    /// ```
+    pub fn check(&self) -> Option<String> {
+        self.compare(Self::expected_auth_url())
+    }
+
+    /// The authentication provider the host page expects, read from its meta tag
+    fn expected_auth_url() -> Option<String> {
+        let document = web_sys::window()?.document()?;
+        let selector = format!("meta[name=\"{}\"]", Self::META_AUTH_URL);
+        document.query_selector(&selector).ok()??.get_attribute("content")
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn environment() -> Environment {
+        Environment::new(String::from("https://auth.kit.edu/auth"), String::from("admin-panel"))
+    }
+
+    #[test]
+    fn matching_environment() {
+        assert_eq!(environment().compare(None), None);
+        assert_eq!(environment().compare(Some(String::from("https://auth.kit.edu/auth/"))), None);
+    }
+
+    #[test]
+    fn mismatching_environment() {
+        let warning = environment().compare(Some(String::from("https://staging.kit.edu/auth")));
+        assert!(warning.unwrap().contains("https://staging.kit.edu/auth"));
+    }
+}
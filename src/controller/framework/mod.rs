@@ -1,23 +1,182 @@
-/// SPDX-License-Identifier: MIT
-/// SPDX-License-Identifier: APACHE
-/// 
-/// 2022, Patrick Schneider <patrick@itermori.de>
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
 
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::throw_str;
 use web_sys::Storage;
+use js_sys::{
+    Function,
+    Promise
+};
+use wasm_bindgen_futures::future_to_promise;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::utils::{
+    set_panic_hook,
+    version
+};
+use crate::stats::Latencies;
+use super::ApiClient;
 use super::AuthManager;
+use super::GraphQLClient;
+use super::PersistentStore;
+use super::Diagnostics;
+use super::EncryptedStore;
+use super::Environment;
+use super::Roles;
+use super::StorageKeys;
+use super::TokenRefresher;
+use super::refresh::refresh_session;
+use super::{
+    SessionEvent,
+    SessionSync
+};
+use super::{
+    SecurityEvent,
+    SecurityEventKind
+};
 use super::auth_manager::{
+    AuthError,
+    AuthProviderRegistry,
     ClientData,
 };
 
-use oauth2::url::Url;
+use openidconnect::url::Url;
 
 #[wasm_bindgen]
 pub struct Framework {
 
-    session: Storage,
-    auth: AuthManager
+    session: Rc<dyn PersistentStore>,
+    auth: Rc<RefCell<AuthManager>>,
+
+    /// Renews the tokens in the background, if started
+    refresher: Rc<RefCell<Option<Rc<RefCell<TokenRefresher>>>>>,
+
+    /// Keeps the session consistent with the other tabs, if started
+    sync: Rc<RefCell<Option<SessionSync>>>,
+
+    /// The identity providers the user may authenticate at
+    providers: AuthProviderRegistry,
+
+    /// The name of the provider the [`AuthManager`] is configured for
+    provider: String,
+
+    /// The keys of the state of the framework itself, in the namespace of the default provider
+    keys: StorageKeys,
+
+    /// The durations of the requests sent to the backend by the clients of this framework
+    latencies: Rc<RefCell<Latencies>>
+}
+
+impl Framework {
+
+    /// The scope of the keys of the state of the framework itself
+    const SCOPE: &'static str = "framework";
+
+    /// The id of the provider of the last successful authentication,
+    /// shared by all providers to restore its session
+    const ID_PROVIDER: &'static str = "provider";
+
+    /// The id of the provider of the initiated authentication, kept as long as its PKCE state,
+    /// to complete the authentication at this provider after the redirect
+    const ID_PENDING_PROVIDER: &'static str = "pending_provider";
+
+    /// The id of the channel the session is kept in sync with the other tabs on
+    const ID_SESSION_CHANNEL: &'static str = "session";
+
+    /// Create the framework with an arbitrary [`PersistentStore`],
+    /// e.g. a [`MemoryStore`](super::MemoryStore) outside of the browser.
+    /// A session persisted in the store is restored.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `client_data` - See [`ClientData`](ClientData)
+    /// * `store` - The [`PersistentStore`] to keep the authentication state in
+    /// 
+    /// # Example
+    /// ```rust
+    /// let client_data = ClientData::from(/* */);
+    /// let framework = Framework::with_store(client_data, Box::new(MemoryStore::new()));
+    /// ```
+    pub fn with_store(
+        client_data: ClientData,
+        store: Box<dyn PersistentStore>
+    ) -> Framework {
+        Framework::with_providers_in_store(AuthProviderRegistry::single(client_data), store)
+    }
+
+    /// Create the framework for several identity providers with an arbitrary [`PersistentStore`].
+    /// The provider of the last successful authentication and its session are restored,
+    /// otherwise the default provider is used.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `providers` - See [`AuthProviderRegistry`]
+    /// * `store` - The [`PersistentStore`] to keep the authentication state in
+    pub fn with_providers_in_store(
+        providers: AuthProviderRegistry,
+        store: Box<dyn PersistentStore>
+    ) -> Framework {
+        set_panic_hook();
+        let keys = StorageKeys::new(providers.default_client_data().storage_namespace(), Self::SCOPE);
+        let provider = match store.get(&keys.key(Self::ID_PROVIDER)) {
+            Ok(Some(name)) if providers.get(&name).is_some() => name,
+            _ => String::from(providers.default_name())
+        };
+        let client_data = providers.get(&provider)
+            .expect("the provider is registered")
+            .clone();
+        let mut auth = AuthManager::new(client_data);
+
+        // Restore a session from a previous page load, if there is any.
+        // If it cannot be restored, the user simply has to authenticate again.
+        let _ = auth.load(store.as_ref());
+
+        Framework {
+            auth: Rc::new(RefCell::new(auth)),
+            session: Rc::from(store),
+            refresher: Rc::new(RefCell::new(None)),
+            sync: Rc::new(RefCell::new(None)),
+            providers,
+            provider,
+            keys,
+            latencies: Rc::new(RefCell::new(Latencies::new()))
+        }
+    }
+
+    /// Inform the other tabs about the change of the session, if the synchronization is started
+    fn broadcast(&self, event: SessionEvent) {
+        if let Some(sync) = self.sync.borrow().as_ref() {
+            sync.broadcast(event);
+        }
+    }
+
+    /// Configure the [`AuthManager`] for the provider with the given name
+    fn choose_provider(&mut self, provider: String) -> Result<(), AuthError> {
+        if provider != self.provider {
+            let client_data = match self.providers.get(&provider) {
+                Some(client_data) => client_data.clone(),
+                None => return Err(AuthError::InvalidConfiguration(format!("The provider {} is not registered!", provider)))
+            };
+            self.stop_token_refresh();
+            *self.auth.borrow_mut() = AuthManager::new(client_data);
+            self.provider = provider;
+        }
+        Ok(())
+    }
+
+    /// Remember the provider the [`AuthManager`] is configured for, to restore its session on the next page load.
+    /// Only called once the user authenticated there, so an abandoned login does not hide the current session.
+    fn remember_provider(&self) -> Result<(), AuthError> {
+        let key = self.keys.key(Self::ID_PROVIDER);
+        if self.provider == self.providers.default_name() {
+            self.session.remove(&key)?;
+        } else {
+            self.session.set(&key, &self.provider)?;
+        }
+        Ok(())
+    }
 }
 
 #[wasm_bindgen]
@@ -28,7 +187,7 @@ impl Framework {
     /// # Arguments
     /// 
     /// * `client_data` - See [`ClientData`](ClientData)
-    /// * `storage` - A [`Storage`](Storage)
+    /// * `storage` - A [`Storage`](Storage), e.g. `localStorage` or `sessionStorage`
     /// 
     /// # Returns
     /// 
@@ -44,68 +203,657 @@ impl Framework {
         client_data: ClientData,
         storage: Storage
     ) -> Framework {
-        Framework {
-            auth: AuthManager::new(client_data),
-            session: storage
-        }
+        Framework::with_store(client_data, Box::new(storage))
+    }
+
+    /// Create the framework with the state persisted encrypted, see [`EncryptedStore`]
+    /// 
+    /// # Arguments
+    /// 
+    /// * `client_data` - See [`ClientData`](ClientData)
+    /// * `storage` - A [`Storage`](Storage), e.g. `localStorage` or `sessionStorage`
+    /// * `secret` - The secret to encrypt the state with, kept outside of the storage,
+    ///   e.g. delivered by the backend with the page
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework = Framework::with_encrypted_storage(client_data, storage, secret);
+    /// ```
+    pub fn with_encrypted_storage(
+        client_data: ClientData,
+        storage: Storage,
+        secret: String
+    ) -> Framework {
+        Framework::with_store(client_data, Box::new(EncryptedStore::new(Box::new(storage), &secret)))
+    }
+
+    /// Create the framework for several identity providers, e.g. the university SSO and a fallback.
+    /// The provider is chosen on [`Framework::initiate_authentication`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `providers` - See [`AuthProviderRegistry`]
+    /// * `storage` - A [`Storage`](Storage), e.g. `localStorage` or `sessionStorage`
+    /// 
+    /// # Example
+    /// ```rust
+    /// let providers = AuthProviderRegistry::new(String::from("kit"), kit_client_data)
+    ///     .register(String::from("google"), google_client_data);
+    /// let framework = Framework::with_providers(providers, storage);
+    /// let url = framework.initiate_authentication(Some(String::from("google")))?;
+    /// ```
+    pub fn with_providers(
+        providers: AuthProviderRegistry,
+        storage: Storage
+    ) -> Framework {
+        Framework::with_providers_in_store(providers, Box::new(storage))
+    }
+
+    /// The name of the identity provider the user authenticates at,
+    /// [`AuthProviderRegistry::DEFAULT_NAME`] for a framework created with a single provider
+    pub fn provider(&self) -> String {
+        self.provider.clone()
     }
 
     /// Initiate the authentication process and retrieve the URL to authenticate on
     /// 
-    /// # Returns
+    /// # Arguments
+    /// 
+    /// * `provider` - The name of the identity provider to authenticate at, the default provider if `undefined`
+    /// * `return_url` - The page to return to after the authentication, the current location if `undefined`.
+    ///   See [`Framework::get_return_url`]
     /// 
-    /// * `String` - `String` representing the URL the user needs to authenticate on
+    /// # Returns
     /// 
-    /// # Throws
-    /// Throws if an error occurred during initiation containing a description of the error
+    /// * `Ok(String)` - `String` representing the URL the user needs to authenticate on
+    /// * `Err(JsValue)` - A structured [`AuthError`] with `code` and `message`, if the initiation failed
     /// 
     /// # Example
     /// ```rust
     /// let framework: Framework;
-    /// let authentication_url: String = framework.initiate_authentication();
+    /// let authentication_url: String = framework.initiate_authentication(None, None)?;
     /// ```
-    pub fn initiate_authentication(&mut self) -> String {
+    pub fn initiate_authentication(
+        &mut self,
+        provider: Option<String>,
+        return_url: Option<String>
+    ) -> Result<String, JsValue> {
 
-        match self.auth.init_authentication(&self.session) {
-            Ok(url) => url.to_string(),
-            Err(err) => throw_str(format!("{:?}", err))
-        }
+        self.choose_provider(provider.unwrap_or_else(|| String::from(self.providers.default_name())))?;
+
+        let return_url = return_url.or_else(|| {
+            web_sys::window().and_then(|window| window.location().href().ok())
+        });
+
+        let url = self.auth.borrow_mut().init_authentication(self.session.as_ref(), return_url)?;
+        self.session.set(&self.keys.key(Self::ID_PENDING_PROVIDER), &self.provider)
+            .map_err(AuthError::from)?;
+        Ok(url.to_string())
     }
 
     /// Authenticate the user by providing the url the user got redirected to.
-    /// This URL `has` to contain a parameter `state` and `code`.
+    /// This URL `has` to contain a parameter `state` and `code`, either in its query or in its fragment.
     /// 
     /// # Arguments
     /// 
     /// * `response` - The response in form of the redirection url after authentication of the user.
     /// 
-    /// # Throws
-    /// If an error occurred, an error containing the cause is thrown.
+    /// # Returns
+    /// 
+    /// * `Ok(Framework)` - The authenticated framework
+    /// * `Err(JsValue)` - A structured [`AuthError`] with `code` and `message`.
+    ///   The instance is consumed, create a new one to try again.
     /// 
     /// # Example
     /// ```rust
     /// let framework: Framewok = 
     /// let response: String = String::from("https://example.com/?state=abc123&code=qwert12345");
-    /// framework.authenticate(response);
-    /// // The user is now authenticated, if nothing was thrown.
+    /// let framework = framework.authenticate(response).await?;
+    /// // The user is now authenticated
     /// ```
-    pub async fn authenticate(mut self, response: String) -> Self {
+    pub async fn authenticate(mut self, response: String) -> Result<Framework, JsValue> {
 
         let url = match Url::parse(&response) {
             Ok(url) => url,
-            _ => throw_str(&format!("{} is not a valid url.", response))
+            _ => return Err(AuthError::InvalidResponse(format!("{} is not a valid url.", response)).into())
         };
 
-        let (code, state) = match AuthManager::get_response(url) {
-            Ok(values) => values,
-            Err(err) => throw_str(&format!("{}", err))
-        };
-        let (auth, result) = self.auth.exchange_token(code, state, Some(&self.session)).await;
-        self.auth = auth;
-        if let Err(err) = result {
-            throw_str(&format!("{}", err))
+        let (code, state) = AuthManager::get_response(url)?;
+
+        // Complete the authentication at the provider it was initiated at, its state can only be used once
+        let pending = self.keys.key(Self::ID_PENDING_PROVIDER);
+        if let Some(provider) = self.session.get(&pending).map_err(AuthError::from)? {
+            self.session.remove(&pending).map_err(AuthError::from)?;
+            self.choose_provider(provider)?;
+        }
+
+        let (request, nonce) = self.auth.borrow_mut()
+            .exchange_request(code, state, Some(self.session.as_ref()), js_sys::Date::now())?;
+        let tokens = request.await?;
+
+        let mut auth = self.auth.borrow_mut();
+        auth.apply_exchange(tokens, &nonce, js_sys::Date::now())?;
+        auth.store(self.session.as_ref())?;
+        drop(auth);
+        self.remember_provider()?;
+
+        self.broadcast(SessionEvent::Login);
+        Ok(self)
+    }
+
+    /// The page the user wanted to visit before being redirected to the identity provider,
+    /// as given to or recorded by [`Framework::initiate_authentication`].
+    /// Only navigate to it if it belongs to the own origin.
+    /// 
+    /// # Returns
+    /// 
+    /// * `Some(String)` - The URL of the page, once [`Framework::authenticate`] succeeded
+    /// * `None` - If no page is known
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework = framework.authenticate(response).await?;
+    /// if let Some(url) = framework.get_return_url() {
+    ///     // navigate back to url
+    /// }
+    /// ```
+    pub fn get_return_url(&self) -> Option<String> {
+        self.auth.borrow().return_url().map(String::from)
+    }
+
+    /// Renew the session by exchanging the refresh token for new tokens,
+    /// without requiring the user to log in again.
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(Framework)` - The framework holding the renewed tokens
+    /// * `Err(JsValue)` - A structured [`AuthError`] with `code` and `message`.
+    ///   The user then needs to authenticate again.
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework; // already authenticated
+    /// let framework = framework.refresh().await?;
+    /// // The tokens are renewed
+    /// ```
+    pub async fn refresh(self) -> Result<Framework, JsValue> {
+
+        refresh_session(&self.auth, &self.session).await?;
+        self.broadcast(SessionEvent::Refresh);
+        Ok(self)
+    }
+
+    /// Create a client to send requests to the backend on behalf of the authenticated user,
+    /// see [`ApiClient`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `base_url` - The URL of the backend, the paths of the requests are resolved against
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(ApiClient)` - The client, sharing the tokens with this framework
+    /// * `Err(JsValue)` - A structured [`AuthError`], if the URL is not valid
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework; // already authenticated
+    /// let api = framework.api_client(String::from("https://api.my.site/v1"))?;
+    /// const response = await api.get("users");
+    /// ```
+    pub fn api_client(&self, base_url: String) -> Result<ApiClient, JsValue> {
+        Ok(ApiClient::new(&base_url, self.auth.clone(), self.session.clone(), self.latencies.clone())?)
+    }
+
+    /// Create a client to query the GraphQL backend on behalf of the authenticated user,
+    /// see [`GraphQLClient`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `endpoint` - The URL of the GraphQL endpoint
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(GraphQLClient)` - The client, sharing the tokens with this framework
+    /// * `Err(JsValue)` - A structured [`AuthError`], if the URL is not valid
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework; // already authenticated
+    /// let graphql = framework.graphql_client(String::from("https://api.my.site/graphql"))?;
+    /// const data = await graphql.query("{ users { id } }", undefined);
+    /// ```
+    pub fn graphql_client(&self, endpoint: String) -> Result<GraphQLClient, JsValue> {
+        Ok(GraphQLClient::new(&endpoint, self.auth.clone(), self.session.clone(), self.latencies.clone())?)
+    }
+
+    /// The durations of the requests the clients of this framework sent to the backend,
+    /// see [`Framework::api_client`] and [`Framework::graphql_client`].
+    /// The requests are timed in milliseconds, at the points in time they were sent at.
+    /// 
+    /// # Returns
+    /// 
+    /// * [`Latencies`] - A snapshot of the durations recorded so far
+    /// 
+    /// # Example
+    /// ```rust
+    /// let latencies = framework.latencies();
+    /// const [p50, p95, p99] = latencies.summary();
+    /// const compliance = latencies.compliance(500);
+    /// ```
+    pub fn latencies(&self) -> Latencies {
+        self.latencies.borrow().clone()
+    }
+
+    /// Log the user out. All tokens and stored authentication data are dropped.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `post_logout_redirect_url` - The URL the authentication provider redirects to after logout
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(String)` - The URL the SPA should navigate to, to end the session at the authentication provider
+    /// * `Ok(undefined)` - If the authentication provider has no end session endpoint configured
+    /// * `Err(JsValue)` - A structured [`AuthError`], if the redirect url is invalid or the storage could not be cleared
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if let Some(url) = framework.logout(Some(String::from("https://my.site")))? {
+    ///     // navigate to url
+    /// }
+    /// ```
+    pub fn logout(&mut self, post_logout_redirect_url: Option<String>) -> Result<Option<String>, JsValue> {
+
+        self.stop_token_refresh();
+        let result = self.auth.borrow_mut().logout(self.session.as_ref(), post_logout_redirect_url);
+        match result {
+            Ok(url) => {
+                self.broadcast(SessionEvent::Logout);
+                Ok(url.map(|url| url.to_string()))
+            },
+            Err(err) => Err(err.into())
         }
+    }
 
-        self
+    /// Check whether the user holds a valid, not expired session.
+    /// 
+    /// # Returns
+    /// 
+    /// * `true` - If an access token is held which is not expired
+    /// * `false` - Otherwise
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if !framework.is_authenticated() {
+    ///     // redirect to the login
+    /// }
+    /// ```
+    pub fn is_authenticated(&self) -> bool {
+        self.auth.borrow().is_authenticated()
     }
-}
\ No newline at end of file
+
+    /// The remaining lifetime of the session in seconds.
+    /// 
+    /// # Returns
+    /// 
+    /// * `number` - The remaining seconds, `0` if the session is expired
+    /// * `undefined` - If the user is not authenticated or the expiry is unknown
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if let Some(seconds) = framework.expires_in_seconds() {
+    ///     // schedule a refresh
+    /// }
+    /// ```
+    pub fn expires_in_seconds(&self) -> Option<u32> {
+        self.auth.borrow().expires_in_seconds()
+    }
+
+    /// Renew the tokens automatically in the background, shortly before the access token expires.
+    /// A previously started renewal is stopped.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `lead_seconds` - The seconds before the expiry of the access token to renew it at
+    /// * `callback` - Invoked after each renewal with `undefined` on success,
+    ///   or the [`AuthError`] on failure. After a failure no further renewal is scheduled.
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework; // already authenticated
+    /// framework.start_token_refresh(60, (err) => {
+    ///     if (err) {
+    ///         // redirect to the login
+    ///     }
+    /// });
+    /// ```
+    pub fn start_token_refresh(&mut self, lead_seconds: u32, callback: Function) {
+        self.stop_token_refresh();
+        *self.refresher.borrow_mut() = Some(TokenRefresher::start(
+            self.auth.clone(),
+            self.session.clone(),
+            lead_seconds,
+            callback,
+            self.sync.clone()
+        ));
+    }
+
+    /// Stop renewing the tokens in the background, see [`Framework::start_token_refresh`].
+    pub fn stop_token_refresh(&mut self) {
+        if let Some(refresher) = self.refresher.borrow_mut().take() {
+            TokenRefresher::stop(&refresher);
+        }
+    }
+
+    /// Keep the session consistent with the other open tabs of the admin panel.
+    /// Logins, logouts and renewals of the tokens in one tab are applied in the others,
+    /// which requires the tabs to share the storage, e.g. the `localStorage`.
+    /// A logout in another tab also stops the renewal in the background, see [`Framework::start_token_refresh`].
+    /// A previously started synchronization is stopped.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `callback` - Invoked with `"login"`, `"logout"` or `"refresh"` after an event of another tab was applied
+    /// 
+    /// # Returns
+    /// 
+    /// * `Ok(())` - If the synchronization is started
+    /// * `Err(JsValue)` - If the browser can neither broadcast nor observe the storage
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// framework.sync_sessions((event) => {
+    ///     if (event === "logout") {
+    ///         // redirect to the login
+    ///     }
+    /// })?;
+    /// ```
+    pub fn sync_sessions(&mut self, callback: Function) -> Result<(), JsValue> {
+        self.stop_session_sync();
+        let auth = self.auth.clone();
+        let session = self.session.clone();
+        let refresher = self.refresher.clone();
+        let sync = SessionSync::start(&self.keys.key(Self::ID_SESSION_CHANNEL), move |event| {
+            let result = match event {
+                SessionEvent::Logout => {
+                    // Nothing is left to renew, the refresher would only report a missing refresh token
+                    if let Some(refresher) = refresher.borrow_mut().take() {
+                        TokenRefresher::stop(&refresher);
+                    }
+                    auth.borrow_mut().logout(session.as_ref(), None).map(|_| ())
+                },
+                SessionEvent::Login | SessionEvent::Refresh => auth.borrow_mut().load(session.as_ref())
+            };
+            if result.is_ok() {
+                let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(event.name()));
+            }
+        })?;
+        *self.sync.borrow_mut() = Some(sync);
+        Ok(())
+    }
+
+    /// Stop keeping the session consistent with the other tabs, see [`Framework::sync_sessions`].
+    pub fn stop_session_sync(&mut self) {
+        self.sync.borrow_mut().take();
+    }
+
+    /// The subject identifier of the authenticated user, unique at the authentication provider.
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The `sub` claim of the id token
+    /// * `undefined` - If the user is not authenticated
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// let subject: Option<String> = framework.get_subject();
+    /// ```
+    pub fn get_subject(&self) -> Option<String> {
+        let auth = self.auth.borrow();
+        auth.claims().map(|claims| claims.subject().to_string())
+    }
+
+    /// The username of the authenticated user.
+    /// Falls back to the full name, if the provider does not supply a username.
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The `preferred_username` or `name` claim of the id token
+    /// * `undefined` - If the user is not authenticated or neither claim is supplied
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if let Some(username) = framework.get_username() {
+    ///     // greet the user
+    /// }
+    /// ```
+    pub fn get_username(&self) -> Option<String> {
+        let auth = self.auth.borrow();
+        let claims = auth.claims()?;
+        claims.preferred_username()
+            .map(|username| username.to_string())
+            .or_else(|| claims.name()?.get(None).map(|name| name.to_string()))
+    }
+
+    /// The email address of the authenticated user.
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The `email` claim of the id token
+    /// * `undefined` - If the user is not authenticated or the claim is not supplied
+    pub fn get_email(&self) -> Option<String> {
+        let auth = self.auth.borrow();
+        auth.claims()?.email().map(|email| email.to_string())
+    }
+
+    /// The URL of the profile picture of the authenticated user.
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The `picture` claim of the id token
+    /// * `undefined` - If the user is not authenticated or the claim is not supplied
+    pub fn get_picture(&self) -> Option<String> {
+        let auth = self.auth.borrow();
+        auth.claims()?.picture()?.get(None).map(|picture| picture.to_string())
+    }
+
+    /// A claim of the authenticated user which is not defined by the OpenID Connect standard,
+    /// e.g. the realm roles or groups issued by Keycloak, serialized as JSON.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `name` - The name of the claim, nested claims separated by dots
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The value of the claim as JSON
+    /// * `undefined` - If the user is not authenticated or the claim is not supplied
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if let Some(roles) = framework.get_custom_claim(String::from("realm_access.roles")) {
+    ///     // JSON.parse(roles) in JS
+    /// }
+    /// ```
+    pub fn get_custom_claim(&self, name: String) -> Option<String> {
+        let auth = self.auth.borrow();
+        serde_json::to_string(auth.custom_claim(&name)?).ok()
+    }
+
+    /// Whether the given role is granted to the authenticated user,
+    /// as realm or client role or in the `roles` claim, see [`Roles`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `role` - The name of the role
+    /// 
+    /// # Returns
+    /// 
+    /// * `true` - If the role is granted
+    /// * `false` - If the role is not granted or the user is not authenticated
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if framework.has_role(String::from("admin")) {
+    ///     // show the admin routes
+    /// }
+    /// ```
+    pub fn has_role(&self, role: String) -> bool {
+        Roles::of(&self.auth.borrow()).contains(&role)
+    }
+
+    /// Whether any of the given roles is granted to the authenticated user, see [`Framework::has_role`].
+    /// 
+    /// # Arguments
+    /// 
+    /// * `roles` - The names of the roles
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if framework.has_any_role(vec![String::from("admin"), String::from("moderator")]) {
+    ///     // show the moderation routes
+    /// }
+    /// ```
+    pub fn has_any_role(&self, roles: Vec<String>) -> bool {
+        Roles::of(&self.auth.borrow()).contains_any(&roles)
+    }
+
+    /// The names of all roles granted to the authenticated user, see [`Framework::has_role`].
+    pub fn get_roles(&self) -> Vec<String> {
+        Roles::of(&self.auth.borrow()).names()
+    }
+
+    /// All claims of the id token of the authenticated user, serialized as JSON.
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The claims as JSON object
+    /// * `undefined` - If the user is not authenticated
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// if let Some(claims) = framework.get_claims_json() {
+    ///     // JSON.parse(claims) in JS
+    /// }
+    /// ```
+    pub fn get_claims_json(&self) -> Option<String> {
+        let auth = self.auth.borrow();
+        serde_json::to_string(auth.claims()?).ok()
+    }
+
+    /// Fetch the claims of the authenticated user from the userinfo endpoint of the provider,
+    /// merged with the claims of the id token. Requires a configured userinfo endpoint,
+    /// see [`ClientData::with_user_info_url`].
+    /// 
+    /// # Returns
+    /// 
+    /// * `Promise<String>` - Resolves to the merged claims as JSON object,
+    ///   rejects with a structured [`AuthError`] if something fails
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework; // already authenticated
+    /// const claims = JSON.parse(await framework.user_info());
+    /// ```
+    pub fn user_info(&self) -> Promise {
+        let auth = self.auth.clone();
+        let session = self.session.clone();
+
+        future_to_promise(async move {
+            let request = auth.borrow().user_info_request()?;
+            let user_info = request.await?;
+
+            let mut auth = auth.borrow_mut();
+            auth.apply_user_info(user_info);
+            auth.store(session.as_ref())?;
+            match auth.merged_claims() {
+                Some(claims) => Ok(JsValue::from_str(&claims.to_string())),
+                None => Err(AuthError::NotAuthenticated.into())
+            }
+        })
+    }
+
+    /// Run a suite of checks on the environment the framework is running in.
+    /// The result can be rendered with [`Diagnostics::report`] for support requests.
+    /// 
+    /// # Returns
+    /// 
+    /// * [`Diagnostics`] - The results of the checks
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// let diagnostics = framework.diagnostics();
+    /// if !diagnostics.web_crypto {
+    ///     // warn the user
+    /// }
+    /// ```
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::run(self.session.as_ref(), &self.keys)
+    }
+
+    /// Query the security relevant events observed on this client, e.g. CSRF mismatches, oldest first.
+    /// The events are persisted, so they are kept after a failed authentication or a switch of the provider.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `kind` - Only return events of this [`SecurityEventKind`], if given
+    /// 
+    /// # Returns
+    /// 
+    /// * `Vec<SecurityEvent>` - The recorded events
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// let events: Vec<SecurityEvent> = framework.security_events(None);
+    /// ```
+    pub fn security_events(&self, kind: Option<SecurityEventKind>) -> Vec<SecurityEvent> {
+        self.auth.borrow().security_events(self.session.as_ref(), kind)
+    }
+
+    /// Describe the environment the framework is configured for,
+    /// e.g. to show an environment banner or to detect a configuration drift.
+    /// 
+    /// # Returns
+    /// 
+    /// * [`Environment`] - The configured environment
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// let environment = framework.environment();
+    /// if let Some(warning) = environment.check() {
+    ///     // the page expects another environment, warn the user
+    /// }
+    /// ```
+    pub fn environment(&self) -> Environment {
+        self.auth.borrow().environment()
+    }
+
+    /// The version of the framework, including the git revision it was built from if known.
+    /// 
+    /// # Returns
+    /// 
+    /// * `String` - The version, e.g. `0.1.0+c18e940`
+    /// 
+    /// # Example
+    /// ```rust
+    /// let framework: Framework;
+    /// console.log(framework.version());
+    /// ```
+    pub fn version(&self) -> String {
+        version()
+    }
+}
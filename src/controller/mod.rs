@@ -1,10 +1,65 @@
-/// SPDX-License-Identifier: MIT
-/// SPDX-License-Identifier: APACHE
-/// 
-/// 2022, Patrick Schneider <patrick@itermori.de>
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+mod store;
+pub use store::{
+    EncryptedStore,
+    MemoryStore,
+    PersistentStore,
+    StorageKeys,
+    StoreError
+};
 
 mod auth_manager;
-pub use auth_manager::AuthManager;
+pub use auth_manager::{
+    AuthManager,
+    AuthProviderRegistry,
+    ClientData
+};
+
+mod authorization;
+pub use authorization::Roles;
+
+mod api;
+pub use api::ApiClient;
+
+mod backend;
+pub use backend::GraphQLClient;
+
+mod session_sync;
+pub use session_sync::{
+    SessionEvent,
+    SessionSync
+};
+
+mod refresh;
+pub use refresh::TokenRefresher;
 
 mod framework;
-pub use framework::Framework;
\ No newline at end of file
+pub use framework::Framework;
+
+mod diagnostics;
+pub use diagnostics::Diagnostics;
+
+mod capabilities;
+pub use capabilities::{
+    Capabilities,
+    Capability
+};
+
+mod security_log;
+pub use security_log::{
+    SecurityEvent,
+    SecurityEventKind
+};
+
+mod environment;
+pub use environment::Environment;
+
+mod network;
+pub use network::{
+    ConnectionTier,
+    NetworkConditions
+};
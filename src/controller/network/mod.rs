@@ -0,0 +1,155 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use js_sys::Reflect;
+
+/// The quality of the connection of the browser, coarse enough to adapt the behavior to
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConnectionTier {
+
+    /// A 2G connection or the user asked to save data
+    Slow,
+
+    /// A 3G connection
+    Moderate,
+
+    /// A 4G or faster connection, or the quality is unknown
+    Fast
+}
+
+/// The NetworkConditions describe the connection of the browser as reported by the
+/// [Network Information API](https://wicg.github.io/netinfo/),
+/// so the managers can adapt to slow networks, e.g. in the dead zones of the campus Wi-Fi.
+/// Browsers without the API are treated as fast.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkConditions {
+
+    /// The quality of the connection
+    pub tier: ConnectionTier
+}
+
+impl NetworkConditions {
+
+    /// Classify the connection
+    ///
+    /// # Arguments
+    ///
+    /// * `effective_type` - The `effectiveType` of `navigator.connection`, e.g. `3g`, if available
+    /// * `save_data` - Whether the user asked to reduce the data usage
+    pub fn from_connection(effective_type: Option<&str>, save_data: bool) -> Self {
+        let tier = match effective_type {
+            _ if save_data => ConnectionTier::Slow,
+            Some("slow-2g") | Some("2g") => ConnectionTier::Slow,
+            Some("3g") => ConnectionTier::Moderate,
+            _ => ConnectionTier::Fast
+        };
+        NetworkConditions {
+            tier
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl NetworkConditions {
+
+    /// Read the current conditions from `navigator.connection`
+    ///
+    /// # Example
+    /// ```rust
+    /// let network = NetworkConditions::current();
+    /// if network.prefetch() {
+    ///     // load the next page in advance
+    /// }
+    /// ```
+    pub fn current() -> NetworkConditions {
+        let connection = Reflect::get(&js_sys::global(), &JsValue::from_str("navigator"))
+            .and_then(|navigator| Reflect::get(&navigator, &JsValue::from_str("connection")))
+            .unwrap_or(JsValue::UNDEFINED);
+        if connection.is_undefined() || connection.is_null() {
+            return NetworkConditions::from_connection(None, false);
+        }
+        let effective_type = Reflect::get(&connection, &JsValue::from_str("effectiveType"))
+            .ok()
+            .and_then(|value| value.as_string());
+        let save_data = Reflect::get(&connection, &JsValue::from_str("saveData"))
+            .ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        NetworkConditions::from_connection(effective_type.as_deref(), save_data)
+    }
+
+    /// The number of entries to request per page, at most the requested one
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - The page size the page asks for
+    pub fn page_size(&self, requested: u32) -> u32 {
+        match self.tier {
+            ConnectionTier::Slow => requested.min(10),
+            ConnectionTier::Moderate => requested.min(25),
+            ConnectionTier::Fast => requested
+        }
+    }
+
+    /// The interval to poll the backend in, at least the requested one
+    ///
+    /// # Arguments
+    ///
+    /// * `requested` - The interval in milliseconds the page asks for
+    pub fn poll_interval(&self, requested: i32) -> i32 {
+        match self.tier {
+            ConnectionTier::Slow => requested.saturating_mul(4),
+            ConnectionTier::Moderate => requested.saturating_mul(2),
+            ConnectionTier::Fast => requested
+        }
+    }
+
+    /// Whether data which might be needed later should be loaded in advance
+    pub fn prefetch(&self) -> bool {
+        self.tier == ConnectionTier::Fast
+    }
+
+    /// The time in milliseconds to wait for a response before giving up
+    pub fn timeout(&self) -> u32 {
+        match self.tier {
+            ConnectionTier::Slow => 60_000,
+            ConnectionTier::Moderate => 30_000,
+            ConnectionTier::Fast => 15_000
+        }
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn classifies_connection() {
+        assert_eq!(NetworkConditions::from_connection(Some("2g"), false).tier, ConnectionTier::Slow);
+        assert_eq!(NetworkConditions::from_connection(Some("3g"), false).tier, ConnectionTier::Moderate);
+        assert_eq!(NetworkConditions::from_connection(Some("4g"), true).tier, ConnectionTier::Slow);
+        assert_eq!(NetworkConditions::from_connection(None, false).tier, ConnectionTier::Fast);
+    }
+
+    #[test]
+    fn adapts_to_slow_connection() {
+        let slow = NetworkConditions::from_connection(Some("slow-2g"), false);
+        assert_eq!(slow.page_size(50), 10);
+        assert_eq!(slow.page_size(5), 5);
+        assert_eq!(slow.poll_interval(2000), 8000);
+        assert!(!slow.prefetch());
+
+        let fast = NetworkConditions::from_connection(Some("4g"), false);
+        assert_eq!(fast.page_size(50), 50);
+        assert_eq!(fast.poll_interval(2000), 2000);
+        assert!(fast.prefetch());
+    }
+}
@@ -0,0 +1,290 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use js_sys::Function;
+use futures::future::{
+    FutureExt,
+    LocalBoxFuture,
+    Shared
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::AuthManager;
+use super::PersistentStore;
+use super::SecurityEventKind;
+use super::auth_manager::AuthError;
+use super::{
+    SessionEvent,
+    SessionSync
+};
+
+/// A renewal of the tokens in progress, awaited by all callers renewing the tokens of the same [`AuthManager`]
+pub(crate) type PendingRefresh = Shared<LocalBoxFuture<'static, Result<(), AuthError>>>;
+
+/// Renew the tokens held by the given [`AuthManager`] and persist them.
+/// A failed renewal is recorded as security relevant event, see [`SecurityEventKind`].
+/// If a renewal is already in progress, e.g. started by a concurrent request or the [`TokenRefresher`],
+/// its result is awaited instead, as a rotated refresh token can only be exchanged once.
+///
+/// # Arguments
+///
+/// * `auth` - The [`AuthManager`] to renew the tokens of
+/// * `session` - The [`PersistentStore`] to persist the renewed tokens and record a failure in
+///
+/// # Returns
+///
+/// * `Ok(())` - If the tokens are renewed
+/// * `Err(AuthError)` - If no refresh token is held or the renewal failed
+pub(crate) async fn refresh_session(
+    auth: &Rc<RefCell<AuthManager>>,
+    session: &Rc<dyn PersistentStore>
+) -> Result<(), AuthError> {
+    let pending = auth.borrow().pending_refresh();
+    if let Some(pending) = pending {
+        return pending.await;
+    }
+
+    let request = auth.borrow().refresh_request()?;
+    // Held by the manager while in progress, so only a weak reference to avoid a cycle
+    let manager = Rc::downgrade(auth);
+    let session = session.clone();
+    let pending = async move {
+        let response = request.await;
+        let auth = match manager.upgrade() {
+            Some(auth) => auth,
+            None => return response.map(|_| ())
+        };
+        auth.borrow_mut().set_pending_refresh(None);
+
+        let result = match response {
+            Ok(tokens) => {
+                let mut auth = auth.borrow_mut();
+                auth.apply_refresh(tokens, js_sys::Date::now())
+                    .and_then(|_| auth.store(session.as_ref()))
+            },
+            Err(err) => Err(err)
+        };
+
+        if let Err(err) = &result {
+            let kind = match err {
+                AuthError::SubjectChanged => SecurityEventKind::SubjectChanged,
+                _ => SecurityEventKind::RefreshFailed
+            };
+            auth.borrow().record_security_event(session.as_ref(), kind, &err.message(), js_sys::Date::now());
+        }
+        result
+    }.boxed_local().shared();
+
+    auth.borrow_mut().set_pending_refresh(Some(pending.clone()));
+    pending.await
+}
+
+/// The TokenRefresher renews the tokens of an [`AuthManager`] in the background,
+/// a configurable amount of seconds before the access token expires.
+/// After each renewal the callback is invoked with `undefined` on success
+/// or the [`AuthError`](super::auth_manager::AuthError) on failure.
+/// A failed renewal stops the refresher, the user then needs to authenticate again.
+/// Before renewing, the tokens are reloaded from the storage, so tabs sharing it renew only once.
+pub struct TokenRefresher {
+
+    /// The seconds before the expiry of the access token to renew it at
+    lead_seconds: u32,
+
+    /// The JS function invoked after each renewal
+    callback: Function,
+
+    /// Whether renewals are still scheduled
+    active: bool,
+
+    /// The handle of the pending timeout, if any
+    timeout: Option<i32>,
+
+    /// The closure invoked by the pending timeout, kept alive until it fires
+    closure: Option<Closure<dyn FnMut()>>,
+
+    /// Informs the other tabs about a renewal, if started
+    sync: Rc<RefCell<Option<SessionSync>>>
+}
+
+impl TokenRefresher {
+
+    /// Start renewing the tokens held by the given [`AuthManager`].
+    /// If the expiry of the access token is unknown, nothing is scheduled.
+    ///
+    /// # Arguments
+    ///
+    /// * `auth` - The [`AuthManager`] to renew the tokens of
+    /// * `session` - The [`PersistentStore`] to persist the renewed tokens in
+    /// * `lead_seconds` - The seconds before the expiry of the access token to renew it at
+    /// * `callback` - The JS function invoked after each renewal
+    /// * `sync` - The [`SessionSync`] to inform the other tabs about a renewal, if started
+    ///
+    /// # Example
+    /// ```rust
+    /// let refresher = TokenRefresher::start(auth, session, 60, callback, sync);
+    /// // later
+    /// TokenRefresher::stop(&refresher);
+    /// ```
+    pub fn start(
+        auth: Rc<RefCell<AuthManager>>,
+        session: Rc<dyn PersistentStore>,
+        lead_seconds: u32,
+        callback: Function,
+        sync: Rc<RefCell<Option<SessionSync>>>
+    ) -> Rc<RefCell<TokenRefresher>> {
+        let refresher = Rc::new(RefCell::new(TokenRefresher {
+            lead_seconds,
+            callback,
+            active: true,
+            timeout: None,
+            closure: None,
+            sync
+        }));
+        TokenRefresher::schedule(&refresher, auth, session);
+        refresher
+    }
+
+    /// Stop renewing the tokens. A renewal in progress is completed, but not reported.
+    pub fn stop(refresher: &Rc<RefCell<TokenRefresher>>) {
+        let mut refresher = refresher.borrow_mut();
+        refresher.active = false;
+        if let (Some(timeout), Some(window)) = (refresher.timeout.take(), web_sys::window()) {
+            window.clear_timeout_with_handle(timeout);
+        }
+        refresher.closure = None;
+    }
+
+    /// The delay until the next renewal in milliseconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `expires_in_seconds` - The remaining lifetime of the access token, if known
+    /// * `lead_seconds` - The seconds before the expiry to renew at
+    ///
+    /// # Returns
+    ///
+    /// * `Some(i32)` - The delay, `0` if the renewal is already due
+    /// * `None` - If the expiry is unknown
+    fn delay(expires_in_seconds: Option<u32>, lead_seconds: u32) -> Option<i32> {
+        let seconds = expires_in_seconds?.saturating_sub(lead_seconds);
+        Some(seconds.saturating_mul(1000).min(i32::MAX as u32) as i32)
+    }
+
+    /// Schedule the next renewal, based on the expiry of the tokens currently held
+    fn schedule(
+        refresher: &Rc<RefCell<TokenRefresher>>,
+        auth: Rc<RefCell<AuthManager>>,
+        session: Rc<dyn PersistentStore>
+    ) {
+        let delay = match TokenRefresher::delay(
+            auth.borrow().expires_in_seconds(),
+            refresher.borrow().lead_seconds
+        ) {
+            Some(delay) => delay,
+            None => return
+        };
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => return
+        };
+
+        let weak = Rc::downgrade(refresher);
+        let closure = Closure::once(move || {
+            if let Some(refresher) = weak.upgrade() {
+                spawn_local(TokenRefresher::renew(refresher, auth, session));
+            }
+        });
+        let timeout = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            closure.as_ref().unchecked_ref(),
+            delay
+        );
+
+        let mut state = refresher.borrow_mut();
+        state.timeout = timeout.ok();
+        state.closure = Some(closure);
+    }
+
+    /// Renew the tokens, report the result and schedule the next renewal on success
+    async fn renew(
+        refresher: Rc<RefCell<TokenRefresher>>,
+        auth: Rc<RefCell<AuthManager>>,
+        session: Rc<dyn PersistentStore>
+    ) {
+        // Another tab sharing the storage may have renewed the tokens meanwhile
+        let reloaded = auth.borrow_mut().load(session.as_ref()).is_ok();
+        let delay = TokenRefresher::delay(auth.borrow().expires_in_seconds(), refresher.borrow().lead_seconds);
+        if reloaded && matches!(delay, Some(delay) if delay > 0) {
+            TokenRefresher::schedule(&refresher, auth, session);
+            return;
+        }
+
+        let result = refresh_session(&auth, &session).await;
+
+        if result.is_ok() {
+            if let Some(sync) = refresher.borrow().sync.borrow().as_ref() {
+                sync.broadcast(SessionEvent::Refresh);
+            }
+        }
+        if !refresher.borrow().active {
+            return;
+        }
+        let callback = refresher.borrow().callback.clone();
+        match result {
+            Ok(()) => {
+                let _ = callback.call1(&JsValue::NULL, &JsValue::UNDEFINED);
+                TokenRefresher::schedule(&refresher, auth, session);
+            },
+            Err(err) => {
+                refresher.borrow_mut().active = false;
+                let _ = callback.call1(&JsValue::NULL, &err.into());
+            }
+        }
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::{
+        ClientData,
+        MemoryStore
+    };
+    use futures::executor::block_on;
+
+    #[test]
+    fn awaits_refresh_in_progress() {
+        let client_data = ClientData::from(
+            String::from("https://auth_provider.org/auth"),
+            String::from("https://auth_provider.org/token"),
+            String::from("client"),
+            String::from("https://my.site")
+        ).ok().unwrap();
+        let auth = Rc::new(RefCell::new(AuthManager::new(client_data)));
+        let session: Rc<dyn PersistentStore> = Rc::new(MemoryStore::new());
+
+        // without a refresh token, only the renewal in progress can succeed
+        let pending = async { Ok(()) }.boxed_local().shared();
+        auth.borrow_mut().set_pending_refresh(Some(pending));
+        assert_eq!(block_on(refresh_session(&auth, &session)), Ok(()));
+
+        auth.borrow_mut().set_pending_refresh(None);
+        assert_eq!(block_on(refresh_session(&auth, &session)), Err(AuthError::MissingRefreshToken));
+    }
+
+    #[test]
+    fn delay_before_expiry() {
+        assert_eq!(TokenRefresher::delay(Some(300), 60), Some(240_000));
+        assert_eq!(TokenRefresher::delay(Some(30), 60), Some(0));
+        assert_eq!(TokenRefresher::delay(None, 60), None);
+        assert_eq!(TokenRefresher::delay(Some(u32::MAX), 0), Some(i32::MAX));
+    }
+}
@@ -0,0 +1,199 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use std::collections::VecDeque;
+
+use super::{
+    PersistentStore,
+    StorageKeys
+};
+
+/// The kinds of security relevant events observed on the client
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SecurityEventKind {
+
+    /// The state returned by the authentication provider did not match the stored one
+    CsrfMismatch,
+
+    /// The tokens could not be renewed, e.g. as the refresh token was revoked
+    RefreshFailed,
+
+    /// A renewed id token was issued for another user than the authenticated one
+    SubjectChanged
+}
+
+/// The SecurityEvent represents a single security relevant event observed on the client.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SecurityEvent {
+
+    /// The kind of the event
+    pub kind: SecurityEventKind,
+
+    /// The time the event occurred in milliseconds since the unix epoch
+    pub timestamp: f64,
+
+    /// A description of the event
+    detail: String
+}
+
+#[wasm_bindgen]
+impl SecurityEvent {
+
+    /// A description of the event
+    pub fn detail(&self) -> String {
+        self.detail.clone()
+    }
+}
+
+/// The SecurityLog collects the most recent security relevant events.
+/// Older events are dropped once the capacity is reached.
+/// The log is persisted in a [`PersistentStore`], so it survives a failed authentication,
+/// a switch of the identity provider and a page reload.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct SecurityLog {
+    events: VecDeque<SecurityEvent>
+}
+
+impl SecurityLog {
+
+    /// The maximum amount of events kept
+    const CAPACITY: usize = 100;
+
+    const ID_EVENTS: &'static str = "events";
+
+    /// Load the log persisted in the given storage.
+    /// A missing or unreadable log is treated as empty, as the log must not prevent the authentication.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The [`PersistentStore`] the log is persisted in
+    /// * `keys` - The [`StorageKeys`] of the log
+    pub fn load(storage: &dyn PersistentStore, keys: &StorageKeys) -> Self {
+        storage.get(&keys.key(Self::ID_EVENTS))
+            .ok()
+            .flatten()
+            .and_then(|serialized| serde_json::from_str(&serialized).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record an event in the log persisted in the given storage.
+    /// This is best effort, the event is dropped if the storage cannot be accessed.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The [`PersistentStore`] the log is persisted in
+    /// * `keys` - The [`StorageKeys`] of the log
+    /// * `kind` - The [`SecurityEventKind`] of the event
+    /// * `detail` - A description of the event
+    /// * `now` - The time the event occurred at in milliseconds since the unix epoch
+    pub fn record_in(
+        storage: &dyn PersistentStore,
+        keys: &StorageKeys,
+        kind: SecurityEventKind,
+        detail: &str,
+        now: f64
+    ) {
+        let mut log = SecurityLog::load(storage, keys);
+        log.record_at(kind, detail, now);
+        if let Ok(serialized) = serde_json::to_string(&log) {
+            let _ = storage.set(&keys.key(Self::ID_EVENTS), &serialized);
+        }
+    }
+
+    /// Record an event which occurred at the given time.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The [`SecurityEventKind`] of the event
+    /// * `detail` - A description of the event
+    /// * `now` - The time the event occurred at in milliseconds since the unix epoch
+    pub fn record_at(&mut self, kind: SecurityEventKind, detail: &str, now: f64) {
+        self.push(SecurityEvent {
+            kind,
+            timestamp: now,
+            detail: String::from(detail)
+        });
+    }
+
+    fn push(&mut self, event: SecurityEvent) {
+        if self.events.len() == Self::CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Query the recorded events, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Only return events of this kind, if given
+    pub fn events(&self, kind: Option<SecurityEventKind>) -> Vec<SecurityEvent> {
+        self.events.iter()
+            .filter(|event| kind.is_none_or(|kind| event.kind == kind))
+            .cloned()
+            .collect()
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::MemoryStore;
+
+    fn event(detail: &str) -> SecurityEvent {
+        SecurityEvent {
+            kind: SecurityEventKind::CsrfMismatch,
+            timestamp: 0.0,
+            detail: String::from(detail)
+        }
+    }
+
+    #[test]
+    fn keeps_most_recent_events() {
+        let mut log = SecurityLog::default();
+        for index in 0..SecurityLog::CAPACITY + 1 {
+            log.push(event(&index.to_string()));
+        }
+        let events = log.events(None);
+        assert_eq!(events.len(), SecurityLog::CAPACITY);
+        assert_eq!(events[0].detail(), "1");
+    }
+
+    #[test]
+    fn filters_by_kind() {
+        let mut log = SecurityLog::default();
+        log.push(event("mismatch"));
+        assert_eq!(log.events(Some(SecurityEventKind::CsrfMismatch)).len(), 1);
+        assert!(log.events(Some(SecurityEventKind::RefreshFailed)).is_empty());
+    }
+
+    #[test]
+    fn persists_events() {
+        let store = MemoryStore::new();
+        let keys = StorageKeys::new("panel", "security");
+        assert!(SecurityLog::load(&store, &keys).events(None).is_empty());
+
+        SecurityLog::record_in(&store, &keys, SecurityEventKind::CsrfMismatch, "mismatch", 1000.0);
+        SecurityLog::record_in(&store, &keys, SecurityEventKind::RefreshFailed, "revoked", 2000.0);
+        let events = SecurityLog::load(&store, &keys).events(None);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].kind, SecurityEventKind::RefreshFailed);
+        assert_eq!(events[1].timestamp, 2000.0);
+
+        store.set(&keys.key(SecurityLog::ID_EVENTS), "{").unwrap();
+        assert!(SecurityLog::load(&store, &keys).events(None).is_empty());
+    }
+}
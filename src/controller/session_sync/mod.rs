@@ -0,0 +1,205 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    BroadcastChannel,
+    MessageEvent,
+    StorageEvent
+};
+
+use super::Diagnostics;
+
+/// The changes of the session which are shared with the other tabs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+
+    /// The user authenticated
+    Login,
+
+    /// The user logged out
+    Logout,
+
+    /// The tokens were renewed
+    Refresh
+}
+
+impl SessionEvent {
+
+    /// The name of the event, as passed to JS
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionEvent::Login => "login",
+            SessionEvent::Logout => "logout",
+            SessionEvent::Refresh => "refresh"
+        }
+    }
+
+    /// Encode the event into a message.
+    /// The time makes repeated events distinct, as a storage event is only fired if the value changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The time of the event in milliseconds since the unix epoch
+    fn encode(&self, now: f64) -> String {
+        format!("{}:{}", self.name(), now)
+    }
+
+    /// Decode a message of another tab, see [`SessionEvent::encode`]
+    ///
+    /// # Returns
+    ///
+    /// * `Some(SessionEvent)` - The event of the message
+    /// * `None` - If the message is not a known event
+    fn decode(message: &str) -> Option<SessionEvent> {
+        match message.split(':').next()? {
+            "login" => Some(SessionEvent::Login),
+            "logout" => Some(SessionEvent::Logout),
+            "refresh" => Some(SessionEvent::Refresh),
+            _ => None
+        }
+    }
+}
+
+/// The SessionSync shares the [`SessionEvent`]s with the other tabs of the same origin,
+/// so several open tabs of the admin panel stay consistent.
+/// It uses a `BroadcastChannel` and falls back to `storage` events of the `localStorage`
+/// on browsers without it.
+pub struct SessionSync {
+
+    /// The name of the channel and the storage key the events are sent on
+    name: String,
+
+    /// The channel the events are sent on, `None` if the `localStorage` is used instead
+    channel: Option<BroadcastChannel>,
+
+    /// The listener for the events of the other tabs, kept alive until stopped
+    listener: Option<Closure<dyn FnMut(JsValue)>>
+}
+
+impl SessionSync {
+
+    /// Start receiving the events of the other tabs.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the channel and the storage key the events are sent on,
+    ///   shared by the tabs to keep in sync, e.g. built by [`StorageKeys`](super::StorageKeys)
+    /// * `on_event` - Invoked with each event received from another tab
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SessionSync)` - The started synchronization, stopped when dropped
+    /// * `Err(JsValue)` - If neither a `BroadcastChannel` nor the `storage` events are available
+    ///
+    /// # Example
+    /// ```rust
+    /// let sync = SessionSync::start(&keys.key("session"), |event| {
+    ///     // reload the session
+    /// })?;
+    /// sync.broadcast(SessionEvent::Login);
+    /// ```
+    pub fn start<F>(name: &str, mut on_event: F) -> Result<SessionSync, JsValue>
+    where
+        F: FnMut(SessionEvent) + 'static
+    {
+        if Diagnostics::has_property(&js_sys::global(), &["BroadcastChannel"]) {
+            let channel = BroadcastChannel::new(name)?;
+            let listener = Closure::<dyn FnMut(JsValue)>::new(move |message: JsValue| {
+                let data = message.unchecked_into::<MessageEvent>().data();
+                if let Some(event) = data.as_string().as_deref().and_then(SessionEvent::decode) {
+                    on_event(event);
+                }
+            });
+            channel.set_onmessage(Some(listener.as_ref().unchecked_ref()));
+            return Ok(SessionSync {
+                name: String::from(name),
+                channel: Some(channel),
+                listener: Some(listener)
+            });
+        }
+
+        let window = web_sys::window()
+            .ok_or_else(|| JsValue::from_str("Neither a BroadcastChannel nor a window is available!"))?;
+        let key = String::from(name);
+        let listener = Closure::<dyn FnMut(JsValue)>::new(move |message: JsValue| {
+            let message = message.unchecked_into::<StorageEvent>();
+            if message.key().as_deref() != Some(key.as_str()) {
+                return;
+            }
+            if let Some(event) = message.new_value().as_deref().and_then(SessionEvent::decode) {
+                on_event(event);
+            }
+        });
+        window.add_event_listener_with_callback("storage", listener.as_ref().unchecked_ref())?;
+        Ok(SessionSync {
+            name: String::from(name),
+            channel: None,
+            listener: Some(listener)
+        })
+    }
+
+    /// Send the event to the other tabs. This is best effort, failures are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The change of the session in this tab
+    pub fn broadcast(&self, event: SessionEvent) {
+        let message = event.encode(js_sys::Date::now());
+        match &self.channel {
+            Some(channel) => {
+                let _ = channel.post_message(&JsValue::from_str(&message));
+            },
+            None => {
+                if let Some(Ok(Some(storage))) = web_sys::window().map(|window| window.local_storage()) {
+                    let _ = storage.set_item(&self.name, &message);
+                }
+            }
+        }
+    }
+
+    /// Stop receiving the events of the other tabs
+    pub fn stop(&mut self) {
+        let listener = match self.listener.take() {
+            Some(listener) => listener,
+            None => return
+        };
+        match self.channel.take() {
+            Some(channel) => {
+                channel.set_onmessage(None);
+                channel.close();
+            },
+            None => {
+                if let Some(window) = web_sys::window() {
+                    let _ = window.remove_event_listener_with_callback("storage", listener.as_ref().unchecked_ref());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SessionSync {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn encodes_events() {
+        let message = SessionEvent::Refresh.encode(1668384000000.0);
+        assert_eq!(message, "refresh:1668384000000");
+        assert_eq!(SessionEvent::decode(&message), Some(SessionEvent::Refresh));
+        assert_eq!(SessionEvent::decode("logout"), Some(SessionEvent::Logout));
+        assert_eq!(SessionEvent::decode("unknown:1"), None);
+    }
+}
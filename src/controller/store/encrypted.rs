@@ -0,0 +1,160 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use aes_gcm::{
+    Aes256Gcm,
+    KeyInit,
+    Nonce
+};
+use aes_gcm::aead::{
+    Aead,
+    Payload
+};
+use sha2::{
+    Digest,
+    Sha256
+};
+
+use super::{
+    PersistentStore,
+    StoreError
+};
+
+/// The EncryptedStore encrypts the values of another [`PersistentStore`] with AES-256-GCM,
+/// so tokens and PKCE verifiers are not readable in plaintext, e.g. in the devtools or by scraping the storage.
+/// The key is derived from a secret given by the page, which has to be kept outside of the storage,
+/// e.g. delivered by the backend per session. Anyone knowing the secret can still decrypt the values.
+/// Each value is bound to its key, so values cannot be moved between keys unnoticed.
+/// The keys themselves are stored in plaintext.
+pub struct EncryptedStore {
+
+    /// The store holding the encrypted values
+    inner: Box<dyn PersistentStore>,
+
+    /// The cipher keyed by the secret
+    cipher: Aes256Gcm
+}
+
+impl EncryptedStore {
+
+    /// The length of the nonce prepended to each encrypted value
+    const NONCE_LENGTH: usize = 12;
+
+    /// Separates the derived key from other uses of the same secret
+    const KEY_CONTEXT: &'static [u8] = b"kifapwa.encrypted-store";
+
+    /// Create a store encrypting the values of the given one
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The [`PersistentStore`] to keep the encrypted values in
+    /// * `secret` - The secret to derive the key from, which should be random
+    ///
+    /// # Example
+    /// ```rust
+    /// let storage: Storage; // provided elsewhere
+    /// let store = EncryptedStore::new(Box::new(storage), &secret);
+    /// let framework = Framework::with_store(client_data, Box::new(store));
+    /// ```
+    pub fn new(inner: Box<dyn PersistentStore>, secret: &str) -> Self {
+        let key = Sha256::new()
+            .chain_update(Self::KEY_CONTEXT)
+            .chain_update(secret.as_bytes())
+            .finalize();
+        EncryptedStore {
+            inner,
+            cipher: Aes256Gcm::new(&key)
+        }
+    }
+
+    /// Encrypt the value stored under the given key with the given nonce
+    ///
+    /// # Returns
+    ///
+    /// The nonce and the encrypted value, encoded in base64
+    fn encrypt(&self, key: &str, value: &str, nonce: [u8; Self::NONCE_LENGTH]) -> Result<String, StoreError> {
+        let payload = Payload {
+            msg: value.as_bytes(),
+            aad: key.as_bytes()
+        };
+        let encrypted = self.cipher.encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| StoreError { cause: format!("The value of {} could not be encrypted", key) })?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(encrypted);
+        Ok(base64::encode(sealed))
+    }
+
+    /// Decrypt the value stored under the given key, see [`EncryptedStore::encrypt`]
+    fn decrypt(&self, key: &str, sealed: &str) -> Result<String, StoreError> {
+        let corrupted = || StoreError { cause: format!("The value of {} could not be decrypted", key) };
+        let sealed = base64::decode(sealed).map_err(|_| corrupted())?;
+        if sealed.len() < Self::NONCE_LENGTH {
+            return Err(corrupted());
+        }
+        let (nonce, encrypted) = sealed.split_at(Self::NONCE_LENGTH);
+        let payload = Payload {
+            msg: encrypted,
+            aad: key.as_bytes()
+        };
+        let value = self.cipher.decrypt(Nonce::from_slice(nonce), payload).map_err(|_| corrupted())?;
+        String::from_utf8(value).map_err(|_| corrupted())
+    }
+}
+
+impl PersistentStore for EncryptedStore {
+
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        self.inner.get(key)?
+            .map(|sealed| self.decrypt(key, &sealed))
+            .transpose()
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), StoreError> {
+        let mut nonce = [0; Self::NONCE_LENGTH];
+        getrandom::getrandom(&mut nonce).map_err(|err| StoreError { cause: err.to_string() })?;
+        self.inner.set(key, &self.encrypt(key, value, nonce)?)
+    }
+
+    fn remove(&self, key: &str) -> Result<(), StoreError> {
+        self.inner.remove(key)
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        self.inner.clear()
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::MemoryStore;
+
+    #[test]
+    fn values_are_encrypted() {
+        let store = EncryptedStore::new(Box::new(MemoryStore::new()), "secret");
+        let sealed = store.encrypt("tokens", "access-token", [7; 12]).unwrap();
+        assert!(!sealed.contains("access-token"));
+        assert_eq!(store.decrypt("tokens", &sealed), Ok(String::from("access-token")));
+
+        // bound to the key and the secret
+        assert!(store.decrypt("claims", &sealed).is_err());
+        let other = EncryptedStore::new(Box::new(MemoryStore::new()), "other");
+        assert!(other.decrypt("tokens", &sealed).is_err());
+        assert!(store.decrypt("tokens", "AAAA").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let store = EncryptedStore::new(Box::new(MemoryStore::new()), "secret");
+        assert_eq!(store.get("tokens"), Ok(None));
+        store.set("tokens", "access-token").unwrap();
+        assert_eq!(store.get("tokens"), Ok(Some(String::from("access-token"))));
+        store.remove("tokens").unwrap();
+        assert_eq!(store.get("tokens"), Ok(None));
+    }
+}
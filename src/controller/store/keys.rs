@@ -0,0 +1,59 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+/// The StorageKeys build the keys state is persisted under in a [`PersistentStore`](super::PersistentStore).
+/// Every key is prefixed by a namespace and a scope, e.g. `kifapwa.my-client@https://auth.my.site/.tokens`,
+/// so it neither collides with other data of the page nor with the state of other clients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageKeys {
+
+    /// The prefix of all keys, ending with a `.`
+    prefix: String
+}
+
+impl StorageKeys {
+
+    /// The namespace used unless configured otherwise
+    pub const DEFAULT_NAMESPACE: &'static str = "kifapwa";
+
+    /// Create the keys for the given namespace and scope
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace of the application, e.g. [`StorageKeys::DEFAULT_NAMESPACE`]
+    /// * `scope` - The scope within the namespace, e.g. the client
+    ///
+    /// # Example
+    /// ```rust
+    /// let keys = StorageKeys::new(StorageKeys::DEFAULT_NAMESPACE, "my-client");
+    /// assert_eq!(keys.key("tokens"), "kifapwa.my-client.tokens");
+    /// ```
+    pub fn new(namespace: &str, scope: &str) -> Self {
+        StorageKeys {
+            prefix: format!("{}.{}.", namespace, scope)
+        }
+    }
+
+    /// The key the value with the given id is stored under
+    pub fn key(&self, id: &str) -> String {
+        format!("{}{}", self.prefix, id)
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn keys_are_prefixed() {
+        let keys = StorageKeys::new("panel", "client");
+        assert_eq!(keys.key("verifier"), "panel.client.verifier");
+        assert_ne!(keys.key("csrf"), StorageKeys::new("panel", "other").key("csrf"));
+        assert_ne!(keys.key("csrf"), StorageKeys::new("other", "client").key("csrf"));
+    }
+}
@@ -0,0 +1,152 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use web_sys::Storage;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+mod keys;
+pub use keys::StorageKeys;
+
+mod encrypted;
+pub use encrypted::EncryptedStore;
+
+/// The StoreError represents an error which occurs while accessing a [`PersistentStore`]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StoreError {
+    cause: String
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error, cannot access storage: {}", self.cause)
+    }
+}
+
+impl fmt::Debug for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{ file: {}, line: {}, message: {} }}", file!(), line!(), self.cause)
+    }
+}
+
+impl From<JsValue> for StoreError {
+    fn from(value: JsValue) -> StoreError {
+        StoreError {
+            cause: value.as_string().unwrap_or_else(|| format!("{:?}", value))
+        }
+    }
+}
+
+/// The PersistentStore is a key-value store used to persist state, e.g. across redirects.
+/// Implemented for [`Storage`](web_sys::Storage), so both `localStorage` and `sessionStorage` can be used,
+/// and by [`MemoryStore`] to run outside of the browser.
+pub trait PersistentStore {
+
+    /// Get the value stored under the given key, if any
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError>;
+
+    /// Store the value under the given key, replacing any previous value
+    fn set(&self, key: &str, value: &str) -> Result<(), StoreError>;
+
+    /// Remove the value stored under the given key, if any
+    fn remove(&self, key: &str) -> Result<(), StoreError>;
+
+    /// Remove all values
+    fn clear(&self) -> Result<(), StoreError>;
+}
+
+impl PersistentStore for Storage {
+
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        Ok(Storage::get(self, key)?)
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), StoreError> {
+        Ok(Storage::set(self, key, value)?)
+    }
+
+    fn remove(&self, key: &str) -> Result<(), StoreError> {
+        Ok(self.remove_item(key)?)
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        Ok(Storage::clear(self)?)
+    }
+}
+
+/// The MemoryStore keeps all values in memory only.
+/// Nothing survives a page reload, which makes it suitable for tests and
+/// environments where web storage is unavailable.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: RefCell<HashMap<String, String>>
+}
+
+impl MemoryStore {
+
+    /// Create a new, empty MemoryStore
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// The amount of stored values
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Whether no values are stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
+
+impl PersistentStore for MemoryStore {
+
+    fn get(&self, key: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.entries.borrow().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), StoreError> {
+        self.entries.borrow_mut().insert(String::from(key), String::from(value));
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), StoreError> {
+        self.entries.borrow_mut().remove(key);
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        self.entries.borrow_mut().clear();
+        Ok(())
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn memory_store() {
+        let store = MemoryStore::new();
+        assert_eq!(store.get("key"), Ok(None));
+
+        store.set("key", "value").unwrap();
+        store.set("other", "value").unwrap();
+        assert_eq!(store.get("key"), Ok(Some(String::from("value"))));
+        assert_eq!(store.len(), 2);
+
+        store.remove("key").unwrap();
+        assert_eq!(store.get("key"), Ok(None));
+
+        store.clear().unwrap();
+        assert!(store.is_empty());
+    }
+}
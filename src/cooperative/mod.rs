@@ -0,0 +1,214 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use js_sys::{
+    Function,
+    Object,
+    Promise,
+    Reflect
+};
+
+/// The priority a long computation continues with after yielding,
+/// see the [Prioritized Task Scheduling API](https://wicg.github.io/scheduling-apis/)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskPriority {
+
+    /// Continue before rendering, e.g. for the response to an input
+    UserBlocking,
+
+    /// Continue after rendering, e.g. to fill a table the user waits for
+    UserVisible,
+
+    /// Continue when the browser is idle, e.g. to precompute statistics
+    Background
+}
+
+impl TaskPriority {
+
+    /// The name of the priority, as passed to `scheduler.postTask`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskPriority::UserBlocking => "user-blocking",
+            TaskPriority::UserVisible => "user-visible",
+            TaskPriority::Background => "background"
+        }
+    }
+}
+
+/// Yield to the event loop, so the browser can render and handle input in between the chunks of a long computation.
+/// Uses `scheduler.postTask` with the given priority if available and falls back to `setTimeout(0)` otherwise.
+///
+/// # Arguments
+///
+/// * `priority` - The [`TaskPriority`] to continue with, only honored by `scheduler.postTask`
+///
+/// # Returns
+///
+/// * `Promise<undefined>` - Resolves once the computation may continue
+///
+/// # Example
+/// ```rust
+/// for chunk in entries.chunks(1000) {
+///     process(chunk);
+///     JsFuture::from(yield_now(TaskPriority::UserVisible)).await?;
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn yield_now(priority: TaskPriority) -> Promise {
+    let global = js_sys::global();
+    if let Some(promise) = post_task(&global, priority) {
+        return promise;
+    }
+    Promise::new(&mut |resolve, _| {
+        let set_timeout = Reflect::get(&global, &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|set_timeout| set_timeout.dyn_into::<Function>().ok());
+        let scheduled = set_timeout
+            .map(|set_timeout| set_timeout.call2(&global, &resolve, &JsValue::from(0)).is_ok())
+            .unwrap_or(false);
+        if !scheduled {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    })
+}
+
+/// Post an empty task with the given priority, if the browser supports `scheduler.postTask`
+fn post_task(global: &Object, priority: TaskPriority) -> Option<Promise> {
+    let scheduler = Reflect::get(global, &JsValue::from_str("scheduler")).ok()?;
+    if !scheduler.is_object() {
+        return None;
+    }
+    let post_task: Function = Reflect::get(&scheduler, &JsValue::from_str("postTask")).ok()?.dyn_into().ok()?;
+    let options = Object::new();
+    Reflect::set(&options, &JsValue::from_str("priority"), &JsValue::from_str(priority.name())).ok()?;
+    let task = Function::new_no_args("");
+    post_task.call2(&scheduler, &task, &options).ok()?.dyn_into().ok()
+}
+
+/// The TimeBudget tells a long computation when to yield, see [`yield_now`].
+/// The computation runs for at most a slice of time before it yields, so it interleaves with rendering
+/// without yielding after every item.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeBudget {
+
+    /// The time to compute before yielding in milliseconds
+    slice: f64,
+
+    /// The start of the current slice in milliseconds since the unix epoch
+    started: f64,
+
+    /// The priority to continue with after yielding
+    priority: TaskPriority
+}
+
+impl TimeBudget {
+
+    /// The slice used for a budget which is not finite or positive, a frame at 60 Hz
+    const DEFAULT_SLICE: f64 = 16.0;
+
+    /// Create a budget whose first slice starts at the given time
+    ///
+    /// # Arguments
+    ///
+    /// * `slice` - The time to compute before yielding in milliseconds
+    /// * `priority` - The [`TaskPriority`] to continue with after yielding
+    /// * `now` - The start of the first slice in milliseconds since the unix epoch
+    pub fn started_at(slice: f64, priority: TaskPriority, now: f64) -> Self {
+        TimeBudget {
+            slice: if slice.is_finite() && slice > 0.0 { slice } else { Self::DEFAULT_SLICE },
+            started: now,
+            priority
+        }
+    }
+
+    /// Whether the current slice is used up at the given time
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The point in time in milliseconds since the unix epoch
+    pub fn is_exhausted_at(&self, now: f64) -> bool {
+        now - self.started >= self.slice
+    }
+
+    /// Yield to the event loop if the current slice is used up, then start the next one
+    pub async fn checkpoint(&mut self) {
+        if self.is_exhausted_at(js_sys::Date::now()) {
+            let _ = JsFuture::from(yield_now(self.priority)).await;
+            self.started = js_sys::Date::now();
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl TimeBudget {
+
+    /// Create a budget whose first slice starts now
+    ///
+    /// # Arguments
+    ///
+    /// * `slice` - The time to compute before yielding in milliseconds, e.g. `8`
+    /// * `priority` - The [`TaskPriority`] to continue with after yielding
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut budget = TimeBudget::new(8.0, TaskPriority::UserVisible);
+    /// for entry in entries {
+    ///     process(entry);
+    ///     budget.checkpoint().await;
+    /// }
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(slice: f64, priority: TaskPriority) -> TimeBudget {
+        TimeBudget::started_at(slice, priority, js_sys::Date::now())
+    }
+
+    /// Whether the current slice is used up and the computation should yield
+    pub fn should_yield(&self) -> bool {
+        self.is_exhausted_at(js_sys::Date::now())
+    }
+
+    /// Start the next slice, after the computation yielded
+    pub fn restart(&mut self) {
+        self.started = js_sys::Date::now();
+    }
+
+    /// The [`TaskPriority`] to continue with after yielding
+    pub fn priority(&self) -> TaskPriority {
+        self.priority
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn exhausts_slices() {
+        let budget = TimeBudget::started_at(8.0, TaskPriority::UserVisible, 1000.0);
+        assert!(!budget.is_exhausted_at(1000.0));
+        assert!(!budget.is_exhausted_at(1007.9));
+        assert!(budget.is_exhausted_at(1008.0));
+
+        let fallback = TimeBudget::started_at(f64::NAN, TaskPriority::Background, 1000.0);
+        assert!(!fallback.is_exhausted_at(1015.0));
+        assert!(fallback.is_exhausted_at(1016.0));
+        assert!(!TimeBudget::started_at(-1.0, TaskPriority::Background, 0.0).is_exhausted_at(1.0));
+    }
+
+    #[test]
+    fn names_priorities() {
+        assert_eq!(TaskPriority::UserBlocking.name(), "user-blocking");
+        assert_eq!(TaskPriority::UserVisible.name(), "user-visible");
+        assert_eq!(TaskPriority::Background.name(), "background");
+    }
+}
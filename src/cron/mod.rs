@@ -0,0 +1,380 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use chrono::{
+    Datelike,
+    Duration,
+    NaiveDate
+};
+use std::collections::BTreeSet;
+
+/// The CronSchedule is a parsed cron expression of the scheduled jobs of the backend,
+/// to validate it before it is submitted and preview when the job will run.
+/// The expression consists of the five fields minute, hour, day of month, month and day of week.
+/// Every field is `*`, a value, a range `a-b` or a list of them, each optionally with a step `/n`.
+/// Months and weekdays may be given by their names, e.g. `JAN` or `MON`.
+/// If both the day of month and the day of week are restricted, a day matches if either matches.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+
+    /// The minutes of the hour, `0` to `59`
+    minutes: BTreeSet<u32>,
+
+    /// The hours of the day, `0` to `23`
+    hours: BTreeSet<u32>,
+
+    /// The days of the month, `1` to `31`
+    days_of_month: BTreeSet<u32>,
+
+    /// The months, `1` to `12`
+    months: BTreeSet<u32>,
+
+    /// The days of the week, `0` for sunday to `6` for saturday
+    days_of_week: BTreeSet<u32>,
+
+    /// The offset of the local time the schedule is given in to UTC in minutes
+    utc_offset: i32
+}
+
+impl CronSchedule {
+
+    /// The number of days to look ahead for the next run, covering two leap days
+    const LOOKAHEAD_DAYS: i64 = 8 * 366;
+
+    const SECONDS_PER_DAY: i64 = 86400;
+
+    const MONTHS: [&'static str; 12] = [
+        "January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December"
+    ];
+
+    const WEEKDAYS: [&'static str; 7] = [
+        "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"
+    ];
+
+    /// The most days each month can have
+    const DAYS_IN_MONTH: [u32; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+    /// Parse the given cron expression, in UTC
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - The cron expression, e.g. `30 2 * * MON-FRI`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CronSchedule)` - If the expression is valid
+    /// * `Err(String)` - A description of the invalid part otherwise
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("A cron expression consists of 5 fields, but {} were given!", fields.len()));
+        }
+        let schedule = CronSchedule {
+            minutes: parse_field(fields[0], "minute", 0, 59, &[])?,
+            hours: parse_field(fields[1], "hour", 0, 23, &[])?,
+            days_of_month: parse_field(fields[2], "day of month", 1, 31, &[])?,
+            months: parse_field(fields[3], "month", 1, 12, &Self::MONTHS)?,
+            days_of_week: parse_field(fields[4], "day of week", 0, 7, &Self::WEEKDAYS)?
+                .into_iter()
+                .map(|day| day % 7)
+                .collect(),
+            utc_offset: 0
+        };
+        if !schedule.can_run() {
+            return Err(String::from("The schedule never runs, as none of the months has the given days!"));
+        }
+        Ok(schedule)
+    }
+
+    /// The next runs after the given point in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The point in time in seconds since the unix epoch
+    /// * `count` - The number of runs to return at most
+    ///
+    /// # Returns
+    ///
+    /// The runs in seconds since the unix epoch, fewer than `count` if there are no more within the next eight years
+    pub fn next_runs_at(&self, now: u64, count: u32) -> Vec<u64> {
+        let mut runs = Vec::new();
+        if count == 0 {
+            return runs;
+        }
+        let offset = i64::from(self.utc_offset) * 60;
+        let start = (now as i64 + offset).div_euclid(60) * 60 + 60;
+        let first_day = start.div_euclid(Self::SECONDS_PER_DAY);
+        let epoch = match NaiveDate::from_ymd_opt(1970, 1, 1) {
+            Some(epoch) => epoch,
+            None => return runs
+        };
+
+        for day_number in first_day..=first_day + Self::LOOKAHEAD_DAYS {
+            let day = match epoch.checked_add_signed(Duration::days(day_number)) {
+                Some(day) => day,
+                None => return runs
+            };
+            if !self.matches_day(day) {
+                continue;
+            }
+            for hour in &self.hours {
+                for minute in &self.minutes {
+                    let time = day_number * Self::SECONDS_PER_DAY + i64::from(*hour * 3600 + *minute * 60);
+                    if time < start {
+                        continue;
+                    }
+                    if let Ok(run) = u64::try_from(time - offset) {
+                        runs.push(run);
+                    }
+                    if runs.len() >= count as usize {
+                        return runs;
+                    }
+                }
+            }
+        }
+        runs
+    }
+
+    /// Whether the job runs on the given day
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        if !self.months.contains(&day.month()) {
+            return false;
+        }
+        let day_of_month = self.days_of_month.contains(&day.day());
+        let day_of_week = self.days_of_week.contains(&day.weekday().num_days_from_sunday());
+        match (self.days_of_month.len() == 31, self.days_of_week.len() == 7) {
+            (true, _) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week
+        }
+    }
+
+    /// Whether any of the months has one of the days of month, if only those restrict the days
+    fn can_run(&self) -> bool {
+        if self.days_of_week.len() != 7 {
+            return true;
+        }
+        let first_day = self.days_of_month.iter().next().copied().unwrap_or(u32::MAX);
+        self.months.iter().any(|month| first_day <= Self::DAYS_IN_MONTH[*month as usize - 1])
+    }
+
+    /// Describe at which times of the day the job runs
+    fn describe_time(&self) -> String {
+        let all_minutes = self.minutes.len() == 60;
+        let all_hours = self.hours.len() == 24;
+        let minutes: Vec<String> = self.minutes.iter().map(u32::to_string).collect();
+        let hours: Vec<String> = self.hours.iter().map(u32::to_string).collect();
+
+        if all_minutes && all_hours {
+            String::from("Every minute")
+        } else if let (true, Some(step)) = (all_hours, minute_step(&self.minutes)) {
+            format!("Every {} minutes", step)
+        } else if self.minutes.len() * self.hours.len() <= 4 {
+            let times: Vec<String> = self.hours.iter()
+                .flat_map(|hour| self.minutes.iter().map(move |minute| format!("{:02}:{:02}", hour, minute)))
+                .collect();
+            format!("At {}", join(&times))
+        } else if all_hours {
+            format!("At minute {} past every hour", join(&minutes))
+        } else if all_minutes {
+            format!("Every minute past hour {}", join(&hours))
+        } else {
+            format!("At minute {} past hour {}", join(&minutes), join(&hours))
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CronSchedule {
+
+    /// Parse the given cron expression, in UTC
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - The cron expression, e.g. `30 2 * * MON-FRI`
+    ///
+    /// # Example
+    /// ```rust
+    /// let schedule = CronSchedule::new(String::from("0 3 * * SUN"))?
+    ///     .with_utc_offset(60);
+    /// let description = schedule.describe(); // "At 03:00 on Sunday"
+    /// let runs = schedule.next_runs(5);
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(expression: String) -> Result<CronSchedule, JsValue> {
+        CronSchedule::parse(&expression).map_err(|cause| JsValue::from_str(&cause))
+    }
+
+    /// Set the offset of the local time the schedule is given in to UTC.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `utc_offset` - The offset in minutes, e.g. `60` for CET
+    pub fn with_utc_offset(mut self, utc_offset: i32) -> CronSchedule {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// The next runs from now in seconds since the unix epoch
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of runs to return at most
+    pub fn next_runs(&self, count: u32) -> Vec<u64> {
+        self.next_runs_at(now(), count)
+    }
+
+    /// A human readable description of the schedule, e.g. `At 02:30 on Monday and Friday`
+    pub fn describe(&self) -> String {
+        let mut description = self.describe_time();
+        let mut days = Vec::new();
+        if self.days_of_month.len() != 31 {
+            let values: Vec<String> = self.days_of_month.iter().map(u32::to_string).collect();
+            days.push(format!("on day {} of the month", join(&values)));
+        }
+        if self.days_of_week.len() != 7 {
+            let values: Vec<String> = self.days_of_week.iter()
+                .map(|day| String::from(Self::WEEKDAYS[*day as usize]))
+                .collect();
+            days.push(format!("on {}", join(&values)));
+        }
+        if !days.is_empty() {
+            description.push(' ');
+            description.push_str(&days.join(" or "));
+        }
+        if self.months.len() != 12 {
+            let values: Vec<String> = self.months.iter()
+                .map(|month| String::from(Self::MONTHS[*month as usize - 1]))
+                .collect();
+            description.push_str(&format!(" in {}", join(&values)));
+        }
+        description
+    }
+}
+
+/// Parse a single field of a cron expression
+///
+/// # Arguments
+///
+/// * `field` - The text of the field
+/// * `name` - The name of the field, for the error message
+/// * `min` - The smallest allowed value
+/// * `max` - The largest allowed value
+/// * `names` - The names of the values starting at `min`, of which the first three letters may be given instead
+fn parse_field(field: &str, name: &str, min: u32, max: u32, names: &[&str]) -> Result<BTreeSet<u32>, String> {
+    let invalid = || format!("The {} {} is not valid!", name, field);
+    let value = |text: &str| -> Result<u32, String> {
+        let value = match names.iter().position(|name| name[..3].eq_ignore_ascii_case(text)) {
+            Some(position) => position as u32 + min,
+            None => text.parse::<u32>().map_err(|_| invalid())?
+        };
+        if value < min || value > max {
+            return Err(format!("The {} {} is not between {} and {}!", name, value, min, max));
+        }
+        Ok(value)
+    };
+
+    let mut values = BTreeSet::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?),
+            None => (part, 1)
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?)
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        values.extend((first..=last).step_by(step as usize));
+    }
+    Ok(values)
+}
+
+/// The step between the minutes, if they are every n-th minute of the hour
+fn minute_step(minutes: &BTreeSet<u32>) -> Option<u32> {
+    let mut iter = minutes.iter().skip_while(|minute| **minute == 0);
+    let step = *iter.next()?;
+    (minutes.contains(&0) && minutes.iter().copied().eq((0..60).step_by(step as usize))).then_some(step)
+}
+
+/// Join the values to a list, e.g. `1, 2 and 3`
+fn join(values: &[String]) -> String {
+    match values.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        Some((last, _)) => last.clone(),
+        None => String::new()
+    }
+}
+
+/// The current time in seconds since the unix epoch
+fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Monday, 2022-11-14 00:00:00 UTC
+    const MONDAY: u64 = 1668384000;
+    const HOUR: u64 = 3600;
+    const DAY: u64 = 24 * HOUR;
+
+    #[test]
+    fn previews_next_runs() {
+        let schedule = CronSchedule::parse("30 2 * * MON-FRI").unwrap();
+        assert_eq!(
+            schedule.next_runs_at(MONDAY + 3 * HOUR, 2),
+            vec![MONDAY + DAY + 2 * HOUR + 1800, MONDAY + 2 * DAY + 2 * HOUR + 1800]
+        );
+        // friday evening to monday morning
+        assert_eq!(schedule.next_runs_at(MONDAY + 4 * DAY + 3 * HOUR, 1), vec![MONDAY + 7 * DAY + 2 * HOUR + 1800]);
+        assert_eq!(schedule.next_runs_at(MONDAY + 2 * HOUR + 1800, 1), vec![MONDAY + DAY + 2 * HOUR + 1800]);
+    }
+
+    #[test]
+    fn respects_utc_offset_and_steps() {
+        let schedule = CronSchedule::parse("*/20 9 1,15 * *").unwrap().with_utc_offset(60);
+        // 2022-11-15 09:00 in CET is 08:00 UTC
+        assert_eq!(
+            schedule.next_runs_at(MONDAY, 4),
+            vec![MONDAY + DAY + 8 * HOUR, MONDAY + DAY + 8 * HOUR + 1200, MONDAY + DAY + 8 * HOUR + 2400, MONDAY + 17 * DAY + 8 * HOUR]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * FOO *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 30 2 *").is_err());
+        assert!(CronSchedule::parse("0 0 30 2 SUN").is_ok());
+        assert!(CronSchedule::parse("0 0 * * 7").is_ok());
+    }
+
+    #[test]
+    fn describes_schedules() {
+        assert_eq!(CronSchedule::parse("* * * * *").unwrap().describe(), "Every minute");
+        assert_eq!(CronSchedule::parse("*/15 * * * *").unwrap().describe(), "Every 15 minutes");
+        assert_eq!(CronSchedule::parse("30 2 * * 1,5").unwrap().describe(), "At 02:30 on Monday and Friday");
+        assert_eq!(
+            CronSchedule::parse("0 0 1 jan,jul 7").unwrap().describe(),
+            "At 00:00 on day 1 of the month or on Sunday in January and July"
+        );
+        assert_eq!(CronSchedule::parse("5 * * * *").unwrap().describe(), "At minute 5 past every hour");
+    }
+}
@@ -0,0 +1,183 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+
+/// The Locale determines the separators and unit notation used when formatting values for display.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    German,
+    English
+}
+
+impl Locale {
+
+    /// The separator placed between groups of three digits
+    fn thousands_separator(&self) -> char {
+        match self {
+            Locale::German => '.',
+            Locale::English => ','
+        }
+    }
+
+    /// The separator placed between the integer and the fractional part
+    fn decimal_separator(&self) -> char {
+        match self {
+            Locale::German => ',',
+            Locale::English => '.'
+        }
+    }
+
+    /// The string placed between a value and its unit
+    fn unit_separator(&self) -> &'static str {
+        match self {
+            Locale::German => "\u{a0}",
+            Locale::English => " "
+        }
+    }
+
+    /// The string placed between a value and `%`
+    fn percent_separator(&self) -> &'static str {
+        match self {
+            // German typography uses a (non-breaking) space in front of `%` as well
+            Locale::German => self.unit_separator(),
+            Locale::English => ""
+        }
+    }
+}
+
+/// The units used to format byte sizes, each one a thousand times the previous
+const BYTE_UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+
+/// Format the given number with thousands separators and a fixed amount of decimals.
+///
+/// # Arguments
+///
+/// * `value` - The number to format
+/// * `decimals` - The amount of decimals to show
+/// * `locale` - The [`Locale`] to format for
+///
+/// # Example
+/// ```rust
+/// assert_eq!(format_number(1234567.891, 2, Locale::German), "1.234.567,89");
+/// assert_eq!(format_number(1234567.891, 2, Locale::English), "1,234,567.89");
+/// ```
+#[wasm_bindgen]
+pub fn format_number(value: f64, decimals: usize, locale: Locale) -> String {
+
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = match formatted.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (formatted.as_str(), None)
+    };
+
+    let mut result = String::new();
+    if value < 0.0 && formatted.chars().any(|digit| digit != '0' && digit != '.') {
+        result.push('-');
+    }
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            result.push(locale.thousands_separator());
+        }
+        result.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        result.push(locale.decimal_separator());
+        result.push_str(fraction);
+    }
+    result
+}
+
+/// Format the given ratio as percentage.
+///
+/// # Arguments
+///
+/// * `ratio` - The ratio to format, `1.0` represents `100 %`
+/// * `decimals` - The amount of decimals to show
+/// * `locale` - The [`Locale`] to format for
+///
+/// # Example
+/// ```rust
+/// assert_eq!(format_percent(0.125, 1, Locale::English), "12.5%");
+/// ```
+#[wasm_bindgen]
+pub fn format_percent(ratio: f64, decimals: usize, locale: Locale) -> String {
+    format!("{}{}%", format_number(ratio * 100.0, decimals, locale), locale.percent_separator())
+}
+
+/// Format the given amount of bytes using the largest fitting unit.
+///
+/// # Arguments
+///
+/// * `bytes` - The amount of bytes
+/// * `locale` - The [`Locale`] to format for
+///
+/// # Example
+/// ```rust
+/// assert_eq!(format_bytes(1_500_000.0, Locale::German), "1,5\u{a0}MB");
+/// ```
+#[wasm_bindgen]
+pub fn format_bytes(bytes: f64, locale: Locale) -> String {
+
+    // bytes are shown without decimals, all larger units with one
+    let round = |value: f64, unit: usize| if unit == 0 { value.round() } else { (value * 10.0).round() / 10.0 };
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while value.abs() >= 1000.0 && unit < BYTE_UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    // e.g. 999.96 kB is rounded up to 1000 kB, which is shown as 1 MB instead
+    let mut rounded = round(value, unit);
+    if rounded.abs() >= 1000.0 && unit < BYTE_UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+        rounded = round(value, unit);
+    }
+
+    let decimals = if unit == 0 || rounded.fract() == 0.0 { 0 } else { 1 };
+    format!("{}{}{}", format_number(rounded, decimals, locale), locale.unit_separator(), BYTE_UNITS[unit])
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn number_with_separators() {
+        assert_eq!(format_number(1234567.891, 2, Locale::German), "1.234.567,89");
+        assert_eq!(format_number(1234567.891, 2, Locale::English), "1,234,567.89");
+        assert_eq!(format_number(-1000.0, 0, Locale::German), "-1.000");
+        assert_eq!(format_number(999.0, 0, Locale::English), "999");
+        assert_eq!(format_number(-0.001, 1, Locale::English), "0.0");
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(format_percent(0.125, 1, Locale::German), "12,5\u{a0}%");
+        assert_eq!(format_percent(0.125, 1, Locale::English), "12.5%");
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(format_bytes(512.0, Locale::English), "512 B");
+        assert_eq!(format_bytes(1_500_000.0, Locale::German), "1,5\u{a0}MB");
+        assert_eq!(format_bytes(2_000.0, Locale::English), "2 kB");
+        assert_eq!(format_bytes(999_999.0, Locale::English), "1 MB");
+        assert_eq!(format_bytes(999.6, Locale::German), "1\u{a0}kB");
+        assert_eq!(format_bytes(1_999_990.0, Locale::English), "2 MB");
+        assert_eq!(format_bytes(999_940.0, Locale::English), "999.9 kB");
+    }
+}
@@ -5,10 +5,90 @@
 
 extern crate console_error_panic_hook;
 mod utils;
-use utils::set_panic_hook;
 
 mod controller;
 pub use controller::AuthManager;
+pub use controller::ClientData;
+pub use controller::AuthProviderRegistry;
+pub use controller::Framework;
+pub use controller::{
+    Capabilities,
+    Capability
+};
+pub use controller::{
+    ConnectionTier,
+    NetworkConditions
+};
+pub use controller::{
+    EncryptedStore,
+    MemoryStore,
+    PersistentStore,
+    StoreError
+};
+
+mod format;
+pub use format::{
+    Locale,
+    format_number,
+    format_percent,
+    format_bytes
+};
+
+mod sanitize;
+pub use sanitize::{
+    SanitizePolicy,
+    sanitize_html
+};
+
+mod qr;
+pub use qr::qr_code_svg;
+
+mod totp;
+pub use totp::TotpSecret;
+
+mod strength;
+pub use strength::{
+    Strength,
+    estimate_strength
+};
+
+mod confirmation;
+pub use confirmation::{
+    ConfirmationSigner,
+    ConfirmationToken
+};
+
+mod announcement;
+pub use announcement::render_announcement;
+
+mod calendar;
+pub use calendar::Calendar;
+
+mod working_hours;
+pub use working_hours::WorkingHours;
+
+mod cron;
+pub use cron::CronSchedule;
+
+mod scheduler;
+pub use scheduler::{
+    ScheduledJob,
+    Scheduler
+};
+
+mod cooperative;
+pub use cooperative::{
+    TaskPriority,
+    TimeBudget,
+    yield_now
+};
+
+mod stats;
+pub use stats::{
+    Heatmap,
+    Latencies,
+    Series
+};
 
 use wasm_bindgen::prelude::*;
 
@@ -26,6 +106,7 @@ extern {
     fn log(s: &str);
 }
 
+#[allow(unused_macros)]
 macro_rules! log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
 }
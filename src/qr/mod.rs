@@ -0,0 +1,64 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use qrcode::QrCode;
+use qrcode::render::svg;
+use qrcode::types::QrError;
+
+/// Render the given data, e.g. an onboarding link, into a QR code.
+///
+/// # Arguments
+///
+/// * `data` - The data to encode
+/// * `size` - The minimal width and height of the image in pixels
+///
+/// # Returns
+///
+/// * `Ok(String)` - The QR code as SVG document
+/// * `Err(JsValue)` - If the data is too long to be encoded
+///
+/// # Example
+/// ```rust
+/// let svg: String = qr_code_svg("https://my.site/onboarding?token=abc", 256)?;
+/// ```
+#[wasm_bindgen]
+pub fn qr_code_svg(data: &str, size: u32) -> Result<String, JsValue> {
+    render_svg(data, size).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Render the given data into a QR code in SVG format.
+/// See [`qr_code_svg`].
+pub fn render_svg(data: &str, size: u32) -> Result<String, QrError> {
+    let code = QrCode::new(data.as_bytes())?;
+    Ok(
+        code.render::<svg::Color>()
+            .min_dimensions(size, size)
+            .dark_color(svg::Color("#000000"))
+            .light_color(svg::Color("#ffffff"))
+            .build()
+    )
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn renders_svg() {
+        let svg = render_svg("https://my.site", 128).unwrap();
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn too_long_data_fails() {
+        let data = "a".repeat(8000);
+        assert!(render_svg(&data, 128).is_err());
+    }
+}
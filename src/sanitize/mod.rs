@@ -0,0 +1,108 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use ammonia::Builder;
+use std::collections::HashSet;
+
+/// The SanitizePolicy determines which HTML is kept when sanitizing.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SanitizePolicy {
+
+    /// No markup at all, only the text content is kept
+    Text,
+
+    /// Inline formatting and links, e.g. for comments
+    Inline,
+
+    /// Block level formatting as rendered from markdown, e.g. for announcements
+    Rich
+}
+
+impl SanitizePolicy {
+
+    /// The tags allowed by this policy
+    fn tags(&self) -> HashSet<&'static str> {
+        let inline = ["br", "em", "strong", "del", "code", "a"];
+        let rich = [
+            "p", "hr", "pre", "blockquote", "ul", "ol", "li", "h1", "h2", "h3", "h4", "h5", "h6",
+            "table", "thead", "tbody", "tr", "th", "td"
+        ];
+
+        match self {
+            SanitizePolicy::Text => HashSet::new(),
+            SanitizePolicy::Inline => HashSet::from(inline),
+            SanitizePolicy::Rich => inline.into_iter().chain(rich).collect()
+        }
+    }
+}
+
+/// The URL schemes links may point to
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Sanitize the given HTML by keeping only the elements allowed by the policy.
+/// Every string received from the backend has to pass this function before it is rendered as HTML.
+///
+/// # Arguments
+///
+/// * `input` - The untrusted HTML
+/// * `policy` - The [`SanitizePolicy`] determining the allowed elements
+///
+/// # Returns
+///
+/// * `String` - The sanitized HTML
+///
+/// # Example
+/// ```rust
+/// let html = sanitize_html("<em>hi</em><script>alert(1)</script>", SanitizePolicy::Inline);
+/// assert_eq!(html, "<em>hi</em>");
+/// ```
+#[wasm_bindgen]
+pub fn sanitize_html(input: &str, policy: SanitizePolicy) -> String {
+    Builder::default()
+        .tags(policy.tags())
+        .url_schemes(HashSet::from(ALLOWED_SCHEMES))
+        .link_rel(Some("noopener noreferrer"))
+        .clean(input)
+        .to_string()
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn text_removes_all_markup() {
+        assert_eq!(sanitize_html("<p>Hello <b>world</b></p>", SanitizePolicy::Text), "Hello world");
+    }
+
+    #[test]
+    fn inline_keeps_formatting() {
+        assert_eq!(
+            sanitize_html("<p><em>hi</em><script>alert(1)</script></p>", SanitizePolicy::Inline),
+            "<em>hi</em>"
+        );
+    }
+
+    #[test]
+    fn rich_keeps_blocks() {
+        assert_eq!(
+            sanitize_html("<ul><li onclick=\"alert(1)\">item</li></ul>", SanitizePolicy::Rich),
+            "<ul><li>item</li></ul>"
+        );
+    }
+
+    #[test]
+    fn links_are_restricted() {
+        let html = sanitize_html("<a href=\"javascript:alert(1)\">x</a>", SanitizePolicy::Inline);
+        assert!(!html.contains("javascript"));
+        let html = sanitize_html("<a href=\"https://kit.edu\">x</a>", SanitizePolicy::Inline);
+        assert_eq!(html, "<a href=\"https://kit.edu\" rel=\"noopener noreferrer\">x</a>");
+    }
+}
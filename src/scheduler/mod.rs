@@ -0,0 +1,461 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use js_sys::Function;
+use serde::{
+    Deserialize,
+    Serialize
+};
+use web_sys::Storage;
+use std::cell::RefCell;
+use std::collections::{
+    BTreeMap,
+    HashMap
+};
+use std::rc::Rc;
+
+use crate::controller::{
+    PersistentStore,
+    StorageKeys
+};
+use crate::cron::CronSchedule;
+use crate::working_hours::WorkingHours;
+
+/// The state of a job persisted across page loads
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobState {
+
+    /// The last run in seconds since the unix epoch, if the job ran before
+    last_run: Option<u64>,
+
+    /// Whether the job is paused
+    paused: bool
+}
+
+/// A job registered at the [`Jobs`]
+struct Job {
+
+    /// When the job runs
+    schedule: CronSchedule,
+
+    /// The working hours the runs are deferred to, if any
+    working_hours: Option<WorkingHours>,
+
+    /// The registration in seconds since the unix epoch, the first run follows it if the job never ran
+    registered_at: u64,
+
+    state: JobState
+}
+
+impl Job {
+
+    /// The next run in seconds since the unix epoch, `None` if paused or the schedule has no more runs
+    fn next_run(&self) -> Option<u64> {
+        if self.state.paused {
+            return None;
+        }
+        let anchor = self.state.last_run.unwrap_or(self.registered_at);
+        let run = *self.schedule.next_runs_at(anchor, 1).first()?;
+        match &self.working_hours {
+            Some(working_hours) => working_hours.next_slot_at(run),
+            None => Some(run)
+        }
+    }
+}
+
+/// The Jobs keep track of the registered jobs and when they are due.
+/// The state of every job is persisted in a [`PersistentStore`], so a run missed
+/// while the admin panel was closed is caught up once on the next page load.
+struct Jobs {
+    store: Box<dyn PersistentStore>,
+    keys: StorageKeys,
+    jobs: BTreeMap<String, Job>
+}
+
+impl Jobs {
+
+    fn new(store: Box<dyn PersistentStore>, keys: StorageKeys) -> Self {
+        Jobs {
+            store,
+            keys,
+            jobs: BTreeMap::new()
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        self.keys.key(&format!("job.{}", name))
+    }
+
+    /// Load the persisted state of a job, a missing or unreadable state is treated as a job which never ran
+    fn load(&self, name: &str) -> JobState {
+        self.store.get(&self.key(name))
+            .ok()
+            .flatten()
+            .and_then(|serialized| serde_json::from_str(&serialized).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persist the state of a job.
+    /// This is best effort, a job which cannot be persisted runs again after a page load.
+    fn persist(&self, name: &str) {
+        if let Some(job) = self.jobs.get(name) {
+            if let Ok(serialized) = serde_json::to_string(&job.state) {
+                let _ = self.store.set(&self.key(name), &serialized);
+            }
+        }
+    }
+
+    /// Register a job, replacing a job of the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the job, its state is persisted under
+    /// * `schedule` - When the job runs
+    /// * `working_hours` - The working hours the runs are deferred to, if any
+    /// * `now` - The registration in seconds since the unix epoch
+    fn register_at(&mut self, name: &str, schedule: CronSchedule, working_hours: Option<WorkingHours>, now: u64) {
+        let state = self.load(name);
+        self.jobs.insert(String::from(name), Job {
+            schedule,
+            working_hours,
+            registered_at: now,
+            state
+        });
+    }
+
+    fn unregister(&mut self, name: &str) -> bool {
+        self.jobs.remove(name).is_some()
+    }
+
+    fn set_paused(&mut self, name: &str, paused: bool) -> bool {
+        match self.jobs.get_mut(name) {
+            Some(job) => job.state.paused = paused,
+            None => return false
+        }
+        self.persist(name);
+        true
+    }
+
+    /// Take the jobs which are due at the given time, their last run is set to it
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The point in time in seconds since the unix epoch
+    ///
+    /// # Returns
+    ///
+    /// The names of the due jobs
+    fn take_due_at(&mut self, now: u64) -> Vec<String> {
+        let due: Vec<String> = self.jobs.iter()
+            .filter(|(_, job)| job.next_run().is_some_and(|run| run <= now))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &due {
+            if let Some(job) = self.jobs.get_mut(name) {
+                job.state.last_run = Some(now);
+            }
+            self.persist(name);
+        }
+        due
+    }
+
+    fn report(&self) -> Vec<ScheduledJob> {
+        self.jobs.iter()
+            .map(|(name, job)| ScheduledJob {
+                name: name.clone(),
+                last_run: job.state.last_run,
+                next_run: job.next_run(),
+                paused: job.state.paused
+            })
+            .collect()
+    }
+}
+
+/// The ScheduledJob reports the state of a job registered at the [`Scheduler`]
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduledJob {
+
+    /// The name of the job
+    name: String,
+
+    /// The last run in seconds since the unix epoch, if the job ran before
+    pub last_run: Option<u64>,
+
+    /// The next run in seconds since the unix epoch, `None` if paused
+    pub next_run: Option<u64>,
+
+    /// Whether the job is paused
+    pub paused: bool
+}
+
+#[wasm_bindgen]
+impl ScheduledJob {
+
+    /// The name of the job
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// The Scheduler runs recurring client-side jobs, e.g. refreshing the stats every five minutes
+/// or purging caches nightly, given by a [`CronSchedule`] in the time of the browser.
+/// Runs may be deferred to [`WorkingHours`], e.g. to publish decisions when someone can react to them.
+/// Whether a job ran and is paused is persisted, so it survives a page load.
+#[wasm_bindgen]
+pub struct Scheduler {
+    jobs: Rc<RefCell<Jobs>>,
+
+    /// The callbacks of the jobs, kept apart to invoke them without borrowing the jobs
+    callbacks: Rc<RefCell<HashMap<String, Function>>>,
+
+    /// The handle of the interval the jobs are checked in, until stopped
+    handle: Option<i32>,
+
+    /// The closure invoked by the interval, kept alive until stopped
+    closure: Option<Closure<dyn FnMut()>>
+}
+
+impl Scheduler {
+
+    /// The interval the due jobs are checked in, in milliseconds
+    const TICK_INTERVAL: i32 = 30_000;
+
+    const SCOPE: &'static str = "scheduler";
+
+    /// Create the scheduler with the state of the jobs persisted in the given store
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The [`PersistentStore`] the state of the jobs is persisted in
+    /// * `namespace` - The namespace of the keys, see [`StorageKeys`]
+    pub fn with_store(store: Box<dyn PersistentStore>, namespace: &str) -> Self {
+        Scheduler {
+            jobs: Rc::new(RefCell::new(Jobs::new(store, StorageKeys::new(namespace, Self::SCOPE)))),
+            callbacks: Rc::new(RefCell::new(HashMap::new())),
+            handle: None,
+            closure: None
+        }
+    }
+
+    /// Run the jobs due at the given time.
+    /// The callbacks are invoked after the jobs are released, so they may pause or register jobs.
+    fn tick_at(jobs: &RefCell<Jobs>, callbacks: &RefCell<HashMap<String, Function>>, now: u64) {
+        let due = jobs.borrow_mut().take_due_at(now);
+        for name in due {
+            let callback = callbacks.borrow().get(&name).cloned();
+            if let Some(callback) = callback {
+                let _ = callback.call0(&JsValue::NULL);
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Scheduler {
+
+    /// Create the scheduler with the state of the jobs persisted in the given storage
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - A [`Storage`](Storage), `localStorage` to keep the state across sessions
+    /// * `namespace` - The namespace of the keys, e.g. the one of the [`ClientData`](crate::ClientData)
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut scheduler = Scheduler::new(storage, String::from("kifapwa"));
+    /// scheduler.register(String::from("stats"), &CronSchedule::new(String::from("*/5 * * * *"))?, refresh);
+    /// scheduler.start()?;
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(storage: Storage, namespace: String) -> Scheduler {
+        Scheduler::with_store(Box::new(storage), &namespace)
+    }
+
+    /// Register a job, replacing a job of the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the job, its state is persisted under
+    /// * `schedule` - When the job runs
+    /// * `callback` - Invoked on every run
+    pub fn register(&mut self, name: String, schedule: &CronSchedule, callback: Function) {
+        self.jobs.borrow_mut().register_at(&name, schedule.clone(), None, now());
+        self.callbacks.borrow_mut().insert(name, callback);
+    }
+
+    /// Register a job whose runs are deferred to the given working hours, replacing a job of the same name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the job, its state is persisted under
+    /// * `schedule` - When the job runs
+    /// * `working_hours` - The [`WorkingHours`] the runs are deferred to
+    /// * `callback` - Invoked on every run
+    pub fn register_within(
+        &mut self,
+        name: String,
+        schedule: &CronSchedule,
+        working_hours: &WorkingHours,
+        callback: Function
+    ) {
+        self.jobs.borrow_mut().register_at(&name, schedule.clone(), Some(working_hours.clone()), now());
+        self.callbacks.borrow_mut().insert(name, callback);
+    }
+
+    /// Remove a job, its persisted state is kept for a later registration
+    ///
+    /// # Returns
+    ///
+    /// Whether the job was registered
+    pub fn unregister(&mut self, name: String) -> bool {
+        self.callbacks.borrow_mut().remove(&name);
+        self.jobs.borrow_mut().unregister(&name)
+    }
+
+    /// Pause a job until resumed, also across page loads
+    ///
+    /// # Returns
+    ///
+    /// Whether the job is registered
+    pub fn pause(&mut self, name: String) -> bool {
+        self.jobs.borrow_mut().set_paused(&name, true)
+    }
+
+    /// Resume a paused job. A run missed while paused is caught up once.
+    ///
+    /// # Returns
+    ///
+    /// Whether the job is registered
+    pub fn resume(&mut self, name: String) -> bool {
+        self.jobs.borrow_mut().set_paused(&name, false)
+    }
+
+    /// The state of the registered jobs, ordered by their name
+    pub fn report(&self) -> Vec<ScheduledJob> {
+        self.jobs.borrow().report()
+    }
+
+    /// Start checking for due jobs, the jobs due by now run immediately
+    pub fn start(&mut self) -> Result<(), JsValue> {
+        if self.handle.is_some() {
+            return Ok(());
+        }
+        let window = web_sys::window()
+            .ok_or_else(|| JsValue::from_str("No window is available to schedule the jobs"))?;
+        let jobs = self.jobs.clone();
+        let callbacks = self.callbacks.clone();
+        let tick = move || Scheduler::tick_at(&jobs, &callbacks, now());
+        tick();
+
+        let closure = Closure::<dyn FnMut()>::new(tick);
+        let handle = window.set_interval_with_callback_and_timeout_and_arguments_0(
+            closure.as_ref().unchecked_ref(),
+            Self::TICK_INTERVAL
+        )?;
+        self.handle = Some(handle);
+        self.closure = Some(closure);
+        Ok(())
+    }
+
+    /// Stop checking for due jobs
+    pub fn stop(&mut self) {
+        if let (Some(handle), Some(window)) = (self.handle.take(), web_sys::window()) {
+            window.clear_interval_with_handle(handle);
+        }
+        self.closure = None;
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::controller::MemoryStore;
+
+    /// Monday, 2024-01-01 00:00 UTC
+    const MONDAY: u64 = 1_704_067_200;
+
+    fn schedule(expression: &str) -> CronSchedule {
+        CronSchedule::parse(expression).unwrap()
+    }
+
+    #[test]
+    fn runs_due_jobs() {
+        let mut jobs = Jobs::new(Box::new(MemoryStore::new()), StorageKeys::new("panel", "scheduler"));
+        jobs.register_at("stats", schedule("*/5 * * * *"), None, MONDAY);
+        jobs.register_at("purge", schedule("0 3 * * *"), None, MONDAY);
+
+        assert!(jobs.take_due_at(MONDAY + 60).is_empty());
+        assert_eq!(jobs.take_due_at(MONDAY + 300), vec![String::from("stats")]);
+        assert!(jobs.take_due_at(MONDAY + 360).is_empty());
+        assert_eq!(jobs.take_due_at(MONDAY + 3 * 3600), vec![String::from("purge"), String::from("stats")]);
+
+        let report = jobs.report();
+        assert_eq!(report[1].name(), "stats");
+        assert_eq!(report[1].last_run, Some(MONDAY + 3 * 3600));
+        assert_eq!(report[1].next_run, Some(MONDAY + 3 * 3600 + 300));
+    }
+
+    #[test]
+    fn pauses_jobs() {
+        let mut jobs = Jobs::new(Box::new(MemoryStore::new()), StorageKeys::new("panel", "scheduler"));
+        jobs.register_at("stats", schedule("*/5 * * * *"), None, MONDAY);
+        assert!(jobs.set_paused("stats", true));
+        assert!(!jobs.set_paused("unknown", true));
+        assert!(jobs.take_due_at(MONDAY + 600).is_empty());
+        assert_eq!(jobs.report()[0].next_run, None);
+
+        assert!(jobs.set_paused("stats", false));
+        assert_eq!(jobs.take_due_at(MONDAY + 600), vec![String::from("stats")]);
+    }
+
+    #[test]
+    fn defers_to_working_hours() {
+        let mut jobs = Jobs::new(Box::new(MemoryStore::new()), StorageKeys::new("panel", "scheduler"));
+        jobs.register_at("publish", schedule("0 6 * * *"), Some(WorkingHours::new(9 * 60, 17 * 60)), MONDAY);
+        assert!(jobs.take_due_at(MONDAY + 6 * 3600).is_empty());
+        assert_eq!(jobs.report()[0].next_run, Some(MONDAY + 9 * 3600));
+        assert_eq!(jobs.take_due_at(MONDAY + 9 * 3600), vec![String::from("publish")]);
+    }
+
+    #[test]
+    fn persists_across_reloads() {
+        let keys = StorageKeys::new("panel", "scheduler");
+        let mut jobs = Jobs::new(Box::new(MemoryStore::new()), keys.clone());
+        jobs.register_at("purge", schedule("0 3 * * *"), None, MONDAY);
+        assert_eq!(jobs.take_due_at(MONDAY + 3 * 3600), vec![String::from("purge")]);
+        jobs.set_paused("purge", true);
+
+        let mut reloaded = Jobs::new(jobs.store, keys.clone());
+        reloaded.register_at("purge", schedule("0 3 * * *"), None, MONDAY + 2 * 86400);
+        assert_eq!(reloaded.report()[0].last_run, Some(MONDAY + 3 * 3600));
+        assert!(reloaded.report()[0].paused);
+
+        reloaded.set_paused("purge", false);
+        assert_eq!(reloaded.take_due_at(MONDAY + 2 * 86400), vec![String::from("purge")]);
+        assert!(reloaded.take_due_at(MONDAY + 2 * 86400 + 60).is_empty());
+
+        reloaded.store.set(&keys.key("job.purge"), "{").unwrap();
+        let mut corrupted = Jobs::new(reloaded.store, keys);
+        corrupted.register_at("purge", schedule("0 3 * * *"), None, MONDAY);
+        assert_eq!(corrupted.report()[0].last_run, None);
+    }
+}
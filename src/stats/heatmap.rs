@@ -0,0 +1,168 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::Promise;
+
+use crate::cooperative::{
+    TaskPriority,
+    TimeBudget
+};
+
+/// The Heatmap counts events, e.g. audit log entries, per hour of the week,
+/// so the activity widget receives 168 counts instead of the raw events.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Heatmap {
+
+    /// The counts per weekday and hour, monday 0:00 first
+    counts: Vec<u32>,
+
+    /// The offset of the local time to UTC in minutes
+    utc_offset: i32
+}
+
+impl Heatmap {
+
+    const HOURS: usize = 24;
+    const WEEKDAYS: usize = 7;
+
+    /// The number of events counted in between checking the [`TimeBudget`]
+    const CHUNK: usize = 1024;
+
+    /// The time to count before yielding in milliseconds
+    const SLICE: f64 = 8.0;
+
+    /// The weekday of the unix epoch, a thursday, counted from monday
+    const EPOCH_WEEKDAY: i64 = 3;
+
+    /// The bucket of the given point in time, in seconds since the unix epoch
+    fn bucket(&self, time: f64) -> Option<usize> {
+        if !time.is_finite() {
+            return None;
+        }
+        let local = time.floor() as i64 + i64::from(self.utc_offset) * 60;
+        let hours = local.div_euclid(3600);
+        let hour = hours.rem_euclid(Self::HOURS as i64) as usize;
+        let weekday = (hours.div_euclid(Self::HOURS as i64) + Self::EPOCH_WEEKDAY).rem_euclid(Self::WEEKDAYS as i64) as usize;
+        Some(weekday * Self::HOURS + hour)
+    }
+}
+
+#[wasm_bindgen]
+impl Heatmap {
+
+    /// Create an empty heatmap
+    ///
+    /// # Arguments
+    ///
+    /// * `utc_offset` - The offset of the local time to show the activity in to UTC in minutes, e.g. `60` for CET
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut heatmap = Heatmap::new(60);
+    /// heatmap.add_all(event_times);
+    /// let mondays = &heatmap.counts()[0..24];
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(utc_offset: i32) -> Heatmap {
+        Heatmap {
+            counts: vec![0; Self::WEEKDAYS * Self::HOURS],
+            utc_offset
+        }
+    }
+
+    /// Count an event at the given point in time
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The point in time in seconds since the unix epoch. Values which are not finite are ignored
+    pub fn add(&mut self, time: f64) {
+        if let Some(bucket) = self.bucket(time) {
+            self.counts[bucket] = self.counts[bucket].saturating_add(1);
+        }
+    }
+
+    /// Count events at the given points in time, see [`Heatmap::add`]
+    pub fn add_all(&mut self, times: Vec<f64>) {
+        for time in times {
+            self.add(time);
+        }
+    }
+
+    /// Count events at the given points in time like [`Heatmap::add_all`],
+    /// but yield to the browser in between, so counting a large audit log does not block the rendering.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - The points in time in seconds since the unix epoch
+    /// * `priority` - The [`TaskPriority`] to continue counting with after yielding
+    ///
+    /// # Returns
+    ///
+    /// * `Promise<Heatmap>` - Resolves to the heatmap with the events counted
+    pub fn add_all_cooperatively(mut self, times: Vec<f64>, priority: TaskPriority) -> Promise {
+        future_to_promise(async move {
+            let mut budget = TimeBudget::new(Self::SLICE, priority);
+            for chunk in times.chunks(Self::CHUNK) {
+                for time in chunk {
+                    self.add(*time);
+                }
+                budget.checkpoint().await;
+            }
+            Ok(JsValue::from(self))
+        })
+    }
+
+    /// The counts per weekday and hour as 7 rows of 24 hours, monday first
+    pub fn counts(&self) -> Vec<u32> {
+        self.counts.clone()
+    }
+
+    /// The count of the given weekday, `0` for monday to `6` for sunday, and hour, `0` to `23`
+    pub fn count(&self, weekday: usize, hour: usize) -> u32 {
+        if weekday >= Self::WEEKDAYS || hour >= Self::HOURS {
+            return 0;
+        }
+        self.counts[weekday * Self::HOURS + hour]
+    }
+
+    /// The highest count, to scale the colors of the heatmap
+    pub fn max(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Monday, 2022-11-14 00:00:00 UTC
+    const MONDAY: f64 = 1668384000.0;
+
+    #[test]
+    fn buckets_by_weekday_and_hour() {
+        let mut heatmap = Heatmap::new(0);
+        heatmap.add_all(vec![MONDAY + 30.0, MONDAY + 600.0, MONDAY + 6.0 * 86400.0 + 23.5 * 3600.0, f64::NAN]);
+        assert_eq!(heatmap.count(0, 0), 2);
+        assert_eq!(heatmap.count(6, 23), 1);
+        assert_eq!(heatmap.counts().iter().sum::<u32>(), 3);
+        assert_eq!(heatmap.max(), 2);
+        assert_eq!(heatmap.count(7, 0), 0);
+    }
+
+    #[test]
+    fn respects_utc_offset() {
+        let mut heatmap = Heatmap::new(60);
+        heatmap.add(MONDAY - 1800.0);
+        // sunday 23:30 UTC is monday 0:30 CET
+        assert_eq!(heatmap.count(0, 0), 1);
+    }
+}
@@ -0,0 +1,203 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use std::collections::VecDeque;
+
+use super::Series;
+
+/// The Latencies collect the durations of API requests on the client,
+/// so the ops dashboard receives percentiles and SLO compliance instead of raw samples.
+/// Only the most recent samples are kept, so the collection does not grow during long sessions.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Latencies {
+
+    /// The samples as pairs of the point in time and the duration, in order of recording
+    samples: VecDeque<(f64, f64)>
+}
+
+impl Latencies {
+
+    /// The maximum number of samples kept
+    const MAX_SAMPLES: usize = 10_000;
+
+    /// The durations sorted ascending
+    fn sorted(&self) -> Vec<f64> {
+        let mut durations: Vec<f64> = self.samples.iter().map(|(_, duration)| *duration).collect();
+        durations.sort_by(f64::total_cmp);
+        durations
+    }
+}
+
+#[wasm_bindgen]
+impl Latencies {
+
+    /// Create an empty collection
+    ///
+    /// # Example
+    /// ```rust
+    /// let mut latencies = Latencies::new();
+    /// latencies.record(Date.now(), 120.0);
+    /// let p95 = latencies.percentile(95.0);
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Latencies {
+        Latencies::default()
+    }
+
+    /// Record the duration of a request, dropping the oldest sample if [`Latencies::MAX_SAMPLES`] are recorded
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The point in time the request was sent at, e.g. in milliseconds since the unix epoch
+    /// * `duration` - The duration of the request, e.g. in milliseconds. Values which are not finite are ignored
+    pub fn record(&mut self, time: f64, duration: f64) {
+        if time.is_finite() && duration.is_finite() {
+            if self.samples.len() == Self::MAX_SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back((time, duration));
+        }
+    }
+
+    /// The number of recorded samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether no sample was recorded
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The duration below which the given percentage of the samples lie,
+    /// interpolated linearly between the closest samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `percentile` - The percentage between `0` and `100`, e.g. `95` for the p95. Clamped to this range
+    ///
+    /// # Returns
+    ///
+    /// * `number` - The duration
+    /// * `undefined` - If no sample was recorded
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        let durations = self.sorted();
+        if durations.is_empty() {
+            return None;
+        }
+        let rank = percentile.clamp(0.0, 100.0) / 100.0 * (durations.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        Some(durations[lower] + (durations[upper] - durations[lower]) * (rank - lower as f64))
+    }
+
+    /// The p50, p95 and p99 durations, in this order, or an empty array if no sample was recorded
+    pub fn summary(&self) -> Vec<f64> {
+        [50.0, 95.0, 99.0].iter()
+            .filter_map(|percentile| self.percentile(*percentile))
+            .collect()
+    }
+
+    /// The share of the samples which meet the given objective
+    ///
+    /// # Arguments
+    ///
+    /// * `objective` - The maximum duration a request may take
+    ///
+    /// # Returns
+    ///
+    /// * `number` - The share between `0` and `1`, `1` if no sample was recorded
+    pub fn compliance(&self, objective: f64) -> f64 {
+        if self.samples.is_empty() {
+            return 1.0;
+        }
+        let met = self.samples.iter().filter(|(_, duration)| *duration <= objective).count();
+        met as f64 / self.samples.len() as f64
+    }
+
+    /// The share of the samples which meet the given objective, per window of time.
+    /// Windows without samples are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The length of a window, in the unit of the recorded points in time
+    /// * `objective` - The maximum duration a request may take
+    ///
+    /// # Returns
+    ///
+    /// * [`Series`] - The start of each window and the share of compliant samples within it
+    pub fn compliance_windows(&self, window: f64, objective: f64) -> Series {
+        let mut windows: Vec<(f64, usize, usize)> = Vec::new();
+        if window > 0.0 {
+            let mut samples = Vec::from(self.samples.clone());
+            samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (time, duration) in samples {
+                let start = (time / window).floor() * window;
+                match windows.last_mut() {
+                    Some(last) if last.0 == start => {},
+                    _ => windows.push((start, 0, 0))
+                }
+                if let Some(last) = windows.last_mut() {
+                    last.1 += usize::from(duration <= objective);
+                    last.2 += 1;
+                }
+            }
+        }
+        Series {
+            xs: windows.iter().map(|(start, _, _)| *start).collect(),
+            ys: windows.iter().map(|(_, met, total)| *met as f64 / *total as f64).collect()
+        }
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn latencies(durations: &[f64]) -> Latencies {
+        let mut latencies = Latencies::new();
+        for (time, duration) in durations.iter().enumerate() {
+            latencies.record(time as f64 * 1000.0, *duration);
+        }
+        latencies
+    }
+
+    #[test]
+    fn computes_percentiles() {
+        let latencies = latencies(&[40.0, 10.0, 30.0, 20.0, 50.0]);
+        assert_eq!(latencies.percentile(50.0), Some(30.0));
+        assert_eq!(latencies.percentile(0.0), Some(10.0));
+        assert_eq!(latencies.percentile(100.0), Some(50.0));
+        assert_eq!(latencies.percentile(95.0), Some(48.0));
+        assert_eq!(Latencies::new().percentile(50.0), None);
+        assert!(Latencies::new().summary().is_empty());
+    }
+
+    #[test]
+    fn computes_compliance() {
+        let latencies = latencies(&[100.0, 200.0, 300.0, f64::NAN, 400.0]);
+        assert_eq!(latencies.len(), 4);
+        assert_eq!(latencies.compliance(250.0), 0.5);
+
+        let windows = latencies.compliance_windows(2000.0, 250.0);
+        assert_eq!(windows.xs(), vec![0.0, 2000.0, 4000.0]);
+        assert_eq!(windows.ys(), vec![1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn keeps_recent_samples() {
+        let mut latencies = Latencies::new();
+        for time in 0..Latencies::MAX_SAMPLES + 1 {
+            latencies.record(time as f64, time as f64);
+        }
+        assert_eq!(latencies.len(), Latencies::MAX_SAMPLES);
+        assert_eq!(latencies.percentile(0.0), Some(1.0));
+    }
+}
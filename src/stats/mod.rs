@@ -0,0 +1,292 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+mod latency;
+pub use latency::Latencies;
+
+mod heatmap;
+pub use heatmap::Heatmap;
+
+use wasm_bindgen::prelude::*;
+
+/// The Series is a time series as returned by the stats endpoints, e.g. the suggestions per minute.
+/// It prepares the series for the charts, which cannot render thousands of points smoothly.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+
+    /// The points in time, ascending
+    xs: Vec<f64>,
+
+    /// The values at the points in time
+    ys: Vec<f64>
+}
+
+impl Series {
+
+    /// The indices of the points kept by [Largest-Triangle-Three-Buckets](https://skemman.is/handle/1946/15343)
+    /// downsampling to the given number of points.
+    /// The first and last point are always kept, in between the point of each bucket is kept
+    /// which forms the largest triangle with the previously kept point and the average of the next bucket.
+    fn lttb(&self, target: usize) -> Vec<usize> {
+        let length = self.xs.len();
+        if target >= length || target < 3 {
+            return (0..length).collect();
+        }
+
+        let bucket = (length - 2) as f64 / (target - 2) as f64;
+        let mut kept = Vec::with_capacity(target);
+        kept.push(0);
+        let mut previous = 0;
+
+        for i in 0..target - 2 {
+            let next_start = ((i + 1) as f64 * bucket) as usize + 1;
+            let next_end = (((i + 2) as f64 * bucket) as usize + 1).min(length);
+            let count = (next_end - next_start) as f64;
+            let average_x = self.xs[next_start..next_end].iter().sum::<f64>() / count;
+            let average_y = self.ys[next_start..next_end].iter().sum::<f64>() / count;
+
+            let start = (i as f64 * bucket) as usize + 1;
+            let end = next_start;
+            let (x, y) = (self.xs[previous], self.ys[previous]);
+            let mut largest = start;
+            let mut largest_area = -1.0;
+            for candidate in start..end {
+                let area = ((x - average_x) * (self.ys[candidate] - y)
+                    - (x - self.xs[candidate]) * (average_y - y)).abs();
+                if area > largest_area {
+                    largest_area = area;
+                    largest = candidate;
+                }
+            }
+            kept.push(largest);
+            previous = largest;
+        }
+
+        kept.push(length - 1);
+        kept
+    }
+
+    /// The z-score of the value at the given index against the values at the given baseline indices,
+    /// `NaN` if the baseline has less than two values.
+    /// A value deviating from a constant baseline has an infinite z-score.
+    fn z_score(&self, index: usize, baseline: impl Iterator<Item = usize>) -> f64 {
+        let values: Vec<f64> = baseline.map(|i| self.ys[i]).collect();
+        if values.len() < 2 {
+            return f64::NAN;
+        }
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        let deviation = self.ys[index] - mean;
+        if variance == 0.0 {
+            return if deviation == 0.0 { 0.0 } else { deviation.signum() * f64::INFINITY };
+        }
+        deviation / variance.sqrt()
+    }
+
+    /// The indices of the values whose z-score exceeds the threshold in either direction
+    fn exceeding(scores: Vec<f64>, threshold: f64) -> Vec<u32> {
+        scores.iter().enumerate()
+            .filter(|(_, score)| score.abs() > threshold)
+            .map(|(i, _)| i as u32)
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+impl Series {
+
+    /// Create a series from the given points
+    ///
+    /// # Arguments
+    ///
+    /// * `xs` - The points in time, ascending
+    /// * `ys` - The values at the points in time
+    ///
+    /// # Example
+    /// ```rust
+    /// let series = Series::new(timestamps, counts)?;
+    /// let chart = series.downsample(500).smooth(5);
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(xs: Vec<f64>, ys: Vec<f64>) -> Result<Series, JsValue> {
+        if xs.len() != ys.len() {
+            return Err(JsValue::from_str("The series needs as many points in time as values!"));
+        }
+        Ok(Series {
+            xs,
+            ys
+        })
+    }
+
+    /// The points in time
+    pub fn xs(&self) -> Vec<f64> {
+        self.xs.clone()
+    }
+
+    /// The values at the points in time
+    pub fn ys(&self) -> Vec<f64> {
+        self.ys.clone()
+    }
+
+    /// The number of points
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    /// Whether the series has no points
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    /// Reduce the series to the given number of points while keeping its visual shape,
+    /// e.g. peaks, using Largest-Triangle-Three-Buckets downsampling.
+    /// Series with at most the given number of points, or a target below 3, are kept as is.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The number of points to keep
+    pub fn downsample(&self, target: usize) -> Series {
+        let kept = self.lttb(target);
+        Series {
+            xs: kept.iter().map(|i| self.xs[*i]).collect(),
+            ys: kept.iter().map(|i| self.ys[*i]).collect()
+        }
+    }
+
+    /// Smooth the series with a trailing moving average.
+    /// Each value is replaced by the average of itself and the up to `window - 1` preceding values.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of values to average, `0` and `1` keep the series as is
+    pub fn smooth(&self, window: usize) -> Series {
+        let window = window.max(1);
+        let mut sum = 0.0;
+        let ys = self.ys.iter().enumerate()
+            .map(|(i, y)| {
+                sum += y;
+                if i >= window {
+                    sum -= self.ys[i - window];
+                }
+                sum / (i + 1).min(window) as f64
+            })
+            .collect();
+        Series {
+            xs: self.xs.clone(),
+            ys
+        }
+    }
+
+    /// The z-score of each value against the `window` preceding values,
+    /// i.e. by how many standard deviations it differs from their mean.
+    /// The first values have no baseline and a z-score of `NaN`.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of preceding values forming the baseline
+    pub fn z_scores(&self, window: usize) -> Vec<f64> {
+        (0..self.ys.len())
+            .map(|i| self.z_score(i, i.saturating_sub(window)..i))
+            .collect()
+    }
+
+    /// The indices of the values which are anomalous compared to the `window` preceding values,
+    /// e.g. a sudden spike in reports, see [`Series::z_scores`].
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of preceding values forming the baseline
+    /// * `threshold` - The z-score a value has to exceed to be anomalous, commonly `3`
+    ///
+    /// # Example
+    /// ```rust
+    /// let series = Series::new(timestamps, reports)?;
+    /// for index in series.anomalies(60, 3.0) {
+    ///     // highlight the point at index
+    /// }
+    /// ```
+    pub fn anomalies(&self, window: usize, threshold: f64) -> Vec<u32> {
+        Series::exceeding(self.z_scores(window), threshold)
+    }
+
+    /// The indices of the values which are anomalous compared to the values at the same point
+    /// in the preceding periods, e.g. the same hour of the previous days.
+    /// This avoids flagging recurring peaks, like the daily rush in the evening.
+    ///
+    /// # Arguments
+    ///
+    /// * `period` - The number of values per period, e.g. `24` for hourly values and daily periods
+    /// * `seasons` - The number of preceding periods forming the baseline
+    /// * `threshold` - The z-score a value has to exceed to be anomalous
+    pub fn seasonal_anomalies(&self, period: usize, seasons: usize, threshold: f64) -> Vec<u32> {
+        let period = period.max(1);
+        let scores = (0..self.ys.len())
+            .map(|i| self.z_score(i, (1..=seasons).filter_map(|season| i.checked_sub(season * period))))
+            .collect();
+        Series::exceeding(scores, threshold)
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn series(ys: Vec<f64>) -> Series {
+        Series::new((0..ys.len()).map(|x| x as f64).collect(), ys).ok().unwrap()
+    }
+
+    #[test]
+    fn downsample_keeps_peaks() {
+        let mut ys = vec![1.0; 1000];
+        ys[500] = 100.0;
+        let downsampled = series(ys).downsample(50);
+        assert_eq!(downsampled.len(), 50);
+        assert_eq!(downsampled.xs()[0], 0.0);
+        assert_eq!(downsampled.xs()[49], 999.0);
+        assert!(downsampled.ys().contains(&100.0));
+    }
+
+    #[test]
+    fn downsample_keeps_short_series() {
+        let short = series(vec![1.0, 2.0, 3.0]);
+        assert_eq!(short.downsample(10), short);
+        assert_eq!(short.downsample(2), short);
+    }
+
+    #[test]
+    fn smooth_averages_trailing_window() {
+        let smoothed = series(vec![3.0, 6.0, 9.0, 0.0]).smooth(2);
+        assert_eq!(smoothed.ys(), vec![3.0, 4.5, 7.5, 4.5]);
+        assert_eq!(series(vec![1.0, 2.0]).smooth(0).ys(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn detects_spikes() {
+        let mut ys: Vec<f64> = (0..100).map(|i| 10.0 + (i % 3) as f64).collect();
+        ys[70] = 40.0;
+        let series = series(ys);
+        assert_eq!(series.anomalies(20, 3.0), vec![70]);
+        assert!(series.z_scores(20)[0].is_nan());
+    }
+
+    #[test]
+    fn constant_baseline() {
+        let series = series(vec![5.0, 5.0, 5.0, 6.0]);
+        assert_eq!(series.z_scores(3)[2], 0.0);
+        assert_eq!(series.anomalies(3, 3.0), vec![3]);
+    }
+
+    #[test]
+    fn ignores_recurring_peaks() {
+        let ys: Vec<f64> = (0..96).map(|i| if i % 24 == 18 { 50.0 } else { 10.0 + (i % 2) as f64 }).collect();
+        let series = series(ys);
+        assert!(!series.anomalies(12, 3.0).is_empty());
+        assert!(series.seasonal_anomalies(24, 3, 3.0).is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+
+/// Passwords which are guessed first by any attacker
+const COMMON_PASSWORDS: [&str; 20] = [
+    "password", "passwort", "123456", "12345678", "123456789", "qwerty", "qwertz",
+    "abc123", "letmein", "welcome", "admin", "administrator", "iloveyou", "monkey",
+    "dragon", "football", "master", "secret", "hallo", "geheim"
+];
+
+/// Keyboard rows, used to detect walks over adjacent keys
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertzuiop", "asdfghjkl", "yxcvbnm"];
+
+/// The minimum length of a repetition or sequence to be penalized
+const MIN_PATTERN_LENGTH: usize = 3;
+
+/// The Strength struct holds the estimated strength of a secret.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strength {
+
+    /// The score from `0` (trivially guessable) to `4` (very strong)
+    pub score: u8,
+
+    /// The estimated entropy in bits
+    pub entropy: f64,
+
+    /// Machine readable hints on how to improve the secret
+    hints: Vec<String>
+}
+
+#[wasm_bindgen]
+impl Strength {
+
+    /// Machine readable hints on how to improve the secret.
+    /// One of `too_short`, `common`, `user_input`, `repeated`, `sequence`, `more_character_classes`.
+    pub fn hints(&self) -> Vec<String> {
+        self.hints.clone()
+    }
+}
+
+/// Estimate the strength of the given secret.
+///
+/// # Arguments
+///
+/// * `secret` - The secret to estimate
+/// * `user_inputs` - Values related to the user, e.g. name or email, which must not be part of the secret
+///
+/// # Returns
+///
+/// * [`Strength`] - The estimated strength including hints for improvement
+///
+/// # Example
+/// ```rust
+/// let strength = estimate_strength("correct horse battery staple", vec![]);
+/// assert_eq!(strength.score, 4);
+/// ```
+#[wasm_bindgen]
+pub fn estimate_strength(secret: &str, user_inputs: Vec<String>) -> Strength {
+
+    let lower = secret.to_lowercase();
+    let chars: Vec<char> = lower.chars().collect();
+    let mut hints = Vec::new();
+
+    if COMMON_PASSWORDS.contains(&lower.as_str()) {
+        return Strength { score: 0, entropy: 0.0, hints: vec![String::from("common")] };
+    }
+
+    // Characters which are part of a pattern only add little entropy
+    let patterns = pattern_length(&chars, |a, b| a == b);
+    if patterns > 0 {
+        hints.push(String::from("repeated"));
+    }
+    let sequences = pattern_length(&chars, is_successor);
+    if sequences > 0 {
+        hints.push(String::from("sequence"));
+    }
+    let related = user_inputs.iter()
+        .map(|input| input.to_lowercase())
+        .filter(|input| input.chars().count() >= MIN_PATTERN_LENGTH && lower.contains(input.as_str()))
+        .map(|input| input.chars().count())
+        .max()
+        .unwrap_or(0);
+    if related > 0 {
+        hints.push(String::from("user_input"));
+    }
+
+    let effective_length = chars.len()
+        .saturating_sub(patterns + sequences + related);
+    let classes = character_classes(secret);
+    let entropy = effective_length as f64 * (pool_size(classes) as f64).log2();
+
+    if chars.len() < 12 {
+        hints.push(String::from("too_short"));
+    }
+    if classes < 3 && entropy < 60.0 {
+        hints.push(String::from("more_character_classes"));
+    }
+
+    let score = match entropy {
+        e if e < 28.0 => 0,
+        e if e < 36.0 => 1,
+        e if e < 60.0 => 2,
+        e if e < 80.0 => 3,
+        _ => 4
+    };
+
+    Strength { score, entropy, hints }
+}
+
+/// Whether `b` directly follows `a`, alphabetically, numerically or on the keyboard
+fn is_successor(a: char, b: char) -> bool {
+    let alphabetical = (a.is_ascii_alphanumeric() && b.is_ascii_alphanumeric())
+        && (b as u32 == a as u32 + 1 || a as u32 == b as u32 + 1);
+    let keyboard = KEYBOARD_ROWS.iter().any(|row| {
+        let keys: Vec<char> = row.chars().collect();
+        keys.windows(2).any(|pair| (pair[0] == a && pair[1] == b) || (pair[0] == b && pair[1] == a))
+    });
+    alphabetical || keyboard
+}
+
+/// Count the characters following the first character of each run of at least
+/// [`MIN_PATTERN_LENGTH`] characters where each character relates to its predecessor.
+fn pattern_length(chars: &[char], related: impl Fn(char, char) -> bool) -> usize {
+    let mut total = 0;
+    let mut run = 1;
+    for window in chars.windows(2) {
+        if related(window[0], window[1]) {
+            run += 1;
+        } else {
+            if run >= MIN_PATTERN_LENGTH {
+                total += run - 1;
+            }
+            run = 1;
+        }
+    }
+    if run >= MIN_PATTERN_LENGTH {
+        total += run - 1;
+    }
+    total
+}
+
+/// The bit set of character classes used in the secret
+fn character_classes(secret: &str) -> u8 {
+    secret.chars().fold(0, |classes, c| {
+        classes | match c {
+            'a'..='z' => 1,
+            'A'..='Z' => 2,
+            '0'..='9' => 4,
+            _ => 8
+        }
+    })
+}
+
+/// The amount of different characters an attacker has to try per position
+fn pool_size(classes: u8) -> u32 {
+    [(1, 26), (2, 26), (4, 10), (8, 33)].iter()
+        .filter(|(class, _)| classes & class != 0)
+        .map(|(_, size)| size)
+        .sum::<u32>()
+        .max(1)
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn common_passwords_are_weak() {
+        let strength = estimate_strength("Passwort", vec![]);
+        assert_eq!(strength.score, 0);
+        assert_eq!(strength.hints(), vec![String::from("common")]);
+    }
+
+    #[test]
+    fn patterns_reduce_strength() {
+        assert!(estimate_strength("aaaaaaaaaaaa", vec![]).score <= 1);
+        assert!(estimate_strength("abcdefghijkl", vec![]).score <= 1);
+        assert!(estimate_strength("asdfghjkl", vec![]).hints().contains(&String::from("sequence")));
+    }
+
+    #[test]
+    fn user_inputs_reduce_strength() {
+        let strength = estimate_strength("patrick2022", vec![String::from("Patrick")]);
+        assert!(strength.hints().contains(&String::from("user_input")));
+        assert!(strength.score <= 1);
+    }
+
+    #[test]
+    fn long_mixed_secrets_are_strong() {
+        assert_eq!(estimate_strength("correct horse battery staple", vec![]).score, 4);
+        assert_eq!(estimate_strength("T4k#9vL!qz2@Wm", vec![]).score, 4);
+    }
+}
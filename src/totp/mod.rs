@@ -0,0 +1,217 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use hmac::{
+    Hmac,
+    Mac
+};
+use sha1::Sha1;
+use openidconnect::url::Url;
+
+use crate::qr::render_svg;
+
+/// The TotpSecret holds a shared secret used to enroll an admin account in
+/// time-based one-time passwords (RFC 6238) as second factor.
+#[wasm_bindgen]
+pub struct TotpSecret {
+
+    /// The raw shared secret
+    secret: Vec<u8>,
+
+    /// The issuer shown in the authenticator app
+    issuer: String,
+
+    /// The account name shown in the authenticator app
+    account: String
+}
+
+impl TotpSecret {
+
+    const SECRET_LENGTH: usize = 20;
+    const DIGITS: u32 = 6;
+    const PERIOD: u64 = 30;
+
+    /// The amount of periods a code may be off to compensate clock drift
+    const ALLOWED_DRIFT: u64 = 1;
+
+    const BASE32_ALPHABET: &'static [u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    /// Create a new TotpSecret instance from the raw secret
+    pub fn new(secret: Vec<u8>, issuer: String, account: String) -> Self {
+        TotpSecret {
+            secret,
+            issuer,
+            account
+        }
+    }
+
+    /// Compute the code for the given counter (RFC 4226)
+    fn code_at(&self, counter: u64) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&counter.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]])
+            & 0x7fff_ffff;
+        format!("{:0width$}", binary % 10u32.pow(Self::DIGITS), width = Self::DIGITS as usize)
+    }
+
+    /// Verify the given code against the given point in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code entered by the user
+    /// * `unix_seconds` - The point in time in seconds since the unix epoch
+    pub fn verify_at(&self, code: &str, unix_seconds: u64) -> bool {
+        let counter = unix_seconds / Self::PERIOD;
+        (counter.saturating_sub(Self::ALLOWED_DRIFT)..=counter + Self::ALLOWED_DRIFT)
+            .any(|counter| self.code_at(counter) == code.trim())
+    }
+
+    /// Encode the given bytes in base32 (RFC 4648) without padding
+    fn base32(bytes: &[u8]) -> String {
+        let mut encoded = String::new();
+        let mut buffer: u32 = 0;
+        let mut bits = 0;
+        for byte in bytes {
+            buffer = (buffer << 8) | *byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(Self::BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            encoded.push(Self::BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+        encoded
+    }
+}
+
+#[wasm_bindgen]
+impl TotpSecret {
+
+    /// Generate a new random secret for the given account.
+    ///
+    /// # Arguments
+    ///
+    /// * `issuer` - The issuer shown in the authenticator app, e.g. the name of the panel
+    /// * `account` - The account to enroll, e.g. the email of the admin
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TotpSecret)` - The generated secret
+    /// * `Err(JsValue)` - If no randomness is available
+    ///
+    /// # Example
+    /// ```rust
+    /// let secret = TotpSecret::generate(String::from("Admin Panel"), String::from("admin@kit.edu"))?;
+    /// let uri: String = secret.uri();
+    /// ```
+    pub fn generate(issuer: String, account: String) -> Result<TotpSecret, JsValue> {
+        let mut secret = vec![0; Self::SECRET_LENGTH];
+        getrandom::getrandom(&mut secret).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        Ok(TotpSecret::new(secret, issuer, account))
+    }
+
+    /// The secret encoded in base32, for manual entry into an authenticator app
+    pub fn secret(&self) -> String {
+        Self::base32(&self.secret)
+    }
+
+    /// The `otpauth://` URI used to provision authenticator apps
+    ///
+    /// # Example
+    /// ```rust
+    /// let secret: TotpSecret;
+    /// // otpauth://totp/Admin%20Panel:admin@kit.edu?secret=...&issuer=Admin+Panel&...
+    /// let uri: String = secret.uri();
+    /// ```
+    pub fn uri(&self) -> String {
+        let mut uri = Url::parse("otpauth://totp").expect("The otpauth URI is valid");
+        uri.path_segments_mut()
+            .expect("The otpauth URI has a host")
+            .push(&format!("{}:{}", self.issuer, self.account));
+        uri.query_pairs_mut()
+            .append_pair("secret", &self.secret())
+            .append_pair("issuer", &self.issuer)
+            .append_pair("algorithm", "SHA1")
+            .append_pair("digits", &Self::DIGITS.to_string())
+            .append_pair("period", &Self::PERIOD.to_string());
+        uri.to_string()
+    }
+
+    /// The provisioning URI rendered as QR code in SVG format
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The minimal width and height of the image in pixels
+    pub fn qr_code_svg(&self, size: u32) -> Result<String, JsValue> {
+        render_svg(&self.uri(), size).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Verify the first code entered by the user against the current time,
+    /// to confirm the authenticator app was set up correctly.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code entered by the user
+    ///
+    /// # Returns
+    ///
+    /// * `true` - If the code is valid now
+    /// * `false` - Otherwise
+    pub fn verify(&self, code: &str) -> bool {
+        let now = (js_sys::Date::now() / 1000.0) as u64;
+        self.verify_at(code, now)
+    }
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::new(
+            b"12345678901234567890".to_vec(),
+            String::from("Admin Panel"),
+            String::from("admin@kit.edu")
+        )
+    }
+
+    #[test]
+    fn rfc_6238_test_vectors() {
+        let secret = rfc_secret();
+        assert!(secret.verify_at("287082", 59));
+        assert!(secret.verify_at("081804", 1111111109));
+        assert!(!secret.verify_at("000000", 59));
+    }
+
+    #[test]
+    fn accepts_drift_of_one_period() {
+        let secret = rfc_secret();
+        assert!(secret.verify_at("287082", 59 + 30));
+        assert!(!secret.verify_at("287082", 59 + 60));
+    }
+
+    #[test]
+    fn base32_encoding() {
+        assert_eq!(TotpSecret::base32(b"12345678901234567890"), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(TotpSecret::base32(b"f"), "MY");
+    }
+
+    #[test]
+    fn provisioning_uri() {
+        let uri = rfc_secret().uri();
+        assert!(uri.starts_with("otpauth://totp/Admin%20Panel:admin@kit.edu?"));
+        assert!(uri.contains("secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"));
+    }
+}
@@ -9,3 +9,16 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+/// The version of this crate, including the git revision it was built from if known.
+/// 
+/// # Example
+/// ```rust
+/// // e.g. "0.1.0+c18e940"
+/// let version: String = version();
+/// ```
+pub fn version() -> String {
+    match option_env!("KIFAPWA_GIT_HASH") {
+        Some(hash) => format!("{}+{}", env!("CARGO_PKG_VERSION"), hash),
+        None => String::from(env!("CARGO_PKG_VERSION"))
+    }
+}
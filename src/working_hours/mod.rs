@@ -0,0 +1,222 @@
+//! SPDX-License-Identifier: MIT
+//! SPDX-License-Identifier: APACHE
+//!
+//! 2022, Patrick Schneider <patrick@itermori.de>
+
+use wasm_bindgen::prelude::*;
+use chrono::{
+    Datelike,
+    Duration,
+    NaiveDate
+};
+use std::collections::BTreeSet;
+
+/// The WorkingHours describe when the team is available,
+/// e.g. to schedule maintenance announcements or publish decisions at a time someone can react to them.
+/// A working day is one of the configured weekdays which is not a holiday.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkingHours {
+
+    /// The start of the working hours in minutes after midnight
+    start: u32,
+
+    /// The end of the working hours in minutes after midnight, exclusive
+    end: u32,
+
+    /// The working weekdays, `0` for monday to `6` for sunday
+    weekdays: BTreeSet<u32>,
+
+    /// The days off, in local time
+    holidays: BTreeSet<NaiveDate>,
+
+    /// The offset of the local time to UTC in minutes
+    utc_offset: i32
+}
+
+impl WorkingHours {
+
+    /// The number of days to look ahead for the next working day, covering a year of holidays
+    const LOOKAHEAD_DAYS: i64 = 400;
+
+    const SECONDS_PER_DAY: i64 = 86400;
+
+    /// Whether the given point in time lies within the working hours
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The point in time in seconds since the unix epoch
+    pub fn is_working_time_at(&self, now: u64) -> bool {
+        self.next_slot_at(now) == Some(now)
+    }
+
+    /// The next point in time within the working hours, starting at the given one.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The point in time in seconds since the unix epoch
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u64)` - `now` if it lies within the working hours, else the start of the next working hours
+    /// * `None` - If there are no working hours within the next year
+    pub fn next_slot_at(&self, now: u64) -> Option<u64> {
+        if self.start >= self.end {
+            return None;
+        }
+        let local = now as i64 + i64::from(self.utc_offset) * 60;
+        let second_of_day = local.rem_euclid(Self::SECONDS_PER_DAY);
+        let today = NaiveDate::from_ymd_opt(1970, 1, 1)?
+            .checked_add_signed(Duration::days(local.div_euclid(Self::SECONDS_PER_DAY)))?;
+        let start = i64::from(self.start) * 60;
+        let end = i64::from(self.end) * 60;
+
+        for offset in 0..=Self::LOOKAHEAD_DAYS {
+            let day = today.checked_add_signed(Duration::days(offset))?;
+            if !self.is_working_day(day) {
+                continue;
+            }
+            if offset == 0 && second_of_day >= end {
+                continue;
+            }
+            if offset == 0 && second_of_day >= start {
+                return Some(now);
+            }
+            let slot = local - second_of_day + offset * Self::SECONDS_PER_DAY + start
+                - i64::from(self.utc_offset) * 60;
+            return u64::try_from(slot).ok();
+        }
+        None
+    }
+
+    /// Whether the given day is a working day
+    fn is_working_day(&self, day: NaiveDate) -> bool {
+        self.weekdays.contains(&day.weekday().num_days_from_monday()) && !self.holidays.contains(&day)
+    }
+}
+
+#[wasm_bindgen]
+impl WorkingHours {
+
+    /// Create working hours on monday to friday, in UTC and without holidays
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the working hours in minutes after midnight
+    /// * `end` - The end of the working hours in minutes after midnight, exclusive
+    ///
+    /// # Example
+    /// ```rust
+    /// let hours = WorkingHours::new(9 * 60, 17 * 60)
+    ///     .with_utc_offset(60)
+    ///     .with_holidays(vec![String::from("2022-12-24")])?;
+    /// let publish_at = hours.next_slot();
+    /// ```
+    #[wasm_bindgen(constructor)]
+    pub fn new(start: u32, end: u32) -> WorkingHours {
+        WorkingHours {
+            start,
+            end,
+            weekdays: (0..5).collect(),
+            holidays: BTreeSet::new(),
+            utc_offset: 0
+        }
+    }
+
+    /// Set the working weekdays.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `weekdays` - The working weekdays, `0` for monday to `6` for sunday. Other values are ignored
+    pub fn with_weekdays(mut self, weekdays: Vec<u32>) -> WorkingHours {
+        self.weekdays = weekdays.into_iter().filter(|weekday| *weekday < 7).collect();
+        self
+    }
+
+    /// Set the days off, e.g. the public holidays taken from the configuration.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `holidays` - The days off in local time, formatted as `YYYY-MM-DD`
+    pub fn with_holidays(mut self, holidays: Vec<String>) -> Result<WorkingHours, JsValue> {
+        for holiday in holidays {
+            match NaiveDate::parse_from_str(&holiday, "%Y-%m-%d") {
+                Ok(day) => self.holidays.insert(day),
+                Err(_) => return Err(JsValue::from_str(&format!("The holiday {} is not a valid date!", holiday)))
+            };
+        }
+        Ok(self)
+    }
+
+    /// Set the offset of the local time the working hours are given in to UTC.
+    /// Consumes this instance!
+    ///
+    /// # Arguments
+    ///
+    /// * `utc_offset` - The offset in minutes, e.g. `60` for CET
+    pub fn with_utc_offset(mut self, utc_offset: i32) -> WorkingHours {
+        self.utc_offset = utc_offset;
+        self
+    }
+
+    /// Whether it is working time now
+    pub fn is_working_time(&self) -> bool {
+        self.is_working_time_at(now())
+    }
+
+    /// The next point in time within the working hours in seconds since the unix epoch,
+    /// now if it is working time, or `undefined` if there are no working hours within the next year
+    pub fn next_slot(&self) -> Option<u64> {
+        self.next_slot_at(now())
+    }
+}
+
+/// The current time in seconds since the unix epoch
+fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+// ********************** Unit Tests *************************
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Monday, 2022-11-14 00:00:00 UTC
+    const MONDAY: u64 = 1668384000;
+    const HOUR: u64 = 3600;
+
+    #[test]
+    fn within_working_hours() {
+        let hours = WorkingHours::new(9 * 60, 17 * 60);
+        assert!(hours.is_working_time_at(MONDAY + 10 * HOUR));
+        assert!(!hours.is_working_time_at(MONDAY + 17 * HOUR));
+        assert_eq!(hours.next_slot_at(MONDAY + 8 * HOUR), Some(MONDAY + 9 * HOUR));
+        assert_eq!(hours.next_slot_at(MONDAY + 18 * HOUR), Some(MONDAY + 33 * HOUR));
+    }
+
+    #[test]
+    fn skips_weekends_and_holidays() {
+        let hours = WorkingHours::new(9 * 60, 17 * 60)
+            .with_holidays(vec![String::from("2022-11-21")])
+            .ok().unwrap();
+        // friday evening to tuesday morning, as monday is a holiday
+        assert_eq!(hours.next_slot_at(MONDAY + 4 * 24 * HOUR + 18 * HOUR), Some(MONDAY + 8 * 24 * HOUR + 9 * HOUR));
+    }
+
+    #[test]
+    fn respects_utc_offset() {
+        let hours = WorkingHours::new(9 * 60, 17 * 60).with_utc_offset(60);
+        assert_eq!(hours.next_slot_at(MONDAY), Some(MONDAY + 8 * HOUR));
+        assert!(!hours.is_working_time_at(MONDAY + 16 * HOUR));
+    }
+
+    #[test]
+    fn no_working_days() {
+        let hours = WorkingHours::new(9 * 60, 17 * 60).with_weekdays(vec![7]);
+        assert_eq!(hours.next_slot_at(MONDAY), None);
+    }
+}
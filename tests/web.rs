@@ -4,6 +4,10 @@
 
 extern crate wasm_bindgen_test;
 use wasm_bindgen_test::*;
+use kifapwa::{
+    ClientData,
+    Framework
+};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -11,3 +15,26 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+fn client_data(auth_url: &str) -> ClientData {
+    ClientData::from(
+        String::from(auth_url),
+        String::from("https://auth_provider.org/token"),
+        String::from("my-client-id"),
+        String::from("https://my.site")
+    ).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn frameworks_do_not_share_storage() {
+    let storage = web_sys::window().unwrap().session_storage().unwrap().unwrap();
+    storage.clear().unwrap();
+
+    let mut first = Framework::new(client_data("https://first.org/auth"), storage.clone());
+    let mut second = Framework::new(client_data("https://second.org/auth"), storage.clone());
+    first.initiate_authentication(None, None).unwrap();
+    second.initiate_authentication(None, None).unwrap();
+
+    // Both instances keep their own verifier, csrf token, nonce, creation time and return url
+    assert_eq!(storage.length().unwrap(), 10);
+}